@@ -6,7 +6,7 @@ use rattler_conda_types::{
     version_spec::{EqualityOperator, LogicalOperator, RangeOperator},
     ChannelConfig, NamedChannelOrUrl, Version, VersionBumpType, VersionSpec,
 };
-use rattler_repodata_gateway::{Gateway, SourceConfig};
+use rattler_repodata_gateway::{fetch::CacheAction, Gateway, SourceConfig};
 use reqwest_middleware::ClientWithMiddleware;
 use serde::{de::IntoDeserializer, Deserialize, Serialize};
 use std::{
@@ -113,6 +113,10 @@ pub struct ConfigCli {
     /// Max concurrent network requests, default is 50
     #[arg(long)]
     pub concurrent_downloads: Option<usize>,
+
+    /// Only use cached repodata, packages and wheels, never access the network
+    #[arg(long, env = "PIXI_OFFLINE")]
+    pub offline: bool,
 }
 
 #[derive(Parser, Debug, Clone, Default)]
@@ -177,8 +181,9 @@ impl RepodataConfig {
 
 #[derive(Parser, Debug, Default, Clone)]
 pub struct ConfigCliActivation {
-    /// Do not use the environment activation cache. (default: true except in experimental mode)
-    #[arg(long)]
+    /// Do not use the environment activation cache, the cache is only used when
+    /// `experimental.use-environment-activation-cache` is enabled
+    #[arg(long, visible_alias = "no-activation-cache")]
     force_activate: bool,
 }
 
@@ -619,6 +624,12 @@ pub struct Config {
     #[serde(default)]
     #[serde(skip_serializing_if = "ConcurrencyConfig::is_default")]
     pub concurrency: ConcurrencyConfig,
+
+    /// If set to true, pixi only uses cached repodata, packages and wheels and
+    /// never accesses the network.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
 }
 
 impl Default for Config {
@@ -638,6 +649,7 @@ impl Default for Config {
             force_activate: None,
             experimental: ExperimentalConfig::default(),
             concurrency: ConcurrencyConfig::default(),
+            offline: None,
         }
     }
 }
@@ -660,6 +672,7 @@ impl From<ConfigCli> for Config {
                     .concurrent_downloads
                     .unwrap_or(ConcurrencyConfig::default().downloads),
             },
+            offline: if cli.offline { Some(true) } else { None },
             ..Default::default()
         }
     }
@@ -674,15 +687,27 @@ impl From<Config> for rattler_repodata_gateway::ChannelConfig {
 impl From<&Config> for rattler_repodata_gateway::ChannelConfig {
     fn from(config: &Config) -> Self {
         let repodata_config = &config.repodata_config;
-        let default = repodata_config.default.clone().into();
 
+        // In offline mode the repodata is only read from the cache, regardless
+        // of whether it is stale.
+        let cache_action = if config.offline() {
+            CacheAction::ForceCacheOnly
+        } else {
+            CacheAction::default()
+        };
+        let source_config = |config: RepodataChannelConfig| SourceConfig {
+            cache_action,
+            ..config.into()
+        };
+
+        let default = source_config(repodata_config.default.clone());
         let per_channel = repodata_config
             .per_channel
             .iter()
             .map(|(url, config)| {
                 (
                     url.clone(),
-                    config.merge(repodata_config.default.clone()).into(),
+                    source_config(config.merge(repodata_config.default.clone())),
                 )
             })
             .collect();
@@ -905,6 +930,7 @@ impl Config {
             "pypi-config.extra-index-urls",
             "pypi-config.keyring-provider",
             "experimental.use-environment-activation-cache",
+            "offline",
         ]
     }
 
@@ -937,6 +963,7 @@ impl Config {
             experimental: other.experimental.merge(self.experimental),
             // Make other take precedence over self to allow for setting the value through the CLI
             concurrency: self.concurrency.merge(other.concurrency),
+            offline: other.offline.or(self.offline),
         }
     }
 
@@ -1012,6 +1039,11 @@ impl Config {
         self.concurrency.downloads
     }
 
+    /// Retrieve the value for the offline field (defaults to false).
+    pub fn offline(&self) -> bool {
+        self.offline.unwrap_or(false)
+    }
+
     /// Modify this config with the given key and value
     ///
     /// # Note
@@ -1043,6 +1075,9 @@ impl Config {
             "tls-no-verify" => {
                 self.tls_no_verify = value.map(|v| v.parse()).transpose().into_diagnostic()?;
             }
+            "offline" => {
+                self.offline = value.map(|v| v.parse()).transpose().into_diagnostic()?;
+            }
             "mirrors" => {
                 self.mirrors = value
                     .map(|v| serde_json::de::from_str(&v))
@@ -1315,6 +1350,7 @@ UNUSED = "unused"
             pypi_keyring_provider: Some(KeyringProvider::Subprocess),
             concurrent_solves: None,
             concurrent_downloads: None,
+            offline: false,
         };
         let config = Config::from(cli);
         assert_eq!(config.tls_no_verify, Some(true));
@@ -1329,6 +1365,7 @@ UNUSED = "unused"
             pypi_keyring_provider: None,
             concurrent_solves: None,
             concurrent_downloads: None,
+            offline: false,
         };
 
         let config = Config::from(cli);
@@ -1338,6 +1375,19 @@ UNUSED = "unused"
             Some(PathBuf::from("path.json"))
         );
         assert!(!config.experimental.use_environment_activation_cache());
+        assert!(!config.offline());
+
+        let cli = ConfigCli {
+            offline: true,
+            ..ConfigCli::default()
+        };
+        let config = Config::default().merge_config(Config::from(cli));
+        assert!(config.offline());
+        let channel_config = rattler_repodata_gateway::ChannelConfig::from(&config);
+        assert_eq!(
+            channel_config.default.cache_action,
+            CacheAction::ForceCacheOnly
+        );
     }
 
     #[test]
@@ -1526,6 +1576,9 @@ UNUSED = "unused"
             .unwrap();
         assert_eq!(config.tls_no_verify, Some(true));
 
+        config.set("offline", Some("true".to_string())).unwrap();
+        assert!(config.offline());
+
         config
             .set(
                 "authentication-override-file",
@@ -22,6 +22,7 @@ pub const SOLVE_GROUP_ENVIRONMENTS_DIR: &str = "solve-group-envs";
 pub const PYPI_DEPENDENCIES: &str = "pypi-dependencies";
 pub const DEPENDENCIES: &str = "dependencies";
 pub const TASK_CACHE_DIR: &str = "task-cache-v0";
+pub const TASK_OUTPUT_STORE_DIR: &str = "outputs-v0";
 pub const ACTIVATION_ENV_CACHE_DIR: &str = "activation-env-v0";
 pub const PIXI_UV_INSTALLER: &str = "uv-pixi";
 pub const CONDA_PACKAGE_CACHE_DIR: &str = rattler_cache::PACKAGE_CACHE_DIR;
@@ -0,0 +1,216 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use thiserror::Error;
+
+use crate::glob_set::{self, GlobSet};
+
+/// The default interval at which the files are polled for changes.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The default duration during which no further changes must be observed
+/// before a change is reported.
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The files that matched the glob patterns together with their modification
+/// time.
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// Watches the files that match a set of glob patterns for changes.
+///
+/// Changes are detected by periodically polling the modification times of the
+/// matching files, so files that are added, removed or modified are all
+/// detected.
+#[derive(Debug)]
+pub struct GlobWatcher {
+    root_dir: PathBuf,
+    globs: Vec<String>,
+    snapshot: Snapshot,
+    poll_interval: Duration,
+    debounce: Duration,
+}
+
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum GlobWatchError {
+    #[error(transparent)]
+    GlobSet(#[from] glob_set::GlobSetError),
+}
+
+impl GlobWatcher {
+    /// Starts watching the files in `root_dir` that match the given glob
+    /// patterns, patterns that start with `!` exclude the files they match.
+    /// Changes are reported relative to the state of the files at the moment
+    /// this function is called.
+    pub fn new<'a>(
+        root_dir: &Path,
+        globs: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, GlobWatchError> {
+        let mut watcher = Self {
+            root_dir: root_dir.to_path_buf(),
+            globs: globs.into_iter().map(ToOwned::to_owned).collect(),
+            snapshot: Snapshot::new(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            debounce: DEFAULT_DEBOUNCE,
+        };
+        watcher.snapshot = take_snapshot(&watcher.root_dir, &watcher.globs)?;
+        Ok(watcher)
+    }
+
+    /// Sets the interval at which the files are polled for changes.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Sets the duration during which no further changes must be observed
+    /// before a change is reported.
+    pub fn with_debounce(self, debounce: Duration) -> Self {
+        Self { debounce, ..self }
+    }
+
+    /// Waits until one or more of the watched files changed and returns the
+    /// paths of the files that were added, removed or modified.
+    ///
+    /// A change is only reported once no further changes have been observed
+    /// for the debounce duration, so that a burst of changes, e.g. when an
+    /// editor saves multiple files, results in a single notification.
+    pub async fn changed(&mut self) -> Result<Vec<PathBuf>, GlobWatchError> {
+        // Wait for the first change.
+        let mut current = loop {
+            tokio::time::sleep(self.poll_interval).await;
+            let current = self.take_snapshot().await?;
+            if current != self.snapshot {
+                break current;
+            }
+        };
+
+        // Wait until the files settled down.
+        loop {
+            tokio::time::sleep(self.debounce).await;
+            let next = self.take_snapshot().await?;
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+
+        let previous = std::mem::replace(&mut self.snapshot, current);
+        Ok(diff_snapshots(&previous, &self.snapshot))
+    }
+
+    /// Collects the modification times of all the files that match the glob
+    /// patterns on a blocking thread, because walking the directory can take a
+    /// while.
+    async fn take_snapshot(&self) -> Result<Snapshot, GlobWatchError> {
+        let root_dir = self.root_dir.clone();
+        let globs = self.globs.clone();
+        tokio::task::spawn_blocking(move || take_snapshot(&root_dir, &globs))
+            .await
+            .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+}
+
+/// Collects the modification times of all the files in `root_dir` that match
+/// the glob patterns.
+fn take_snapshot(root_dir: &Path, globs: &[String]) -> Result<Snapshot, GlobWatchError> {
+    // If the root is not a directory or does not exist, nothing matches.
+    if !root_dir.is_dir() {
+        return Ok(Snapshot::new());
+    }
+
+    let glob_set = GlobSet::create(globs.iter().map(String::as_str))?;
+    let mut snapshot = Snapshot::new();
+    for entry in glob_set.filter_directory(root_dir) {
+        let entry = entry?;
+        // A file might be removed while we are walking the directory, in
+        // which case it is simply recorded without modification time.
+        let modified = entry
+            .metadata()
+            .ok()
+            .and_then(|metadata| metadata.modified().ok());
+        snapshot.insert(entry.path().to_owned(), modified);
+    }
+    Ok(snapshot)
+}
+
+/// Returns the paths that differ between two snapshots.
+fn diff_snapshots(previous: &Snapshot, current: &Snapshot) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = current
+        .iter()
+        .filter(|(path, modified)| previous.get(*path) != Some(*modified))
+        .map(|(path, _)| path.clone())
+        .collect();
+    changed.extend(
+        previous
+            .keys()
+            .filter(|path| !current.contains_key(*path))
+            .cloned(),
+    );
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_glob_watcher() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path();
+
+        let old = SystemTime::now() - Duration::from_secs(120);
+        File::create(dir_path.join("a.txt"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        File::create(dir_path.join("b.md")).unwrap();
+
+        let mut watcher = GlobWatcher::new(dir_path, ["*.txt"])
+            .unwrap()
+            .with_poll_interval(Duration::from_millis(10))
+            .with_debounce(Duration::from_millis(10));
+
+        // Modify a watched file and add a new one.
+        File::create(dir_path.join("a.txt"))
+            .unwrap()
+            .set_modified(SystemTime::now())
+            .unwrap();
+        File::create(dir_path.join("c.txt")).unwrap();
+        let changed = watcher.changed().await.unwrap();
+        assert_eq!(changed, [dir_path.join("a.txt"), dir_path.join("c.txt")]);
+
+        // Removing a file is a change as well.
+        fs_err::remove_file(dir_path.join("c.txt")).unwrap();
+        let changed = watcher.changed().await.unwrap();
+        assert_eq!(changed, [dir_path.join("c.txt")]);
+    }
+
+    #[tokio::test]
+    async fn test_glob_watcher_excludes() {
+        let temp_dir = tempdir().unwrap();
+        let dir_path = temp_dir.path();
+        fs_err::create_dir(dir_path.join("build")).unwrap();
+
+        let mut watcher = GlobWatcher::new(dir_path, ["**/*.txt", "!build/**"])
+            .unwrap()
+            .with_poll_interval(Duration::from_millis(10))
+            .with_debounce(Duration::from_millis(10));
+
+        // Changes to excluded files are not reported.
+        File::create(dir_path.join("build/out.txt")).unwrap();
+        File::create(dir_path.join("a.txt")).unwrap();
+        let changed = watcher.changed().await.unwrap();
+        assert_eq!(changed, [dir_path.join("a.txt")]);
+    }
+}
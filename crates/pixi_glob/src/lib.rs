@@ -6,7 +6,9 @@ mod glob_hash;
 mod glob_hash_cache;
 mod glob_mtime;
 mod glob_set;
+mod glob_watch;
 
 pub use glob_hash::{GlobHash, GlobHashError};
 pub use glob_hash_cache::{GlobHashCache, GlobHashKey};
 pub use glob_mtime::{GlobModificationTime, GlobModificationTimeError};
+pub use glob_watch::{GlobWatchError, GlobWatcher};
@@ -7,4 +7,8 @@ pub struct Activation {
     pub scripts: Option<Vec<String>>,
     /// Environment variables to set before running the scripts.
     pub env: Option<IndexMap<String, String>>,
+    /// Dotenv files, relative to the project root, to load the environment
+    /// variables from. Later files take precedence over earlier ones.
+    #[serde(rename = "env-file")]
+    pub env_file: Option<Vec<String>>,
 }
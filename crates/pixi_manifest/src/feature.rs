@@ -340,6 +340,19 @@ impl Feature {
             .next()
     }
 
+    /// Returns the activation env-files for the most specific target that
+    /// matches the given `platform`.
+    ///
+    /// Returns `None` if this feature does not define any target with
+    /// activation env-files.
+    pub fn activation_env_files(&self, platform: Option<Platform>) -> Option<&Vec<String>> {
+        self.targets
+            .resolve(platform)
+            .filter_map(|t| t.activation.as_ref())
+            .filter_map(|a| a.env_file.as_ref())
+            .next()
+    }
+
     /// Returns the activation environment for the most specific target that
     /// matches the given `platform`.
     ///
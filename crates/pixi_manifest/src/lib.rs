@@ -14,6 +14,7 @@ mod package;
 mod preview;
 pub mod pypi;
 pub mod pyproject;
+pub mod python_import;
 mod solve_group;
 mod spec_type;
 mod system_requirements;
@@ -359,7 +359,7 @@ impl PyProjectManifest {
 /// Try to return a NamelessMatchSpec from a pep508_rs::VersionOrUrl
 /// This will only work if it is not URL and the VersionSpecifier can
 /// successfully be interpreted as a NamelessMatchSpec.version
-fn version_or_url_to_spec(
+pub(crate) fn version_or_url_to_spec(
     version: &Option<VersionSpecifiers>,
 ) -> Result<PixiSpec, RequirementConversionError> {
     match version {
@@ -0,0 +1,581 @@
+//! Imports the dependencies of projects managed by other python project
+//! managers (poetry, pipenv and uv) so they can be used to bootstrap a pixi
+//! manifest.
+
+mod pipenv;
+mod poetry;
+mod uv;
+
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use pep440_rs::VersionSpecifiers;
+use pep508_rs::{ExtraName, Requirement};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, Value};
+use url::Url;
+
+use crate::{
+    pypi::{
+        pypi_options::{FindLinksUrlOrPath, PypiOptions},
+        pypi_requirement::ParsedGitUrl,
+        GitRev, PyPiPackageName, VersionOrStar,
+    },
+    pyproject::version_or_url_to_spec,
+    PyPiRequirement,
+};
+
+/// The pypi dependencies of a single group.
+pub type PypiDependencies = IndexMap<PyPiPackageName, PyPiRequirement>;
+
+/// The python project manager a project was imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PythonProjectKind {
+    Poetry,
+    Pipenv,
+    Uv,
+}
+
+impl Display for PythonProjectKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PythonProjectKind::Poetry => write!(f, "poetry"),
+            PythonProjectKind::Pipenv => write!(f, "pipenv"),
+            PythonProjectKind::Uv => write!(f, "uv"),
+        }
+    }
+}
+
+/// The dependencies, groups and indexes of an imported python project.
+#[derive(Debug, Default)]
+pub struct ImportedPythonProject {
+    /// The name of the project, if defined.
+    pub name: Option<String>,
+
+    /// The python version constraint of the project.
+    pub requires_python: Option<VersionSpecifiers>,
+
+    /// The dependencies of the project.
+    pub dependencies: PypiDependencies,
+
+    /// The dependency groups, extras and dev-dependencies of the project. Each
+    /// group becomes a feature and an environment.
+    pub groups: IndexMap<String, PypiDependencies>,
+
+    /// The indexes the dependencies are resolved from.
+    pub pypi_options: PypiOptions,
+}
+
+impl ImportedPythonProject {
+    /// Imports the python project from the file at `path`, this can either be
+    /// the manifest or the lock file of the project.
+    ///
+    /// Relative paths are rewritten to be relative to `project_dir`, the
+    /// directory the pixi manifest is created in. Returns `None` if the file
+    /// does not belong to a poetry, pipenv or uv project.
+    pub fn from_path(
+        path: &Path,
+        project_dir: &Path,
+    ) -> miette::Result<Option<(PythonProjectKind, Self)>> {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            return Ok(None);
+        };
+        let import_dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let (kind, manifest_path) = match file_name {
+            "Pipfile" | "Pipfile.lock" => (PythonProjectKind::Pipenv, import_dir.join("Pipfile")),
+            "poetry.lock" => (PythonProjectKind::Poetry, import_dir.join("pyproject.toml")),
+            "uv.lock" => (PythonProjectKind::Uv, import_dir.join("pyproject.toml")),
+            "pyproject.toml" => {
+                let source = read_source(path)?;
+                let document = DocumentMut::from_str(&source)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to parse '{}'", path.display()))?;
+                let kind = if document
+                    .get("tool")
+                    .and_then(|tool| tool.get("poetry"))
+                    .is_some()
+                {
+                    PythonProjectKind::Poetry
+                } else if document.contains_key("project")
+                    || document
+                        .get("tool")
+                        .and_then(|tool| tool.get("uv"))
+                        .is_some()
+                {
+                    PythonProjectKind::Uv
+                } else {
+                    miette::bail!(
+                        "'{}' does not contain a [project], [tool.poetry] or [tool.uv] table",
+                        path.display()
+                    );
+                };
+                (kind, path.to_path_buf())
+            }
+            _ => return Ok(None),
+        };
+
+        let source = read_source(&manifest_path)?;
+        let mut project = match kind {
+            PythonProjectKind::Poetry => poetry::import(&source),
+            PythonProjectKind::Pipenv => pipenv::import(&source),
+            PythonProjectKind::Uv => uv::import(&source),
+        }
+        .wrap_err_with(|| {
+            format!(
+                "failed to import the {kind} project from '{}'",
+                manifest_path.display()
+            )
+        })?;
+
+        // Paths are relative to the imported project.
+        if !is_same_dir(import_dir, project_dir) {
+            project.rebase_paths(import_dir);
+        }
+
+        Ok(Some((kind, project)))
+    }
+
+    /// Makes all relative paths absolute by joining them with `root`.
+    fn rebase_paths(&mut self, root: &Path) {
+        let root = dunce::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let rebase = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = root.join(&*path);
+            }
+        };
+        for requirement in self
+            .dependencies
+            .values_mut()
+            .chain(self.groups.values_mut().flat_map(|deps| deps.values_mut()))
+        {
+            if let PyPiRequirement::Path { path, .. } = requirement {
+                rebase(path);
+            }
+        }
+        for find_links in self.pypi_options.find_links.iter_mut().flatten() {
+            if let FindLinksUrlOrPath::Path(path) = find_links {
+                rebase(path);
+            }
+        }
+    }
+
+    /// Returns the name of the feature and environment of a group.
+    ///
+    /// Environment names can only contain lowercase letters, numbers and
+    /// dashes.
+    pub fn feature_name(group: &str) -> String {
+        group.to_lowercase().replace(['_', '.'], "-")
+    }
+
+    /// Adds the imported dependencies, features, environments and pypi
+    /// options to the tables of a `pixi.toml` manifest.
+    pub fn add_to_document(&self, document: &mut DocumentMut) -> miette::Result<()> {
+        // PyPI dependencies require a conda python interpreter.
+        let spec = version_or_url_to_spec(&self.requires_python).into_diagnostic()?;
+        get_or_insert_table(document.as_table_mut(), "dependencies")
+            .insert("python", Item::Value(spec.to_toml_value()));
+
+        if !self.dependencies.is_empty() {
+            let table = get_or_insert_table(document.as_table_mut(), "pypi-dependencies");
+            insert_dependencies(table, &self.dependencies);
+        }
+
+        if !self.groups.is_empty() {
+            let features = get_or_insert_table(document.as_table_mut(), "feature");
+            features.set_implicit(true);
+            for (group, dependencies) in &self.groups {
+                let feature = get_or_insert_table(features, &Self::feature_name(group));
+                feature.set_implicit(true);
+                insert_dependencies(
+                    get_or_insert_table(feature, "pypi-dependencies"),
+                    dependencies,
+                );
+            }
+
+            let environments = get_or_insert_table(document.as_table_mut(), "environments");
+            environments.insert(
+                "default",
+                Item::Value(Value::InlineTable(InlineTable::from_iter([(
+                    "solve-group",
+                    Value::from("default"),
+                )]))),
+            );
+            for group in self.groups.keys() {
+                let name = Self::feature_name(group);
+                environments.insert(
+                    &name,
+                    Item::Value(Value::InlineTable(InlineTable::from_iter([
+                        ("features", Value::Array(Array::from_iter([name.as_str()]))),
+                        ("solve-group", Value::from("default")),
+                    ]))),
+                );
+            }
+        }
+
+        let PypiOptions {
+            index_url,
+            extra_index_urls,
+            find_links,
+            ..
+        } = &self.pypi_options;
+        if index_url.is_some() || extra_index_urls.is_some() || find_links.is_some() {
+            let table = get_or_insert_table(document.as_table_mut(), "pypi-options");
+            if let Some(index_url) = index_url {
+                table.insert("index-url", Item::Value(Value::from(index_url.as_str())));
+            }
+            if let Some(extra_index_urls) = extra_index_urls {
+                table.insert(
+                    "extra-index-urls",
+                    Item::Value(Value::Array(Array::from_iter(
+                        extra_index_urls.iter().map(Url::as_str),
+                    ))),
+                );
+            }
+            if let Some(find_links) = find_links {
+                table.insert(
+                    "find-links",
+                    Item::Value(Value::Array(Array::from_iter(find_links.iter().map(
+                        |find_links| {
+                            let (key, value) = match find_links {
+                                FindLinksUrlOrPath::Url(url) => ("url", url.to_string()),
+                                FindLinksUrlOrPath::Path(path) => {
+                                    ("path", path.display().to_string())
+                                }
+                            };
+                            Value::InlineTable(InlineTable::from_iter([(key, Value::from(value))]))
+                        },
+                    )))),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the contents of a file of the project that is imported.
+fn read_source(path: &Path) -> miette::Result<String> {
+    fs_err::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read '{}'", path.display()))
+}
+
+/// Returns true if both paths point to the same directory.
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    match (dunce::canonicalize(a), dunce::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn get_or_insert_table<'a>(table: &'a mut Table, key: &str) -> &'a mut Table {
+    table
+        .entry(key)
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .expect("the entry should be a table")
+}
+
+fn insert_dependencies(table: &mut Table, dependencies: &PypiDependencies) {
+    for (name, requirement) in dependencies {
+        table.insert(
+            name.as_source(),
+            Item::Value(Value::from(requirement.clone())),
+        );
+    }
+}
+
+/// Parses a PEP 508 requirement and adds it to `dependencies`.
+///
+/// Environment markers can't be expressed in pixi, these are dropped with a
+/// warning.
+fn add_pep508_requirement(
+    dependencies: &mut PypiDependencies,
+    requirement: &str,
+) -> miette::Result<()> {
+    let requirement = Requirement::from_str(requirement)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to parse '{requirement}' as a PEP 508 requirement"))?;
+    if !requirement.marker.is_true() {
+        warn_dropped_marker(requirement.name.as_ref(), &requirement.to_string());
+    }
+    let name = PyPiPackageName::from_normalized(requirement.name.clone());
+    let requirement = PyPiRequirement::try_from(requirement).into_diagnostic()?;
+    dependencies.insert(name, requirement);
+    Ok(())
+}
+
+fn warn_dropped_marker(name: &str, marker: &str) {
+    tracing::warn!(
+        "the environment marker of '{name}' ({marker}) is not supported and is ignored, use platform specific dependencies instead"
+    );
+}
+
+/// Returns the PEP 440 release segments of a version, e.g. `[1, 2]` for
+/// `1.2b1`.
+fn release_segments(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map_while(|segment| {
+            let digits: String = segment.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+/// Returns the version that is the exclusive upper bound when incrementing the
+/// segment at `index`, e.g. `2` for `1.4.2` with index `0`.
+fn bump_segment(segments: &[u64], index: usize) -> String {
+    segments
+        .iter()
+        .take(index + 1)
+        .enumerate()
+        .map(|(i, segment)| if i == index { segment + 1 } else { *segment })
+        .join(".")
+}
+
+/// Converts a poetry style version constraint (`^1.2`, `~1.2.3`, `1.2.*`,
+/// `>=1,<2`) to PEP 440 version specifiers.
+///
+/// Returns `None` for `*` or an empty constraint.
+pub(crate) fn poetry_constraint_to_pep440(constraint: &str) -> miette::Result<Option<String>> {
+    let constraint = constraint.trim();
+    if constraint.is_empty() || constraint == "*" {
+        return Ok(None);
+    }
+    if constraint.contains('|') {
+        miette::bail!("the version constraint '{constraint}' contains alternatives which are not supported by PEP 440");
+    }
+
+    let mut specifiers = Vec::new();
+    // Constraints are separated by commas, or by whitespace between two
+    // constraints (e.g. `>=1.2 <2.0`).
+    for part in constraint
+        .split(',')
+        .flat_map(|part| split_whitespace_constraints(part.trim()))
+    {
+        let operator_len = part
+            .find(|c: char| c.is_ascii_digit() || c == '*')
+            .unwrap_or(part.len());
+        let (operator, version) = part.split_at(operator_len);
+        let (operator, version) = (operator.trim(), version.trim());
+        if version.is_empty() {
+            miette::bail!("invalid version constraint '{constraint}'");
+        }
+
+        match operator {
+            "^" => {
+                let segments = release_segments(version);
+                let index = segments
+                    .iter()
+                    .position(|segment| *segment != 0)
+                    .unwrap_or(segments.len().saturating_sub(1));
+                specifiers.push(format!(">={version}"));
+                specifiers.push(format!("<{}", bump_segment(&segments, index)));
+            }
+            "~" => {
+                let segments = release_segments(version);
+                let index = if segments.len() >= 2 { 1 } else { 0 };
+                specifiers.push(format!(">={version}"));
+                specifiers.push(format!("<{}", bump_segment(&segments, index)));
+            }
+            "" | "=" => specifiers.push(format!("=={version}")),
+            ">=" | "<=" | ">" | "<" | "==" | "!=" | "~=" | "===" => {
+                specifiers.push(format!("{operator}{version}"))
+            }
+            _ => miette::bail!(
+                "unsupported operator '{operator}' in version constraint '{constraint}'"
+            ),
+        }
+    }
+
+    Ok(Some(specifiers.join(",")))
+}
+
+/// Splits a constraint like `>=1.2 <2.0` into `>=1.2` and `<2.0` while keeping
+/// `>= 1.2` together.
+fn split_whitespace_constraints(part: &str) -> Vec<String> {
+    let is_operator = |token: &str| {
+        token
+            .chars()
+            .all(|c| matches!(c, '<' | '>' | '=' | '!' | '~' | '^'))
+    };
+    let mut constraints: Vec<String> = Vec::new();
+    for token in part.split_whitespace() {
+        match constraints.last_mut() {
+            // An operator that is separated from its version by whitespace.
+            Some(last) if is_operator(last) => last.push_str(token),
+            _ => constraints.push(token.to_string()),
+        }
+    }
+    constraints
+}
+
+/// Converts a PEP 440 or poetry version constraint to a pixi requirement.
+fn version_requirement(
+    constraint: Option<&str>,
+    extras: &[String],
+    index: Option<Url>,
+    poetry_syntax: bool,
+) -> miette::Result<PyPiRequirement> {
+    let specifiers = match constraint.map(str::trim) {
+        None | Some("") | Some("*") => None,
+        Some(constraint) if poetry_syntax => poetry_constraint_to_pep440(constraint)?,
+        // A bare version means an exact version.
+        Some(constraint) if constraint.starts_with(|c: char| c.is_ascii_digit()) => {
+            Some(format!("=={constraint}"))
+        }
+        Some(constraint) => Some(constraint.to_string()),
+    };
+    let version = match specifiers {
+        Some(specifiers) => VersionOrStar::from_str(&specifiers)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse the version constraint '{specifiers}'"))?,
+        None => VersionOrStar::Star,
+    };
+    let extras = parse_extras(extras)?;
+
+    if extras.is_empty() && index.is_none() {
+        Ok(PyPiRequirement::RawVersion(version))
+    } else {
+        Ok(PyPiRequirement::Version {
+            version,
+            extras,
+            index,
+        })
+    }
+}
+
+/// Creates a git requirement. `reference` is used as the revision if it looks
+/// like a commit hash and as a branch otherwise.
+fn git_requirement(
+    url: &str,
+    branch: Option<String>,
+    tag: Option<String>,
+    reference: Option<String>,
+    subdirectory: Option<String>,
+    extras: &[String],
+) -> miette::Result<PyPiRequirement> {
+    let git = Url::parse(url.strip_prefix("git+").unwrap_or(url))
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to parse the git url '{url}'"))?;
+    let (rev, branch) = match reference {
+        Some(reference) => match GitRev::from_str(&reference) {
+            Ok(rev) => (Some(rev), branch),
+            Err(_) => (None, branch.or(Some(reference))),
+        },
+        None => (None, branch),
+    };
+    Ok(PyPiRequirement::Git {
+        url: ParsedGitUrl {
+            git,
+            branch,
+            tag,
+            rev,
+            subdirectory,
+        },
+        extras: parse_extras(extras)?,
+    })
+}
+
+fn parse_extras(extras: &[String]) -> miette::Result<Vec<ExtraName>> {
+    extras
+        .iter()
+        .map(|extra| ExtraName::from_str(extra).into_diagnostic())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use toml_edit::DocumentMut;
+
+    use super::*;
+
+    #[test]
+    fn test_poetry_constraint_to_pep440() {
+        let cases = [
+            ("*", None),
+            ("^1.2.3", Some(">=1.2.3,<2")),
+            ("^1.2", Some(">=1.2,<2")),
+            ("^0.2.3", Some(">=0.2.3,<0.3")),
+            ("^0.0.3", Some(">=0.0.3,<0.0.4")),
+            ("^0.0", Some(">=0.0,<0.1")),
+            ("^0", Some(">=0,<1")),
+            ("~1.2.3", Some(">=1.2.3,<1.3")),
+            ("~1.2", Some(">=1.2,<1.3")),
+            ("~1", Some(">=1,<2")),
+            ("1.2.*", Some("==1.2.*")),
+            ("1.2.3", Some("==1.2.3")),
+            ("==1.2.3", Some("==1.2.3")),
+            (">=1.2,<2.0", Some(">=1.2,<2.0")),
+            (">=1.2 <2.0", Some(">=1.2,<2.0")),
+            (">= 1.2, < 2.0", Some(">=1.2,<2.0")),
+            ("~=3.9", Some("~=3.9")),
+        ];
+        for (constraint, expected) in cases {
+            assert_eq!(
+                poetry_constraint_to_pep440(constraint).unwrap().as_deref(),
+                expected,
+                "constraint: {constraint}"
+            );
+        }
+        assert!(poetry_constraint_to_pep440("^1.0 || ^2.0").is_err());
+    }
+
+    #[test]
+    fn test_add_to_document() {
+        let mut project = ImportedPythonProject {
+            requires_python: Some(VersionSpecifiers::from_str(">=3.10").unwrap()),
+            ..Default::default()
+        };
+        add_pep508_requirement(&mut project.dependencies, "requests>=2").unwrap();
+        add_pep508_requirement(
+            project.groups.entry("docs_build".to_string()).or_default(),
+            "sphinx",
+        )
+        .unwrap();
+        project.pypi_options.extra_index_urls =
+            Some(vec![Url::parse("https://example.com/simple").unwrap()]);
+
+        let mut document = DocumentMut::from_str("[project]\nname = \"test\"\n").unwrap();
+        project.add_to_document(&mut document).unwrap();
+
+        assert_eq!(document["dependencies"]["python"].as_str(), Some(">=3.10"));
+        assert_eq!(
+            document["pypi-dependencies"]["requests"].as_str(),
+            Some(">=2")
+        );
+        assert_eq!(
+            document["feature"]["docs-build"]["pypi-dependencies"]["sphinx"].as_str(),
+            Some("*")
+        );
+        assert_eq!(
+            document["environments"]["docs-build"]["features"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|feature| feature.as_str())
+                .collect_vec(),
+            vec!["docs-build"]
+        );
+        assert_eq!(
+            document["pypi-options"]["extra-index-urls"]
+                .as_array()
+                .unwrap()
+                .get(0)
+                .and_then(|url| url.as_str()),
+            Some("https://example.com/simple")
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Imports a pipenv `Pipfile`.
+
+use std::{path::PathBuf, str::FromStr};
+
+use indexmap::IndexMap;
+use miette::IntoDiagnostic;
+use pep440_rs::VersionSpecifiers;
+use serde::Deserialize;
+use url::Url;
+
+use super::{
+    git_requirement, parse_extras, version_requirement, warn_dropped_marker, ImportedPythonProject,
+    PypiDependencies,
+};
+use crate::{
+    pypi::{pypi_options::PypiOptions, PyPiPackageName},
+    PyPiRequirement,
+};
+
+/// The default index of pipenv.
+const PYPI_SIMPLE_URL: &str = "https://pypi.org/simple";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Pipfile {
+    #[serde(default)]
+    source: Vec<Source>,
+    #[serde(default)]
+    packages: IndexMap<String, Dependency>,
+    #[serde(default)]
+    dev_packages: IndexMap<String, Dependency>,
+    requires: Option<Requires>,
+}
+
+#[derive(Deserialize)]
+struct Source {
+    name: String,
+    url: Url,
+}
+
+#[derive(Deserialize)]
+struct Requires {
+    python_version: Option<String>,
+    python_full_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Dependency {
+    Version(String),
+    Detailed(DetailedDependency),
+}
+
+#[derive(Deserialize)]
+struct DetailedDependency {
+    version: Option<String>,
+    #[serde(default)]
+    extras: Vec<String>,
+    path: Option<PathBuf>,
+    #[serde(default)]
+    editable: bool,
+    git: Option<String>,
+    #[serde(rename = "ref")]
+    reference: Option<String>,
+    subdirectory: Option<String>,
+    file: Option<Url>,
+    index: Option<String>,
+    markers: Option<String>,
+    sys_platform: Option<String>,
+}
+
+pub(super) fn import(source: &str) -> miette::Result<ImportedPythonProject> {
+    let pipfile: Pipfile = toml_edit::de::from_str(source).into_diagnostic()?;
+
+    // The first source is the primary index, the others are searched after it.
+    let mut sources = pipfile.source.iter();
+    let index_url = sources
+        .next()
+        .map(|source| source.url.clone())
+        .filter(|url| url.as_str().trim_end_matches('/') != PYPI_SIMPLE_URL);
+    let extra_index_urls = sources.map(|source| source.url.clone()).collect::<Vec<_>>();
+    let indexes = pipfile
+        .source
+        .iter()
+        .map(|source| (source.name.as_str(), source.url.clone()))
+        .collect::<IndexMap<_, _>>();
+
+    let requires_python = match pipfile.requires {
+        Some(Requires {
+            python_full_version: Some(version),
+            ..
+        }) => Some(format!("=={version}")),
+        Some(Requires {
+            python_version: Some(version),
+            ..
+        }) => Some(format!("=={version}.*")),
+        _ => None,
+    }
+    .map(|specifiers| VersionSpecifiers::from_str(&specifiers))
+    .transpose()
+    .into_diagnostic()?;
+
+    let mut project = ImportedPythonProject {
+        requires_python,
+        dependencies: convert_dependencies(&pipfile.packages, &indexes)?,
+        pypi_options: PypiOptions {
+            index_url,
+            extra_index_urls: (!extra_index_urls.is_empty()).then_some(extra_index_urls),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    if !pipfile.dev_packages.is_empty() {
+        project.groups.insert(
+            String::from("dev"),
+            convert_dependencies(&pipfile.dev_packages, &indexes)?,
+        );
+    }
+
+    Ok(project)
+}
+
+fn convert_dependencies(
+    dependencies: &IndexMap<String, Dependency>,
+    indexes: &IndexMap<&str, Url>,
+) -> miette::Result<PypiDependencies> {
+    dependencies
+        .iter()
+        .map(|(name, dependency)| {
+            let package_name = PyPiPackageName::from_str(name).into_diagnostic()?;
+            let requirement = convert_dependency(name, dependency, indexes)?;
+            Ok((package_name, requirement))
+        })
+        .collect()
+}
+
+fn convert_dependency(
+    name: &str,
+    dependency: &Dependency,
+    indexes: &IndexMap<&str, Url>,
+) -> miette::Result<PyPiRequirement> {
+    let dependency = match dependency {
+        Dependency::Version(version) => {
+            return version_requirement(Some(version), &[], None, false)
+        }
+        Dependency::Detailed(dependency) => dependency,
+    };
+
+    for marker in [&dependency.markers, &dependency.sys_platform]
+        .into_iter()
+        .flatten()
+    {
+        warn_dropped_marker(name, marker);
+    }
+
+    if let Some(git) = &dependency.git {
+        git_requirement(
+            git,
+            None,
+            None,
+            dependency.reference.clone(),
+            dependency.subdirectory.clone(),
+            &dependency.extras,
+        )
+    } else if let Some(path) = &dependency.path {
+        Ok(PyPiRequirement::Path {
+            path: path.clone(),
+            editable: dependency.editable.then_some(true),
+            extras: parse_extras(&dependency.extras)?,
+        })
+    } else if let Some(url) = &dependency.file {
+        Ok(PyPiRequirement::Url {
+            url: url.clone(),
+            subdirectory: dependency.subdirectory.clone(),
+            extras: parse_extras(&dependency.extras)?,
+        })
+    } else {
+        let index = match &dependency.index {
+            Some(index) => Some(indexes.get(index.as_str()).cloned().ok_or_else(|| {
+                miette::miette!("'{name}' refers to the unknown index '{index}'")
+            })?),
+            None => None,
+        };
+        version_requirement(
+            dependency.version.as_deref(),
+            &dependency.extras,
+            index,
+            false,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_pipfile() {
+        let source = r#"
+        [[source]]
+        url = "https://pypi.org/simple"
+        verify_ssl = true
+        name = "pypi"
+
+        [[source]]
+        url = "https://download.pytorch.org/whl/cpu"
+        verify_ssl = true
+        name = "pytorch"
+
+        [packages]
+        requests = "*"
+        flask = { version = ">=2.0", extras = ["async"] }
+        torch = { version = "==2.1.0", index = "pytorch" }
+        mylib = { path = "./mylib", editable = true }
+        django = { git = "https://github.com/django/django.git", ref = "stable/5.0.x" }
+
+        [dev-packages]
+        pytest = "==8.0.0"
+
+        [requires]
+        python_version = "3.11"
+        "#;
+
+        let project = import(source).unwrap();
+        assert_eq!(
+            project.requires_python,
+            Some(VersionSpecifiers::from_str("==3.11.*").unwrap())
+        );
+        assert_eq!(
+            project
+                .dependencies
+                .iter()
+                .map(|(name, requirement)| format!("{} = {requirement}", name.as_source()))
+                .collect::<Vec<_>>(),
+            vec![
+                r#"requests = "*""#,
+                r#"flask = { version = ">=2.0", extras = ["async"] }"#,
+                r#"torch = { version = "==2.1.0", index = "https://download.pytorch.org/whl/cpu" }"#,
+                r#"mylib = { path = "./mylib", editable = true }"#,
+                r#"django = { git = "https://github.com/django/django.git", branch = "stable/5.0.x" }"#,
+            ]
+        );
+        assert_eq!(
+            project.groups["dev"]
+                .values()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![r#""==8.0.0""#]
+        );
+        assert_eq!(project.pypi_options.index_url, None);
+        assert_eq!(
+            project.pypi_options.extra_index_urls,
+            Some(vec![
+                Url::parse("https://download.pytorch.org/whl/cpu").unwrap()
+            ])
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Imports the `[tool.poetry]` table of a `pyproject.toml`.
+
+use std::{path::PathBuf, str::FromStr};
+
+use indexmap::IndexMap;
+use miette::{IntoDiagnostic, WrapErr};
+use pep440_rs::VersionSpecifiers;
+use serde::Deserialize;
+use url::Url;
+
+use super::{
+    add_pep508_requirement, git_requirement, parse_extras, poetry_constraint_to_pep440,
+    version_requirement, warn_dropped_marker, ImportedPythonProject,
+};
+use crate::{
+    pypi::{pypi_options::PypiOptions, PyPiPackageName},
+    PyPiRequirement,
+};
+
+#[derive(Deserialize)]
+struct PyProject {
+    project: Option<Project>,
+    tool: Tool,
+}
+
+/// The PEP 621 table, which is supported since poetry 2.0.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Project {
+    name: Option<String>,
+    requires_python: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    optional_dependencies: IndexMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Tool {
+    poetry: ToolPoetry,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ToolPoetry {
+    name: Option<String>,
+    #[serde(default = "default_package_mode")]
+    package_mode: bool,
+    #[serde(default)]
+    dependencies: IndexMap<String, Dependency>,
+    #[serde(default)]
+    dev_dependencies: IndexMap<String, Dependency>,
+    #[serde(default)]
+    group: IndexMap<String, Group>,
+    #[serde(default)]
+    extras: IndexMap<String, Vec<String>>,
+    #[serde(default)]
+    source: Vec<Source>,
+}
+
+fn default_package_mode() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct Group {
+    #[serde(default)]
+    dependencies: IndexMap<String, Dependency>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Dependency {
+    Version(String),
+    Detailed(DetailedDependency),
+    Multiple(Vec<DetailedDependency>),
+}
+
+#[derive(Deserialize)]
+struct DetailedDependency {
+    version: Option<String>,
+    #[serde(default)]
+    extras: Vec<String>,
+    #[serde(default)]
+    optional: bool,
+    python: Option<String>,
+    markers: Option<String>,
+    platform: Option<String>,
+    git: Option<String>,
+    branch: Option<String>,
+    tag: Option<String>,
+    rev: Option<String>,
+    subdirectory: Option<String>,
+    path: Option<PathBuf>,
+    #[serde(default)]
+    develop: bool,
+    url: Option<Url>,
+    source: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Source {
+    name: String,
+    url: Option<Url>,
+    priority: Option<String>,
+    #[serde(default)]
+    default: bool,
+    #[serde(default)]
+    secondary: bool,
+}
+
+impl Source {
+    /// Returns the priority of the source, taking the deprecated `default`
+    /// and `secondary` flags into account.
+    fn priority(&self) -> &str {
+        match self.priority.as_deref() {
+            Some(priority) => priority,
+            None if self.default => "default",
+            None if self.secondary => "secondary",
+            None => "primary",
+        }
+    }
+}
+
+pub(super) fn import(source: &str) -> miette::Result<ImportedPythonProject> {
+    let pyproject: PyProject = toml_edit::de::from_str(source).into_diagnostic()?;
+    let poetry = pyproject.tool.poetry;
+
+    let mut project = ImportedPythonProject {
+        name: poetry
+            .name
+            .clone()
+            .or_else(|| pyproject.project.as_ref().and_then(|p| p.name.clone())),
+        ..Default::default()
+    };
+
+    // Sources with a `primary` or `default` priority replace PyPI, the others
+    // are searched after the primary index. `explicit` sources are only used
+    // by the packages that select them.
+    let mut index_url = None;
+    let mut extra_index_urls = Vec::new();
+    let mut sources = IndexMap::new();
+    for source in &poetry.source {
+        let Some(url) = &source.url else {
+            // A source without url refers to PyPI.
+            continue;
+        };
+        sources.insert(source.name.as_str(), url.clone());
+        match source.priority() {
+            "explicit" => {}
+            "primary" | "default" if index_url.is_none() => index_url = Some(url.clone()),
+            _ => extra_index_urls.push(url.clone()),
+        }
+    }
+    project.pypi_options = PypiOptions {
+        index_url,
+        extra_index_urls: (!extra_index_urls.is_empty()).then_some(extra_index_urls),
+        ..Default::default()
+    };
+
+    // Add the dependencies of the PEP 621 table.
+    if let Some(pep621) = &pyproject.project {
+        if let Some(requires_python) = &pep621.requires_python {
+            project.requires_python = Some(
+                VersionSpecifiers::from_str(requires_python)
+                    .into_diagnostic()
+                    .wrap_err("failed to parse `requires-python`")?,
+            );
+        }
+        for requirement in &pep621.dependencies {
+            add_pep508_requirement(&mut project.dependencies, requirement)?;
+        }
+        for (extra, requirements) in &pep621.optional_dependencies {
+            let group = project.groups.entry(extra.clone()).or_default();
+            for requirement in requirements {
+                add_pep508_requirement(group, requirement)?;
+            }
+        }
+    }
+
+    // Optional dependencies are only installed as part of an extra.
+    let mut optional = IndexMap::new();
+    for (name, dependency) in &poetry.dependencies {
+        if name.eq_ignore_ascii_case("python") {
+            if let Dependency::Version(constraint) = dependency {
+                project.requires_python = poetry_constraint_to_pep440(constraint)?
+                    .map(|specifiers| VersionSpecifiers::from_str(&specifiers))
+                    .transpose()
+                    .into_diagnostic()?;
+            }
+            continue;
+        }
+        let (package_name, requirement, is_optional) =
+            convert_dependency(name, dependency, &sources)?;
+        if is_optional {
+            optional.insert(
+                package_name.as_normalized().clone(),
+                (package_name, requirement),
+            );
+        } else {
+            project.dependencies.insert(package_name, requirement);
+        }
+    }
+    for (extra, packages) in &poetry.extras {
+        let group = project.groups.entry(extra.clone()).or_default();
+        for package in packages {
+            let package_name = pep508_rs::PackageName::from_str(package).into_diagnostic()?;
+            if let Some((package_name, requirement)) = optional.get(&package_name) {
+                group.insert(package_name.clone(), requirement.clone());
+            }
+        }
+    }
+
+    // The legacy dev-dependencies are the same as the `dev` group.
+    let groups = poetry
+        .dev_dependencies
+        .iter()
+        .map(|dependencies| ("dev", dependencies))
+        .chain(poetry.group.iter().flat_map(|(name, group)| {
+            group
+                .dependencies
+                .iter()
+                .map(move |dependencies| (name.as_str(), dependencies))
+        }));
+    for (group, (name, dependency)) in groups {
+        let (package_name, requirement, _) = convert_dependency(name, dependency, &sources)?;
+        project
+            .groups
+            .entry(group.to_string())
+            .or_default()
+            .insert(package_name, requirement);
+    }
+
+    // Poetry installs the project itself unless it is only used for dependency
+    // management.
+    if poetry.package_mode {
+        if let Some(name) = &project.name {
+            let package_name = PyPiPackageName::from_str(name).into_diagnostic()?;
+            project.dependencies.insert(
+                package_name,
+                PyPiRequirement::Path {
+                    path: PathBuf::from("."),
+                    editable: Some(true),
+                    extras: Vec::new(),
+                },
+            );
+        }
+    }
+
+    Ok(project)
+}
+
+/// Converts a poetry dependency to a pixi requirement. Also returns whether the
+/// dependency is optional.
+fn convert_dependency(
+    name: &str,
+    dependency: &Dependency,
+    sources: &IndexMap<&str, Url>,
+) -> miette::Result<(PyPiPackageName, PyPiRequirement, bool)> {
+    let package_name = PyPiPackageName::from_str(name).into_diagnostic()?;
+    let dependency = match dependency {
+        Dependency::Version(constraint) => {
+            let requirement = version_requirement(Some(constraint), &[], None, true)?;
+            return Ok((package_name, requirement, false));
+        }
+        Dependency::Detailed(dependency) => dependency,
+        Dependency::Multiple(dependencies) => {
+            tracing::warn!(
+                "'{name}' has multiple constraints, which are not supported, only the first one is used"
+            );
+            dependencies
+                .first()
+                .ok_or_else(|| miette::miette!("'{name}' does not have any constraints"))?
+        }
+    };
+
+    for marker in [
+        &dependency.python,
+        &dependency.platform,
+        &dependency.markers,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        warn_dropped_marker(name, marker);
+    }
+
+    let requirement = if let Some(git) = &dependency.git {
+        git_requirement(
+            git,
+            dependency.branch.clone(),
+            dependency.tag.clone(),
+            dependency.rev.clone(),
+            dependency.subdirectory.clone(),
+            &dependency.extras,
+        )?
+    } else if let Some(path) = &dependency.path {
+        PyPiRequirement::Path {
+            path: path.clone(),
+            editable: dependency.develop.then_some(true),
+            extras: parse_extras(&dependency.extras)?,
+        }
+    } else if let Some(url) = &dependency.url {
+        PyPiRequirement::Url {
+            url: url.clone(),
+            subdirectory: dependency.subdirectory.clone(),
+            extras: parse_extras(&dependency.extras)?,
+        }
+    } else {
+        let index = match &dependency.source {
+            Some(source) => Some(sources.get(source.as_str()).cloned().ok_or_else(|| {
+                miette::miette!("'{name}' refers to the unknown source '{source}'")
+            })?),
+            None => None,
+        };
+        version_requirement(
+            dependency.version.as_deref(),
+            &dependency.extras,
+            index,
+            true,
+        )?
+    };
+
+    Ok((package_name, requirement, dependency.optional))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_poetry() {
+        let source = r#"
+        [tool.poetry]
+        name = "my-project"
+
+        [tool.poetry.dependencies]
+        python = "^3.10"
+        requests = { version = "^2.28", extras = ["socks"] }
+        private = { version = "*", source = "internal" }
+        lib = { path = "../lib", develop = true }
+        pendulum = { git = "https://github.com/sdispater/pendulum.git", branch = "develop" }
+        psycopg = { version = "^3.1", optional = true }
+
+        [tool.poetry.extras]
+        postgres = ["psycopg"]
+
+        [tool.poetry.group.test.dependencies]
+        pytest = "~8.1"
+
+        [tool.poetry.dev-dependencies]
+        black = "*"
+
+        [[tool.poetry.source]]
+        name = "internal"
+        url = "https://pypi.internal.example/simple"
+        priority = "explicit"
+
+        [[tool.poetry.source]]
+        name = "mirror"
+        url = "https://mirror.example/simple"
+        priority = "supplemental"
+        "#;
+
+        let project = import(source).unwrap();
+        assert_eq!(project.name.as_deref(), Some("my-project"));
+        assert_eq!(
+            project.requires_python,
+            Some(VersionSpecifiers::from_str(">=3.10,<4").unwrap())
+        );
+        assert_eq!(
+            project
+                .dependencies
+                .iter()
+                .map(|(name, requirement)| format!("{} = {requirement}", name.as_source()))
+                .collect::<Vec<_>>(),
+            vec![
+                r#"requests = { version = ">=2.28, <3", extras = ["socks"] }"#,
+                r#"private = { version = "*", index = "https://pypi.internal.example/simple" }"#,
+                r#"lib = { path = "../lib", editable = true }"#,
+                r#"pendulum = { git = "https://github.com/sdispater/pendulum.git", branch = "develop" }"#,
+                r#"my-project = { path = ".", editable = true }"#,
+            ]
+        );
+        assert_eq!(
+            project.groups.keys().collect::<Vec<_>>(),
+            vec!["postgres", "dev", "test"]
+        );
+        assert_eq!(
+            project.groups["test"]
+                .values()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![r#"">=8.1, <8.2""#]
+        );
+        assert_eq!(project.pypi_options.index_url, None);
+        assert_eq!(
+            project.pypi_options.extra_index_urls,
+            Some(vec![Url::parse("https://mirror.example/simple").unwrap()])
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Imports the `[project]`, `[dependency-groups]` and `[tool.uv]` tables of a
+//! `pyproject.toml`.
+
+use std::{path::PathBuf, str::FromStr};
+
+use indexmap::IndexMap;
+use miette::{IntoDiagnostic, WrapErr};
+use pep440_rs::VersionSpecifiers;
+use pep508_rs::{PackageName, Requirement};
+use serde::{de::IgnoredAny, Deserialize};
+use url::Url;
+
+use super::{git_requirement, warn_dropped_marker, ImportedPythonProject, PypiDependencies};
+use crate::{
+    pypi::{
+        pypi_options::{FindLinksUrlOrPath, PypiOptions},
+        PyPiPackageName,
+    },
+    PyPiRequirement,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyProject {
+    project: Option<Project>,
+    build_system: Option<IgnoredAny>,
+    #[serde(default)]
+    dependency_groups: IndexMap<String, Vec<DependencyGroupEntry>>,
+    tool: Option<Tool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Project {
+    name: Option<String>,
+    requires_python: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    optional_dependencies: IndexMap<String, Vec<String>>,
+}
+
+/// An entry of a PEP 735 dependency group.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DependencyGroupEntry {
+    Requirement(String),
+    Include {
+        #[serde(rename = "include-group")]
+        include_group: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct Tool {
+    uv: Option<ToolUv>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ToolUv {
+    package: Option<bool>,
+    #[serde(default)]
+    dev_dependencies: Vec<String>,
+    #[serde(default)]
+    sources: IndexMap<PackageName, Sources>,
+    #[serde(default)]
+    index: Vec<Index>,
+    index_url: Option<Url>,
+    #[serde(default)]
+    extra_index_url: Vec<Url>,
+    #[serde(default)]
+    find_links: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Sources {
+    Single(Source),
+    Multiple(Vec<Source>),
+}
+
+#[derive(Deserialize)]
+struct Source {
+    git: Option<String>,
+    branch: Option<String>,
+    tag: Option<String>,
+    rev: Option<String>,
+    subdirectory: Option<String>,
+    path: Option<PathBuf>,
+    editable: Option<bool>,
+    url: Option<Url>,
+    index: Option<String>,
+    #[serde(default)]
+    workspace: bool,
+}
+
+#[derive(Deserialize)]
+struct Index {
+    name: Option<String>,
+    url: Url,
+    #[serde(default)]
+    default: bool,
+    #[serde(default)]
+    explicit: bool,
+}
+
+pub(super) fn import(source: &str) -> miette::Result<ImportedPythonProject> {
+    let pyproject: PyProject = toml_edit::de::from_str(source).into_diagnostic()?;
+    let uv = pyproject.tool.and_then(|tool| tool.uv).unwrap_or_default();
+
+    // The default index replaces PyPI, the other indexes are searched after
+    // it. Explicit indexes are only used by the packages that select them.
+    let mut index_url = uv.index_url.clone();
+    let mut extra_index_urls = uv.extra_index_url.clone();
+    let mut indexes = IndexMap::new();
+    for index in &uv.index {
+        if let Some(name) = &index.name {
+            indexes.insert(name.as_str(), index.url.clone());
+        }
+        if index.explicit {
+            continue;
+        }
+        if index.default {
+            index_url = Some(index.url.clone());
+        } else {
+            extra_index_urls.push(index.url.clone());
+        }
+    }
+    let find_links = uv
+        .find_links
+        .iter()
+        .map(|location| match Url::parse(location) {
+            Ok(url) => FindLinksUrlOrPath::Url(url),
+            Err(_) => FindLinksUrlOrPath::Path(PathBuf::from(location)),
+        })
+        .collect::<Vec<_>>();
+
+    let converter = Converter {
+        sources: &uv.sources,
+        indexes: &indexes,
+    };
+
+    let mut project = ImportedPythonProject {
+        pypi_options: PypiOptions {
+            index_url,
+            extra_index_urls: (!extra_index_urls.is_empty()).then_some(extra_index_urls),
+            find_links: (!find_links.is_empty()).then_some(find_links),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    if let Some(pep621) = &pyproject.project {
+        project.name = pep621.name.clone();
+        if let Some(requires_python) = &pep621.requires_python {
+            project.requires_python = Some(
+                VersionSpecifiers::from_str(requires_python)
+                    .into_diagnostic()
+                    .wrap_err("failed to parse `requires-python`")?,
+            );
+        }
+        for requirement in &pep621.dependencies {
+            converter.add(&mut project.dependencies, requirement)?;
+        }
+        for (extra, requirements) in &pep621.optional_dependencies {
+            let group = project.groups.entry(extra.clone()).or_default();
+            for requirement in requirements {
+                converter.add(group, requirement)?;
+            }
+        }
+    }
+
+    // The legacy dev-dependencies are the same as the `dev` group.
+    if !uv.dev_dependencies.is_empty() {
+        let group = project.groups.entry(String::from("dev")).or_default();
+        for requirement in &uv.dev_dependencies {
+            converter.add(group, requirement)?;
+        }
+    }
+    for name in pyproject.dependency_groups.keys() {
+        let mut requirements = Vec::new();
+        collect_group_requirements(
+            name,
+            &pyproject.dependency_groups,
+            &mut Vec::new(),
+            &mut requirements,
+        )?;
+        let group = project.groups.entry(name.clone()).or_default();
+        for requirement in requirements {
+            converter.add(group, requirement)?;
+        }
+    }
+
+    // uv installs the project itself if it defines a build system, unless
+    // this is overridden with `tool.uv.package`.
+    if uv.package.unwrap_or(pyproject.build_system.is_some()) {
+        if let Some(name) = &project.name {
+            let package_name = PyPiPackageName::from_str(name).into_diagnostic()?;
+            project.dependencies.insert(
+                package_name,
+                PyPiRequirement::Path {
+                    path: PathBuf::from("."),
+                    editable: Some(true),
+                    extras: Vec::new(),
+                },
+            );
+        }
+    }
+
+    Ok(project)
+}
+
+/// Collects the requirements of a dependency group, including the
+/// requirements of the groups it includes.
+fn collect_group_requirements<'a>(
+    name: &'a str,
+    groups: &'a IndexMap<String, Vec<DependencyGroupEntry>>,
+    stack: &mut Vec<&'a str>,
+    requirements: &mut Vec<&'a str>,
+) -> miette::Result<()> {
+    if stack.contains(&name) {
+        miette::bail!("the dependency group '{name}' includes itself");
+    }
+    let entries = groups
+        .get(name)
+        .ok_or_else(|| miette::miette!("the dependency group '{name}' does not exist"))?;
+    stack.push(name);
+    for entry in entries {
+        match entry {
+            DependencyGroupEntry::Requirement(requirement) => requirements.push(requirement),
+            DependencyGroupEntry::Include { include_group } => {
+                collect_group_requirements(include_group, groups, stack, requirements)?
+            }
+        }
+    }
+    stack.pop();
+    Ok(())
+}
+
+/// Converts PEP 508 requirements, applying the `tool.uv.sources` of the
+/// project.
+struct Converter<'a> {
+    sources: &'a IndexMap<PackageName, Sources>,
+    indexes: &'a IndexMap<&'a str, Url>,
+}
+
+impl Converter<'_> {
+    fn add(&self, dependencies: &mut PypiDependencies, requirement: &str) -> miette::Result<()> {
+        let requirement = Requirement::from_str(requirement)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!("failed to parse '{requirement}' as a PEP 508 requirement")
+            })?;
+        if !requirement.marker.is_true() {
+            warn_dropped_marker(requirement.name.as_ref(), &requirement.to_string());
+        }
+        let name = PyPiPackageName::from_normalized(requirement.name.clone());
+        let source = self.source(&requirement.name);
+        let extras = requirement.extras.clone();
+        let requirement = PyPiRequirement::try_from(requirement).into_diagnostic()?;
+
+        let requirement = match source {
+            Some(source) => self.apply_source(name.as_source(), source, requirement, extras)?,
+            None => requirement,
+        };
+        dependencies.insert(name, requirement);
+        Ok(())
+    }
+
+    fn source(&self, name: &PackageName) -> Option<&Source> {
+        match self.sources.get(name)? {
+            Sources::Single(source) => Some(source),
+            Sources::Multiple(sources) => {
+                tracing::warn!(
+                    "'{name}' has multiple sources, which are not supported, only the first one is used"
+                );
+                sources.first()
+            }
+        }
+    }
+
+    fn apply_source(
+        &self,
+        name: &str,
+        source: &Source,
+        requirement: PyPiRequirement,
+        extras: Vec<pep508_rs::ExtraName>,
+    ) -> miette::Result<PyPiRequirement> {
+        let extra_strings = extras.iter().map(ToString::to_string).collect::<Vec<_>>();
+        if let Some(git) = &source.git {
+            git_requirement(
+                git,
+                source.branch.clone(),
+                source.tag.clone(),
+                source.rev.clone(),
+                source.subdirectory.clone(),
+                &extra_strings,
+            )
+        } else if let Some(path) = &source.path {
+            Ok(PyPiRequirement::Path {
+                path: path.clone(),
+                editable: source.editable,
+                extras,
+            })
+        } else if let Some(url) = &source.url {
+            Ok(PyPiRequirement::Url {
+                url: url.clone(),
+                subdirectory: source.subdirectory.clone(),
+                extras,
+            })
+        } else if let Some(index) = &source.index {
+            let index =
+                self.indexes.get(index.as_str()).cloned().ok_or_else(|| {
+                    miette::miette!("'{name}' refers to the unknown index '{index}'")
+                })?;
+            let version = match requirement {
+                PyPiRequirement::RawVersion(version) | PyPiRequirement::Version { version, .. } => {
+                    version
+                }
+                _ => miette::bail!("'{name}' selects an index but is not a version requirement"),
+            };
+            Ok(PyPiRequirement::Version {
+                version,
+                extras,
+                index: Some(index),
+            })
+        } else {
+            if source.workspace {
+                tracing::warn!(
+                    "'{name}' is a uv workspace member, which is not supported, add it as a path dependency instead"
+                );
+            }
+            Ok(requirement)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_uv() {
+        let source = r#"
+        [project]
+        name = "my-project"
+        requires-python = ">=3.11"
+        dependencies = ["requests>=2.31", "torch==2.2.0", "httpx[http2]", "lib"]
+
+        [dependency-groups]
+        test = ["pytest>=8"]
+        lint = ["ruff"]
+        ci = [{ include-group = "test" }, { include-group = "lint" }]
+
+        [build-system]
+        requires = ["hatchling"]
+        build-backend = "hatchling.build"
+
+        [tool.uv]
+        dev-dependencies = ["ipython"]
+        find-links = ["./wheels"]
+
+        [tool.uv.sources]
+        torch = { index = "pytorch" }
+        httpx = { git = "https://github.com/encode/httpx", tag = "0.27.0" }
+        lib = { path = "../lib", editable = true }
+
+        [[tool.uv.index]]
+        name = "pytorch"
+        url = "https://download.pytorch.org/whl/cpu"
+        explicit = true
+        "#;
+
+        let project = import(source).unwrap();
+        assert_eq!(project.name.as_deref(), Some("my-project"));
+        assert_eq!(
+            project.requires_python,
+            Some(VersionSpecifiers::from_str(">=3.11").unwrap())
+        );
+        assert_eq!(
+            project
+                .dependencies
+                .iter()
+                .map(|(name, requirement)| format!("{} = {requirement}", name.as_source()))
+                .collect::<Vec<_>>(),
+            vec![
+                r#"requests = ">=2.31""#,
+                r#"torch = { version = "==2.2.0", index = "https://download.pytorch.org/whl/cpu" }"#,
+                r#"httpx = { git = "https://github.com/encode/httpx", tag = "0.27.0", extras = ["http2"] }"#,
+                r#"lib = { path = "../lib", editable = true }"#,
+                r#"my-project = { path = ".", editable = true }"#,
+            ]
+        );
+        assert_eq!(
+            project.groups.keys().collect::<Vec<_>>(),
+            vec!["dev", "test", "lint", "ci"]
+        );
+        assert_eq!(
+            project.groups["ci"]
+                .keys()
+                .map(|name| name.as_source())
+                .collect::<Vec<_>>(),
+            vec!["pytest", "ruff"]
+        );
+        assert_eq!(project.pypi_options.index_url, None);
+        assert_eq!(project.pypi_options.extra_index_urls, None);
+        assert_eq!(
+            project.pypi_options.find_links,
+            Some(vec![FindLinksUrlOrPath::Path(PathBuf::from("./wheels"))])
+        );
+    }
+}
@@ -1,12 +1,15 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
-use serde::{Deserialize, Serialize};
-use serde_with::{formats::PreferMany, serde_as, OneOrMany};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_with::{formats::PreferMany, serde_as, DurationSeconds, OneOrMany};
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use toml_edit::{Array, Item, Table, Value};
 
+use crate::EnvironmentName;
+
 /// Represents a task name
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct TaskName(String);
@@ -53,8 +56,8 @@ pub enum Task {
 }
 
 impl Task {
-    /// Returns the names of the task that this task depends on
-    pub fn depends_on(&self) -> &[TaskName] {
+    /// Returns the tasks that this task depends on
+    pub fn depends_on(&self) -> &[Dependency] {
         match self {
             Task::Plain(_) | Task::Custom(_) => &[],
             Task::Execute(cmd) => &cmd.depends_on,
@@ -114,6 +117,15 @@ impl Task {
         }
     }
 
+    /// Returns the arguments that this task accepts, or `None` if the task
+    /// does not declare any arguments.
+    pub fn args(&self) -> Option<&[TaskArg]> {
+        match self {
+            Task::Execute(exe) => exe.args.as_deref(),
+            Task::Plain(_) | Task::Custom(_) | Task::Alias(_) => None,
+        }
+    }
+
     /// Returns the environment variables for the task to run in.
     pub fn env(&self) -> Option<&IndexMap<String, String>> {
         match self {
@@ -149,6 +161,46 @@ impl Task {
         matches!(self, Task::Custom(_))
     }
 
+    /// Returns the sandbox the task should run in, if any.
+    pub fn sandbox(&self) -> Option<&Sandbox> {
+        match self {
+            Task::Plain(_) => None,
+            Task::Custom(_) => None,
+            Task::Execute(execute) => execute.sandbox.as_ref(),
+            Task::Alias(_) => None,
+        }
+    }
+
+    /// Returns the maximum time the task can run.
+    pub fn timeout(&self) -> Option<Duration> {
+        match self {
+            Task::Plain(_) => None,
+            Task::Custom(_) => None,
+            Task::Execute(execute) => execute.timeout,
+            Task::Alias(_) => None,
+        }
+    }
+
+    /// Returns the number of times the task is retried when it fails.
+    pub fn retries(&self) -> u32 {
+        match self {
+            Task::Plain(_) => 0,
+            Task::Custom(_) => 0,
+            Task::Execute(execute) => execute.retries,
+            Task::Alias(_) => 0,
+        }
+    }
+
+    /// Returns the resource limits of the processes of the task, if any.
+    pub fn limits(&self) -> Option<&TaskLimits> {
+        match self {
+            Task::Plain(_) => None,
+            Task::Custom(_) => None,
+            Task::Execute(execute) => execute.limits.as_ref(),
+            Task::Alias(_) => None,
+        }
+    }
+
     /// True if this task is meant to run in a clean environment, stripped of all non required variables.
     pub fn clean_env(&self) -> bool {
         match self {
@@ -179,7 +231,11 @@ pub struct Execute {
     // BREAK: Make the remove the alias and force kebab-case
     #[serde(default, alias = "depends_on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<Dependency>,
+
+    /// The arguments that can be passed to this task. The values of the
+    /// arguments can be used in the command through `{{ name }}` placeholders.
+    pub args: Option<Vec<TaskArg>>,
 
     /// The working directory for the command relative to the root of the project.
     pub cwd: Option<PathBuf>,
@@ -193,6 +249,100 @@ pub struct Execute {
     /// Isolate the task from the running machine
     #[serde(default)]
     pub clean_env: bool,
+
+    /// Restrict the filesystem and network access of the command
+    pub sandbox: Option<Sandbox>,
+
+    /// The maximum time in seconds the command can run, the processes of the
+    /// command are killed when it takes longer
+    #[serde(default)]
+    #[serde_as(deserialize_as = "Option<DurationSeconds<u64>>")]
+    pub timeout: Option<Duration>,
+
+    /// The number of times the command is retried when it fails or times out
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Resource limits for the processes of the command
+    pub limits: Option<TaskLimits>,
+}
+
+/// Resource limits that apply to every process of a task.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TaskLimits {
+    /// The maximum virtual memory of a process in megabytes.
+    pub memory: Option<u64>,
+
+    /// The maximum cpu time of a process in seconds.
+    #[serde(default, deserialize_with = "deserialize_cpu_time")]
+    pub cpu_time: Option<Duration>,
+}
+
+/// Deserializes the cpu time limit in seconds. A limit of zero seconds would
+/// kill every process of the task as soon as it starts, so it is rejected.
+fn deserialize_cpu_time<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<u64>::deserialize(deserializer)? {
+        Some(0) => Err(serde::de::Error::custom(
+            "`cpu-time` must be at least 1 second",
+        )),
+        seconds => Ok(seconds.map(Duration::from_secs)),
+    }
+}
+
+impl From<TaskLimits> for Value {
+    fn from(limits: TaskLimits) -> Self {
+        let mut table = Table::new().into_inline_table();
+        if let Some(memory) = limits.memory {
+            table.insert("memory", (memory as i64).into());
+        }
+        if let Some(cpu_time) = limits.cpu_time {
+            table.insert("cpu-time", (cpu_time.as_secs() as i64).into());
+        }
+        Value::InlineTable(table)
+    }
+}
+
+/// The filesystem and network access that is allowed for a sandboxed task.
+/// Accessing anything else fails.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Sandbox {
+    /// Paths that can be read, relative to the root of the project.
+    #[serde(default)]
+    pub read: Vec<PathBuf>,
+
+    /// Paths that can be read and written, relative to the root of the project.
+    #[serde(default)]
+    pub write: Vec<PathBuf>,
+
+    /// Whether the network can be accessed.
+    #[serde(default)]
+    pub network: bool,
+}
+
+impl From<Sandbox> for Value {
+    fn from(sandbox: Sandbox) -> Self {
+        let paths = |paths: Vec<PathBuf>| {
+            Value::Array(Array::from_iter(
+                paths
+                    .into_iter()
+                    .map(|path| path.to_string_lossy().into_owned()),
+            ))
+        };
+        let mut table = Table::new().into_inline_table();
+        if !sandbox.read.is_empty() {
+            table.insert("read", paths(sandbox.read));
+        }
+        if !sandbox.write.is_empty() {
+            table.insert("write", paths(sandbox.write));
+        }
+        table.insert("network", sandbox.network.into());
+        Value::InlineTable(table)
+    }
 }
 
 impl From<Execute> for Task {
@@ -255,6 +405,183 @@ impl CmdArgs {
     }
 }
 
+/// A dependency of a task on another task.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Dependency {
+    /// The name of the task that is depended on
+    pub task_name: TaskName,
+
+    /// The values of the arguments that are passed to the task, in the order
+    /// that the task declares its arguments.
+    pub args: Option<Vec<String>>,
+
+    /// The environment to run the task in. If this is `None` the environment
+    /// is determined the same way as for a task passed on the command line.
+    pub environment: Option<EnvironmentName>,
+}
+
+impl Dependency {
+    /// Constructs a new dependency on a task without passing any arguments.
+    pub fn new(task_name: TaskName) -> Self {
+        Self {
+            task_name,
+            args: None,
+            environment: None,
+        }
+    }
+}
+
+impl From<TaskName> for Dependency {
+    fn from(task_name: TaskName) -> Self {
+        Self::new(task_name)
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(task_name: &str) -> Self {
+        Self::new(TaskName::from(task_name))
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.args {
+            Some(args) if !args.is_empty() => write!(
+                f,
+                "{}({})",
+                self.task_name,
+                args.iter().map(|arg| quote(arg)).format(", ")
+            )?,
+            _ => write!(f, "{}", self.task_name)?,
+        }
+        if let Some(environment) = &self.environment {
+            write!(f, " in {}", environment)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Dependency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct TomlDependency {
+            task: TaskName,
+            args: Option<Vec<String>>,
+            environment: Option<EnvironmentName>,
+        }
+
+        serde_untagged::UntaggedEnumVisitor::new()
+            .string(|name| Ok(Dependency::new(TaskName::from(name))))
+            .map(|map| {
+                let TomlDependency {
+                    task,
+                    args,
+                    environment,
+                } = map.deserialize()?;
+                Ok(Dependency {
+                    task_name: task,
+                    args,
+                    environment,
+                })
+            })
+            .expecting("a task name or a table with a `task` key")
+            .deserialize(deserializer)
+    }
+}
+
+/// An argument that can be passed to a task.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TaskArg {
+    /// The name of the argument, this is the name of the placeholder in the
+    /// command, e.g. `{{ name }}`.
+    pub name: String,
+
+    /// The value that is used when no value for the argument is passed.
+    pub default: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for TaskArg {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct TomlTaskArg {
+            arg: String,
+            default: Option<String>,
+        }
+
+        fn validate_name<E: serde::de::Error>(name: String) -> Result<String, E> {
+            let mut chars = name.chars();
+            let is_valid = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if is_valid {
+                Ok(name)
+            } else {
+                Err(E::custom(format!(
+                    "'{name}' is not a valid argument name, argument names can only contain letters, digits and underscores and cannot start with a digit"
+                )))
+            }
+        }
+
+        serde_untagged::UntaggedEnumVisitor::new()
+            .string(|name| {
+                Ok(TaskArg {
+                    name: validate_name(name.to_string())?,
+                    default: None,
+                })
+            })
+            .map(|map| {
+                let TomlTaskArg { arg, default } = map.deserialize()?;
+                Ok(TaskArg {
+                    name: validate_name(arg)?,
+                    default,
+                })
+            })
+            .expecting("an argument name or a table with an `arg` key")
+            .deserialize(deserializer)
+    }
+}
+
+impl From<TaskArg> for Value {
+    fn from(arg: TaskArg) -> Self {
+        match arg.default {
+            Some(default) => {
+                let mut table = Table::new().into_inline_table();
+                table.insert("arg", arg.name.into());
+                table.insert("default", default.into());
+                Value::InlineTable(table)
+            }
+            None => arg.name.into(),
+        }
+    }
+}
+
+impl From<Dependency> for Value {
+    fn from(dependency: Dependency) -> Self {
+        if dependency.args.is_none() && dependency.environment.is_none() {
+            return String::from(dependency.task_name).into();
+        }
+
+        let mut table = Table::new().into_inline_table();
+        table.insert("task", String::from(dependency.task_name).into());
+        if let Some(args) = dependency.args {
+            table.insert("args", Value::Array(Array::from_iter(args)));
+        }
+        if let Some(environment) = dependency.environment {
+            table.insert("environment", environment.to_string().into());
+        }
+        Value::InlineTable(table)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde_as]
@@ -262,7 +589,7 @@ pub struct Alias {
     /// A list of commands that should be run before this one
     #[serde(alias = "depends-on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<Dependency>,
 
     /// A description of the task.
     pub description: Option<String>,
@@ -327,6 +654,23 @@ pub fn quote(in_str: &str) -> Cow<str> {
     }
 }
 
+/// Quotes a string so our shell implementation passes it as a single word
+/// without interpreting any of its characters. Unlike [`quote`], this also
+/// quotes variables and other characters with a special meaning, so it is
+/// used for values that are not part of a command, like the values of task
+/// arguments. Strings that only consist of characters without a special
+/// meaning are left as they are.
+pub fn quote_literal(in_str: &str) -> Cow<str> {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c);
+    if !in_str.is_empty() && in_str.chars().all(is_plain) {
+        in_str.into()
+    } else {
+        // A single quote can't be escaped within single quotes, so it is closed,
+        // followed by a double quoted single quote and opened again.
+        format!("'{}'", in_str.replace('\'', r#"'"'"'"#)).into()
+    }
+}
+
 impl From<Task> for Item {
     fn from(value: Task) -> Self {
         match value {
@@ -345,14 +689,16 @@ impl From<Task> for Item {
                     table.insert(
                         "depends-on",
                         Value::Array(Array::from_iter(
-                            process
-                                .depends_on
-                                .into_iter()
-                                .map(String::from)
-                                .map(Value::from),
+                            process.depends_on.into_iter().map(Value::from),
                         )),
                     );
                 }
+                if let Some(args) = process.args {
+                    table.insert(
+                        "args",
+                        Value::Array(Array::from_iter(args.into_iter().map(Value::from))),
+                    );
+                }
                 if let Some(cwd) = process.cwd {
                     table.insert("cwd", cwd.to_string_lossy().to_string().into());
                 }
@@ -362,6 +708,18 @@ impl From<Task> for Item {
                 if let Some(description) = process.description {
                     table.insert("description", description.into());
                 }
+                if let Some(sandbox) = process.sandbox {
+                    table.insert("sandbox", sandbox.into());
+                }
+                if let Some(timeout) = process.timeout {
+                    table.insert("timeout", (timeout.as_secs() as i64).into());
+                }
+                if process.retries > 0 {
+                    table.insert("retries", i64::from(process.retries).into());
+                }
+                if let Some(limits) = process.limits {
+                    table.insert("limits", limits.into());
+                }
                 Item::Value(Value::InlineTable(table))
             }
             Task::Alias(alias) => {
@@ -369,11 +727,7 @@ impl From<Task> for Item {
                 table.insert(
                     "depends-on",
                     Value::Array(Array::from_iter(
-                        alias
-                            .depends_on
-                            .into_iter()
-                            .map(String::from)
-                            .map(Value::from),
+                        alias.depends_on.into_iter().map(Value::from),
                     )),
                 );
                 Item::Value(Value::InlineTable(table))
@@ -385,7 +739,9 @@ impl From<Task> for Item {
 
 #[cfg(test)]
 mod tests {
-    use super::quote;
+    use std::{path::PathBuf, time::Duration};
+
+    use super::{quote, quote_literal, Dependency, Execute, TaskArg};
 
     #[test]
     fn test_quote() {
@@ -401,4 +757,116 @@ mod tests {
         );
         assert_eq!(quote("name=[64,64]"), "\"name=[64,64]\"");
     }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("foobar"), "foobar");
+        assert_eq!(quote_literal("src/main.rs"), "src/main.rs");
+        assert_eq!(quote_literal(""), "''");
+        assert_eq!(quote_literal("foo bar"), "'foo bar'");
+        assert_eq!(quote_literal("$PATH"), "'$PATH'");
+        assert_eq!(quote_literal("a; rm -rf b"), "'a; rm -rf b'");
+        assert_eq!(quote_literal("it's"), r#"'it'"'"'s'"#);
+    }
+
+    #[test]
+    fn test_deserialize_args() {
+        let execute: Execute = toml_edit::de::from_str(
+            r#"
+            cmd = "echo {{ name }} {{ greeting }}"
+            args = ["name", { arg = "greeting", default = "hello" }]
+            depends-on = ["build", { task = "setup", args = ["{{ name }}"] }, { task = "lint", environment = "lint" }]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            execute.args.unwrap(),
+            vec![
+                TaskArg {
+                    name: String::from("name"),
+                    default: None
+                },
+                TaskArg {
+                    name: String::from("greeting"),
+                    default: Some(String::from("hello"))
+                },
+            ]
+        );
+        assert_eq!(
+            execute.depends_on,
+            vec![
+                Dependency::from("build"),
+                Dependency {
+                    task_name: "setup".into(),
+                    args: Some(vec![String::from("{{ name }}")]),
+                    environment: None,
+                },
+                Dependency {
+                    task_name: "lint".into(),
+                    args: None,
+                    environment: Some("lint".parse().unwrap()),
+                },
+            ]
+        );
+
+        assert!(toml_edit::de::from_str::<Execute>(
+            r#"
+            cmd = "echo"
+            args = ["1name"]
+            "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_deserialize_sandbox() {
+        let execute: Execute = toml_edit::de::from_str(
+            r#"
+            cmd = "make"
+            sandbox = { read = ["src"], write = ["build"] }
+            "#,
+        )
+        .unwrap();
+
+        let sandbox = execute.sandbox.unwrap();
+        assert_eq!(sandbox.read, vec![PathBuf::from("src")]);
+        assert_eq!(sandbox.write, vec![PathBuf::from("build")]);
+        assert!(!sandbox.network);
+
+        assert!(toml_edit::de::from_str::<Execute>(
+            r#"
+            cmd = "make"
+            sandbox = { exec = ["bin"] }
+            "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_deserialize_limits() {
+        let execute: Execute = toml_edit::de::from_str(
+            r#"
+            cmd = "pytest"
+            timeout = 600
+            retries = 2
+            limits = { memory = 2048, cpu-time = 300 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(execute.timeout, Some(Duration::from_secs(600)));
+        assert_eq!(execute.retries, 2);
+        let limits = execute.limits.unwrap();
+        assert_eq!(limits.memory, Some(2048));
+        assert_eq!(limits.cpu_time, Some(Duration::from_secs(300)));
+
+        assert!(toml_edit::de::from_str::<Execute>(
+            r#"
+            cmd = "pytest"
+            limits = { cpu-time = 0 }
+            "#,
+        )
+        .is_err());
+    }
 }
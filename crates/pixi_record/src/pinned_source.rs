@@ -123,12 +123,67 @@ pub struct PinnedUrlSpec {
     pub md5: Option<Md5Hash>,
 }
 
+impl PinnedUrlSpec {
+    /// Converts this instance into the url that is stored in the lock-file.
+    ///
+    /// The hashes of the archive are stored in the fragment of the url, e.g.
+    /// `https://example.com/package-1.0.tar.gz#sha256=<hash>&md5=<hash>`.
+    pub fn into_locked_url(self) -> Url {
+        let mut url = self.url;
+        let mut fragment = format!("sha256={:x}", self.sha256);
+        if let Some(md5) = self.md5 {
+            fragment.push_str(&format!("&md5={:x}", md5));
+        }
+        url.set_fragment(Some(&fragment));
+        url
+    }
+
+    /// Parses a url that was created with [`Self::into_locked_url`].
+    pub fn from_locked_url(mut url: Url) -> Result<Self, ParseError> {
+        let mut sha256 = None;
+        let mut md5 = None;
+        for (key, value) in url
+            .fragment()
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+        {
+            match key {
+                "sha256" => {
+                    sha256 = Some(
+                        rattler_digest::parse_digest_from_hex::<rattler_digest::Sha256>(value)
+                            .ok_or_else(|| ParseError::InvalidUrlHash(url.clone(), "sha256"))?,
+                    )
+                }
+                "md5" => {
+                    md5 = Some(
+                        rattler_digest::parse_digest_from_hex::<rattler_digest::Md5>(value)
+                            .ok_or_else(|| ParseError::InvalidUrlHash(url.clone(), "md5"))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        let Some(sha256) = sha256 else {
+            return Err(ParseError::MissingUrlHash(url));
+        };
+        url.set_fragment(None);
+
+        Ok(Self { url, sha256, md5 })
+    }
+}
+
 impl From<PinnedUrlSpec> for PinnedSourceSpec {
     fn from(value: PinnedUrlSpec) -> Self {
         PinnedSourceSpec::Url(value)
     }
 }
 
+/// The prefix of the scheme of a git url in the lock-file, e.g.
+/// `git+https://github.com/prefix-dev/pixi`.
+const GIT_URL_SCHEME_PREFIX: &str = "git+";
+
 /// A pinned version of a git checkout.
 #[derive(Debug, Clone)]
 pub struct PinnedGitSpec {
@@ -137,6 +192,60 @@ pub struct PinnedGitSpec {
     pub rev: Option<GitReference>,
 }
 
+impl PinnedGitSpec {
+    /// Converts this instance into the url that is stored in the lock-file.
+    ///
+    /// The url has the form `git+<url>?<branch|tag|rev>=<name>#<commit>`, the
+    /// query is only present if a reference was requested.
+    pub fn into_locked_url(self) -> Url {
+        let mut url = Url::parse(&format!("{GIT_URL_SCHEME_PREFIX}{}", self.git))
+            .expect("prefixing a valid url with git+ results in a valid url");
+        if let Some(rev) = &self.rev {
+            let (key, value) = match rev {
+                GitReference::Branch(branch) => ("branch", branch),
+                GitReference::Tag(tag) => ("tag", tag),
+                GitReference::Rev(rev) => ("rev", rev),
+            };
+            url.query_pairs_mut().append_pair(key, value);
+        }
+        url.set_fragment(Some(&self.commit));
+        url
+    }
+
+    /// Parses a url that was created with [`Self::into_locked_url`].
+    pub fn from_locked_url(url: Url) -> Result<Self, ParseError> {
+        let commit = match url.fragment() {
+            Some(commit) if !commit.is_empty() => commit.to_string(),
+            _ => return Err(ParseError::MissingGitCommit(url)),
+        };
+
+        let mut git = Url::parse(
+            url.as_str()
+                .strip_prefix(GIT_URL_SCHEME_PREFIX)
+                .unwrap_or(url.as_str()),
+        )
+        .map_err(|e| ParseError::InvalidGitUrl(url.clone(), e))?;
+        git.set_fragment(None);
+
+        let mut rev = None;
+        let mut other_pairs = Vec::new();
+        for (key, value) in git.query_pairs() {
+            match key.as_ref() {
+                "branch" => rev = Some(GitReference::Branch(value.into_owned())),
+                "tag" => rev = Some(GitReference::Tag(value.into_owned())),
+                "rev" => rev = Some(GitReference::Rev(value.into_owned())),
+                _ => other_pairs.push((key.into_owned(), value.into_owned())),
+            }
+        }
+        git.set_query(None);
+        if !other_pairs.is_empty() {
+            git.query_pairs_mut().extend_pairs(other_pairs);
+        }
+
+        Ok(Self { git, commit, rev })
+    }
+}
+
 impl From<PinnedGitSpec> for PinnedSourceSpec {
     fn from(value: PinnedGitSpec) -> Self {
         PinnedSourceSpec::Git(value)
@@ -184,26 +293,41 @@ impl From<PinnedPathSpec> for UrlOrPath {
 }
 
 impl From<PinnedGitSpec> for UrlOrPath {
-    fn from(_value: PinnedGitSpec) -> Self {
-        unimplemented!()
+    fn from(value: PinnedGitSpec) -> Self {
+        UrlOrPath::Url(value.into_locked_url())
     }
 }
 
 impl From<PinnedUrlSpec> for UrlOrPath {
-    fn from(_value: PinnedUrlSpec) -> Self {
-        unimplemented!()
+    fn from(value: PinnedUrlSpec) -> Self {
+        UrlOrPath::Url(value.into_locked_url())
     }
 }
 
 #[derive(Debug, Error)]
-pub enum ParseError {}
+pub enum ParseError {
+    #[error("the git url '{0}' does not contain a commit hash")]
+    MissingGitCommit(Url),
+
+    #[error("the git url '{0}' is invalid")]
+    InvalidGitUrl(Url, #[source] url::ParseError),
+
+    #[error("the url '{0}' does not contain a sha256 hash")]
+    MissingUrlHash(Url),
+
+    #[error("the url '{0}' contains an invalid {1} hash")]
+    InvalidUrlHash(Url, &'static str),
+}
 
 impl TryFrom<UrlOrPath> for PinnedSourceSpec {
     type Error = ParseError;
 
     fn try_from(value: UrlOrPath) -> Result<Self, Self::Error> {
         match value {
-            UrlOrPath::Url(_) => unimplemented!(),
+            UrlOrPath::Url(url) if url.scheme().starts_with(GIT_URL_SCHEME_PREFIX) => {
+                Ok(PinnedGitSpec::from_locked_url(url)?.into())
+            }
+            UrlOrPath::Url(url) => Ok(PinnedUrlSpec::from_locked_url(url)?.into()),
             UrlOrPath::Path(path) => Ok(PinnedPathSpec { path }.into()),
         }
     }
@@ -332,15 +456,15 @@ impl Display for PinnedSourceSpec {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             PinnedSourceSpec::Path(spec) => write!(f, "{}", spec.path),
-            PinnedSourceSpec::Url(spec) => write!(f, "{}", spec.url),
-            PinnedSourceSpec::Git(spec) => write!(f, "{}", spec.git),
+            PinnedSourceSpec::Url(spec) => write!(f, "{}", spec),
+            PinnedSourceSpec::Git(spec) => write!(f, "{}", spec),
         }
     }
 }
 
 impl Display for PinnedUrlSpec {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.url)
+        write!(f, "{}@{:x}", self.url, self.sha256)
     }
 }
 
@@ -355,3 +479,65 @@ impl Display for PinnedGitSpec {
         write!(f, "{}@{}", self.git, self.commit)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pixi_spec::GitReference;
+    use url::Url;
+
+    use super::{PinnedGitSpec, PinnedUrlSpec};
+
+    #[test]
+    fn test_locked_git_url_roundtrip() {
+        for rev in [
+            None,
+            Some(GitReference::Branch("main".to_string())),
+            Some(GitReference::Tag("v1.0.0".to_string())),
+            Some(GitReference::Rev("abcdef".to_string())),
+        ] {
+            let spec = PinnedGitSpec {
+                git: Url::parse("https://github.com/prefix-dev/pixi?subdirectory=foo").unwrap(),
+                commit: "0123456789abcdef0123456789abcdef01234567".to_string(),
+                rev,
+            };
+            let url = spec.clone().into_locked_url();
+            assert!(url.as_str().starts_with("git+https://"), "{url}");
+            let parsed = PinnedGitSpec::from_locked_url(url).unwrap();
+            assert_eq!(parsed.git, spec.git);
+            assert_eq!(parsed.commit, spec.commit);
+            assert_eq!(parsed.rev, spec.rev);
+        }
+    }
+
+    #[test]
+    fn test_locked_git_url_without_commit() {
+        let url = Url::parse("git+https://github.com/prefix-dev/pixi?branch=main").unwrap();
+        assert!(PinnedGitSpec::from_locked_url(url).is_err());
+    }
+
+    #[test]
+    fn test_locked_url_roundtrip() {
+        let sha256 = rattler_digest::parse_digest_from_hex::<rattler_digest::Sha256>(
+            "90553586879bf328f2f9efb8d8faa958ecba822faf379f0a20c3461467b9b955",
+        )
+        .unwrap();
+        let md5 = rattler_digest::parse_digest_from_hex::<rattler_digest::Md5>(
+            "defd5d375853a2caff36a19d2d81a28e",
+        )
+        .unwrap();
+        for md5 in [None, Some(md5)] {
+            let spec = PinnedUrlSpec {
+                url: Url::parse("https://example.com/package-0.1.0.tar.gz").unwrap(),
+                sha256,
+                md5,
+            };
+            let parsed = PinnedUrlSpec::from_locked_url(spec.clone().into_locked_url()).unwrap();
+            assert_eq!(parsed.url, spec.url);
+            assert_eq!(parsed.sha256, spec.sha256);
+            assert_eq!(parsed.md5, spec.md5);
+        }
+
+        let url = Url::parse("https://example.com/package-0.1.0.tar.gz").unwrap();
+        assert!(PinnedUrlSpec::from_locked_url(url).is_err());
+    }
+}
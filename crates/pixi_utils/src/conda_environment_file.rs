@@ -1,11 +1,13 @@
+use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::{Context, Diagnostic, IntoDiagnostic, NamedSource, SourceSpan};
 use pixi_config::Config;
 use rattler_conda_types::{MatchSpec, NamedChannelOrUrl, ParseStrictness::Lenient};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::path::PathBuf;
 use std::{io::BufRead, path::Path, str::FromStr};
 use thiserror::Error;
+use url::Url;
 
 #[derive(Debug, Error)]
 #[error("Failed to parse '{path}' as a conda environment file")]
@@ -56,6 +58,33 @@ pub struct CondaEnvFile {
     #[serde(default)]
     channels: Vec<NamedChannelOrUrl>,
     dependencies: Vec<CondaEnvDep>,
+    #[serde(default, deserialize_with = "deserialize_variables")]
+    variables: IndexMap<String, String>,
+}
+
+/// The environment variables are usually strings, but numbers and booleans
+/// are also accepted by conda.
+fn deserialize_variables<'de, D>(deserializer: D) -> Result<IndexMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    IndexMap::<String, serde_yaml::Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_yaml::Value::String(value) => value,
+                serde_yaml::Value::Number(value) => value.to_string(),
+                serde_yaml::Value::Bool(value) => value.to_string(),
+                serde_yaml::Value::Null => String::new(),
+                _ => {
+                    return Err(serde::de::Error::custom(format!(
+                        "the value of variable '{key}' must be a string"
+                    )))
+                }
+            };
+            Ok((key, value))
+        })
+        .collect()
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -65,6 +94,35 @@ pub enum CondaEnvDep {
     Pip { pip: Vec<String> },
 }
 
+/// A local package that is installed from the `pip` section, either as a
+/// plain path or with `-e`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipPathDependency {
+    /// The name from the `#egg=` fragment, if any.
+    pub name: Option<String>,
+    pub path: PathBuf,
+    pub editable: bool,
+}
+
+/// The options and local packages of the `pip` sections of an environment
+/// file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipOptions {
+    pub index_url: Option<Url>,
+    pub extra_index_urls: Vec<Url>,
+    pub find_links: Vec<String>,
+    pub path_dependencies: Vec<PipPathDependency>,
+}
+
+/// A single line of a `pip` section.
+enum PipLine {
+    Requirement(pep508_rs::Requirement),
+    IndexUrl(Url),
+    ExtraIndexUrl(Url),
+    FindLinks(String),
+    Path(PipPathDependency),
+}
+
 type ParsedDependencies = (
     Vec<MatchSpec>,
     Vec<pep508_rs::Requirement>,
@@ -84,6 +142,31 @@ impl CondaEnvFile {
         &self.dependencies
     }
 
+    /// Returns the environment variables that are set when the environment is
+    /// activated.
+    pub fn variables(&self) -> &IndexMap<String, String> {
+        &self.variables
+    }
+
+    /// Returns the index urls, find-links and local packages of the `pip`
+    /// sections.
+    pub fn pip_options(&self) -> miette::Result<PipOptions> {
+        let mut options = PipOptions::default();
+        for line in self.dependencies.iter().flat_map(|dep| match dep {
+            CondaEnvDep::Pip { pip } => pip.as_slice(),
+            CondaEnvDep::Conda(_) => &[],
+        }) {
+            match parse_pip_line(line)? {
+                Some(PipLine::IndexUrl(url)) => options.index_url = Some(url),
+                Some(PipLine::ExtraIndexUrl(url)) => options.extra_index_urls.push(url),
+                Some(PipLine::FindLinks(location)) => options.find_links.push(location),
+                Some(PipLine::Path(dependency)) => options.path_dependencies.push(dependency),
+                Some(PipLine::Requirement(_)) | None => {}
+            }
+        }
+        Ok(options)
+    }
+
     pub fn from_path(path: &Path) -> miette::Result<Self> {
         let file = fs_err::File::open(path).into_diagnostic()?;
         let reader = std::io::BufReader::new(file);
@@ -155,21 +238,97 @@ fn parse_dependencies(deps: Vec<CondaEnvDep>) -> miette::Result<ParsedDependenci
                 }
                 conda_deps.push(match_spec);
             }
-            CondaEnvDep::Pip { pip } => pip_deps.extend(
-                pip.iter()
-                    .map(|dep| {
-                        pep508_rs::Requirement::from_str(dep)
-                            .into_diagnostic()
-                            .wrap_err(format!("Can't parse '{}' as pypi dependency", dep))
-                    })
-                    .collect::<miette::Result<Vec<_>>>()?,
-            ),
+            CondaEnvDep::Pip { pip } => {
+                for line in pip {
+                    if let Some(PipLine::Requirement(requirement)) = parse_pip_line(&line)? {
+                        pip_deps.push(requirement);
+                    }
+                }
+            }
         }
     }
 
     Ok((conda_deps, pip_deps, picked_up_channels))
 }
 
+/// Parses a line of a `pip` section, this is either a requirement or one of
+/// the options pip supports in a requirements file. Returns `None` for
+/// options that are not supported.
+fn parse_pip_line(line: &str) -> miette::Result<Option<PipLine>> {
+    let line = line.trim();
+    if !line.starts_with('-') {
+        if line.starts_with('.') || line.starts_with('/') {
+            return Ok(Some(PipLine::Path(parse_pip_path(line, false))));
+        }
+        return parse_pip_requirement(line)
+            .map(|requirement| Some(PipLine::Requirement(requirement)));
+    }
+
+    // Options are either separated from their value by whitespace or by `=`.
+    let (option, value) = line
+        .split_once(|c: char| c.is_whitespace() || c == '=')
+        .map(|(option, value)| (option, value.trim()))
+        .unwrap_or((line, ""));
+    let parse_url = |value: &str| {
+        Url::parse(value)
+            .into_diagnostic()
+            .wrap_err(format!("Can't parse '{}' as index url", value))
+    };
+    let line = match option {
+        "-i" | "--index-url" => PipLine::IndexUrl(parse_url(value)?),
+        "--extra-index-url" => PipLine::ExtraIndexUrl(parse_url(value)?),
+        "-f" | "--find-links" => PipLine::FindLinks(value.to_string()),
+        "-e" | "--editable" if value.starts_with("git+") => {
+            tracing::warn!("Editable git installs are not supported, installing '{value}' as a regular pypi dependency");
+            PipLine::Requirement(parse_pip_requirement(value)?)
+        }
+        "-e" | "--editable" => PipLine::Path(parse_pip_path(value, true)),
+        _ => {
+            tracing::warn!("Skipping unsupported pip option: \"{}\"", line);
+            return Ok(None);
+        }
+    };
+    Ok(Some(line))
+}
+
+/// Parses a PEP 508 requirement. Also accepts the `git+https://...#egg=name`
+/// form without a name in front of the url.
+fn parse_pip_requirement(line: &str) -> miette::Result<pep508_rs::Requirement> {
+    let requirement = if line.starts_with("git+") {
+        let (url, name) = split_egg_fragment(line);
+        let name = name.ok_or_else(|| {
+            miette::miette!(
+                "Can't parse '{}' as pypi dependency, add the name of the package as '<name> @ {}'",
+                line,
+                line
+            )
+        })?;
+        format!("{name} @ {url}")
+    } else {
+        line.to_string()
+    };
+    pep508_rs::Requirement::from_str(&requirement)
+        .into_diagnostic()
+        .wrap_err(format!("Can't parse '{}' as pypi dependency", line))
+}
+
+fn parse_pip_path(value: &str, editable: bool) -> PipPathDependency {
+    let (path, name) = split_egg_fragment(value);
+    PipPathDependency {
+        name: name.map(ToString::to_string),
+        path: PathBuf::from(path),
+        editable,
+    }
+}
+
+/// Splits the `#egg=<name>` fragment of a pip url or path.
+fn split_egg_fragment(value: &str) -> (&str, Option<&str>) {
+    match value.split_once("#egg=") {
+        Some((value, name)) => (value, Some(name.split('&').next().unwrap_or(name))),
+        None => (value, None),
+    }
+}
+
 fn parse_channels(channels: Vec<NamedChannelOrUrl>) -> Vec<NamedChannelOrUrl> {
     let mut new_channels = vec![];
     for channel in channels {
@@ -303,6 +462,82 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_conda_env_file_with_pip_options_and_variables() {
+        let example_conda_env_file = r#"
+        name: pixi_example_project
+        channels:
+          - conda-forge
+        dependencies:
+          - python
+          - pip:
+            - --index-url https://pypi.example.com/simple
+            - --extra-index-url=https://download.pytorch.org/whl/cpu
+            - --find-links ./wheels
+            - -e ./my-package
+            - ./other-package#egg=other
+            - foo @ git+https://github.com/example/foo.git@v1.0
+            - git+https://github.com/example/bar.git@main#egg=bar
+            - requests
+        variables:
+          MY_VAR: some value
+          DEBUG: 1
+        "#;
+
+        let f = tempfile::NamedTempFile::new().unwrap();
+        let path = f.path();
+        let mut file = fs_err::File::create(path).unwrap();
+        file.write_all(example_conda_env_file.as_bytes()).unwrap();
+
+        let conda_env_file_data = CondaEnvFile::from_path(path).unwrap();
+        assert_eq!(
+            conda_env_file_data
+                .variables()
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("MY_VAR", "some value"), ("DEBUG", "1")]
+        );
+
+        assert_eq!(
+            conda_env_file_data.pip_options().unwrap(),
+            PipOptions {
+                index_url: Some(Url::parse("https://pypi.example.com/simple").unwrap()),
+                extra_index_urls: vec![Url::parse("https://download.pytorch.org/whl/cpu").unwrap()],
+                find_links: vec!["./wheels".to_string()],
+                path_dependencies: vec![
+                    PipPathDependency {
+                        name: None,
+                        path: PathBuf::from("./my-package"),
+                        editable: true,
+                    },
+                    PipPathDependency {
+                        name: Some("other".to_string()),
+                        path: PathBuf::from("./other-package"),
+                        editable: false,
+                    },
+                ],
+            }
+        );
+
+        let (_, pip_deps, _) =
+            parse_dependencies(conda_env_file_data.dependencies().clone()).unwrap();
+        assert_eq!(
+            pip_deps,
+            vec![
+                pep508_rs::Requirement::from_str(
+                    "foo @ git+https://github.com/example/foo.git@v1.0"
+                )
+                .unwrap(),
+                pep508_rs::Requirement::from_str(
+                    "bar @ git+https://github.com/example/bar.git@main"
+                )
+                .unwrap(),
+                pep508_rs::Requirement::from_str("requests").unwrap(),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_conda_env_file_with_explicit_pip_dep() {
         let example_conda_env_file = r#"
@@ -9,9 +9,11 @@ use rattler_networking::{
     OciMiddleware,
 };
 
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use http::Extensions;
+use reqwest::{Client, Request, Response};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
 use std::collections::HashMap;
+use url::Url;
 
 use pixi_config::Config;
 
@@ -21,7 +23,9 @@ pub fn default_retry_policy() -> ExponentialBackoff {
     ExponentialBackoff::builder().build_with_max_retries(3)
 }
 
-fn auth_middleware(config: &Config) -> Result<AuthenticationMiddleware, FileStorageError> {
+/// Returns the storage of the credentials used by pixi, this respects the
+/// authentication override file of the config.
+pub fn authentication_storage(config: &Config) -> Result<AuthenticationStorage, FileStorageError> {
     if let Some(auth_file) = config.authentication_override_file() {
         tracing::info!("Loading authentication from file: {:?}", auth_file);
 
@@ -34,10 +38,16 @@ fn auth_middleware(config: &Config) -> Result<AuthenticationMiddleware, FileStor
             authentication_storage::backends::file::FileStorage::new(PathBuf::from(&auth_file))?,
         ));
 
-        return Ok(AuthenticationMiddleware::new(store));
+        return Ok(store);
     }
 
-    Ok(AuthenticationMiddleware::default())
+    Ok(AuthenticationStorage::default())
+}
+
+fn auth_middleware(config: &Config) -> Result<AuthenticationMiddleware, FileStorageError> {
+    Ok(AuthenticationMiddleware::new(authentication_storage(
+        config,
+    )?))
 }
 
 pub fn mirror_middleware(config: &Config) -> MirrorMiddleware {
@@ -76,6 +86,31 @@ pub fn oci_middleware() -> OciMiddleware {
     OciMiddleware
 }
 
+/// The error returned for every request that is made in offline mode.
+#[derive(Debug, thiserror::Error)]
+#[error("cannot download '{url}' because pixi is running in offline mode and it is not available in the cache")]
+pub struct OfflineError {
+    pub url: Url,
+}
+
+/// A middleware that fails every request, everything that pixi needs has to
+/// come from the cache in offline mode.
+pub struct OfflineMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for OfflineMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        Err(reqwest_middleware::Error::middleware(OfflineError {
+            url: req.url().clone(),
+        }))
+    }
+}
+
 pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMiddleware) {
     let app_user_agent = format!("pixi/{}", consts::PIXI_VERSION);
 
@@ -101,6 +136,11 @@ pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMidd
 
     let mut client_builder = ClientBuilder::new(client.clone());
 
+    if config.offline() {
+        tracing::info!("Running in offline mode, all network requests are rejected");
+        client_builder = client_builder.with(OfflineMiddleware);
+    }
+
     if !config.mirror_map().is_empty() {
         client_builder = client_builder
             .with(mirror_middleware(&config))
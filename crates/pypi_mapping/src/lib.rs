@@ -8,6 +8,7 @@ use std::{
 use async_once_cell::OnceCell as AsyncCell;
 use custom_pypi_mapping::fetch_mapping_from_path;
 use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache, HttpCacheOptions};
+use itertools::Itertools;
 use pixi_config::get_cache_dir;
 use rattler_conda_types::{PackageRecord, PackageUrl, RepoDataRecord};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
@@ -135,29 +136,17 @@ impl PurlSource {
     }
 }
 
+/// Adds the purls of the conda packages that are also available on PyPI.
+///
+/// When `offline` is true the mapping is only read from the local http cache.
 pub async fn amend_pypi_purls(
     client: ClientWithMiddleware,
     mapping_source: &MappingSource,
     conda_packages: impl IntoIterator<Item = &mut RepoDataRecord>,
     reporter: Option<Arc<dyn Reporter>>,
+    offline: bool,
 ) -> miette::Result<()> {
-    // Construct a client with a retry policy and local caching
-    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-    let retry_strategy = RetryTransientMiddleware::new_with_policy(retry_policy);
-    let cache_strategy = Cache(HttpCache {
-        mode: CacheMode::Default,
-        manager: CACacheManager {
-            path: get_cache_dir()
-                .expect("missing cache directory")
-                .join(pixi_consts::consts::CONDA_PYPI_MAPPING_CACHE_DIR),
-        },
-        options: HttpCacheOptions::default(),
-    });
-
-    let client = ClientBuilder::from_client(client)
-        .with(cache_strategy)
-        .with(retry_strategy)
-        .build();
+    let client = mapping_client(client, offline);
 
     match mapping_source {
         MappingSource::Custom(mapping) => {
@@ -183,6 +172,146 @@ pub async fn amend_pypi_purls(
     Ok(())
 }
 
+/// Returns the names of the conda packages in the given channels that might
+/// provide the PyPI package, by looking up the PyPI name in the mappings in
+/// reverse. The names are ordered by the priority of the channels.
+///
+/// The hash mapping can only be consulted for concrete records, so the
+/// candidates should be verified by amending the purls of their records with
+/// [`amend_pypi_purls`].
+pub async fn conda_names_for_pypi_package(
+    client: ClientWithMiddleware,
+    mapping_source: &MappingSource,
+    channels: &[ChannelName],
+    pypi_name: &pep508_rs::PackageName,
+    offline: bool,
+) -> miette::Result<Vec<rattler_conda_types::PackageName>> {
+    let client = mapping_client(client, offline);
+    let custom_mapping = match mapping_source {
+        MappingSource::Custom(mapping) => Some(mapping.fetch_custom_mapping(&client).await?),
+        _ => None,
+    };
+
+    // The compressed mapping is only needed for conda-forge channels that don't
+    // have a mapping of their own.
+    let needs_compressed_mapping = !matches!(mapping_source, MappingSource::Disabled)
+        && channels.iter().any(|channel| {
+            let channel = channel.trim_end_matches('/');
+            is_conda_forge_channel(channel)
+                && !custom_mapping
+                    .as_ref()
+                    .is_some_and(|mapping| mapping.contains_key(channel))
+        });
+    let compressed_mapping = if needs_compressed_mapping {
+        Some(prefix_pypi_name_mapping::conda_pypi_name_compressed_mapping(&client).await?)
+    } else {
+        None
+    };
+
+    Ok(conda_names_in_mappings(
+        channels,
+        pypi_name,
+        custom_mapping.as_ref(),
+        compressed_mapping.as_ref(),
+    ))
+}
+
+/// Looks up the conda names of the PyPI package in the mappings of the
+/// channels, see [`conda_names_for_pypi_package`]. Conda-forge channels
+/// without a custom mapping use the compressed mapping, or the PyPI name
+/// itself when the compressed mapping is `None` because the mapping is
+/// disabled.
+fn conda_names_in_mappings(
+    channels: &[ChannelName],
+    pypi_name: &pep508_rs::PackageName,
+    custom_mapping: Option<&MappingByChannel>,
+    compressed_mapping: Option<&HashMap<String, Option<String>>>,
+) -> Vec<rattler_conda_types::PackageName> {
+    let mut names = Vec::new();
+    for channel in channels {
+        let channel = channel.trim_end_matches('/');
+        let is_conda_forge = is_conda_forge_channel(channel);
+
+        let mapping = match custom_mapping.and_then(|mapping| mapping.get(channel)) {
+            Some(mapping) => mapping,
+            // Without a mapping for the channel only conda-forge packages are
+            // assumed to be PyPI packages.
+            None if !is_conda_forge => continue,
+            None => match compressed_mapping {
+                Some(mapping) => mapping,
+                None => {
+                    names.extend(identity_conda_name(pypi_name));
+                    continue;
+                }
+            },
+        };
+
+        names.extend(reverse_lookup(mapping, pypi_name));
+        // A conda-forge package that is not in the mapping is assumed to have
+        // the same name as the PyPI package.
+        if is_conda_forge && !mapping.contains_key(pypi_name.as_ref()) {
+            names.extend(identity_conda_name(pypi_name));
+        }
+    }
+
+    names.into_iter().unique().collect()
+}
+
+/// Returns `true` if the channel name is the url of a conda-forge channel.
+fn is_conda_forge_channel(channel: &str) -> bool {
+    Url::parse(channel).map_or(false, |url| is_conda_forge_url(&url))
+}
+
+/// Returns the conda names that are mapped to the PyPI name.
+fn reverse_lookup(
+    mapping: &HashMap<String, Option<String>>,
+    pypi_name: &pep508_rs::PackageName,
+) -> Vec<rattler_conda_types::PackageName> {
+    mapping
+        .iter()
+        .filter(|(_, mapped)| {
+            mapped.as_deref().is_some_and(|mapped| {
+                pep508_rs::PackageName::from_str(mapped).is_ok_and(|mapped| &mapped == pypi_name)
+            })
+        })
+        .filter_map(|(conda_name, _)| rattler_conda_types::PackageName::from_str(conda_name).ok())
+        .sorted_by(|a, b| a.as_normalized().cmp(b.as_normalized()))
+        .collect()
+}
+
+/// Returns the conda package name that is identical to the PyPI name.
+fn identity_conda_name(
+    pypi_name: &pep508_rs::PackageName,
+) -> Option<rattler_conda_types::PackageName> {
+    rattler_conda_types::PackageName::from_str(pypi_name.as_ref()).ok()
+}
+
+/// Constructs a client with a retry policy and local caching to fetch the
+/// mappings with. When `offline` is true the mappings are only read from the
+/// cache.
+fn mapping_client(client: ClientWithMiddleware, offline: bool) -> ClientWithMiddleware {
+    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
+    let retry_strategy = RetryTransientMiddleware::new_with_policy(retry_policy);
+    let cache_strategy = Cache(HttpCache {
+        mode: if offline {
+            CacheMode::OnlyIfCached
+        } else {
+            CacheMode::Default
+        },
+        manager: CACacheManager {
+            path: get_cache_dir()
+                .expect("missing cache directory")
+                .join(pixi_consts::consts::CONDA_PYPI_MAPPING_CACHE_DIR),
+        },
+        options: HttpCacheOptions::default(),
+    });
+
+    ClientBuilder::from_client(client)
+        .with(cache_strategy)
+        .with(retry_strategy)
+        .build()
+}
+
 /// Returns `true` if the specified record refers to a conda-forge package.
 pub fn is_conda_forge_record(record: &RepoDataRecord) -> bool {
     record
@@ -211,3 +340,155 @@ pub fn build_pypi_purl_from_package_record(package_record: &PackageRecord) -> Op
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONDA_FORGE: &str = "https://conda.anaconda.org/conda-forge/";
+    const OTHER_CHANNEL: &str = "https://conda.anaconda.org/other/";
+
+    fn pypi_name(name: &str) -> pep508_rs::PackageName {
+        pep508_rs::PackageName::from_str(name).unwrap()
+    }
+
+    fn conda_names(names: &[&str]) -> Vec<rattler_conda_types::PackageName> {
+        names
+            .iter()
+            .map(|name| rattler_conda_types::PackageName::from_str(name).unwrap())
+            .collect()
+    }
+
+    fn mapping(entries: &[(&str, Option<&str>)]) -> HashMap<String, Option<String>> {
+        entries
+            .iter()
+            .map(|(conda, pypi)| (conda.to_string(), pypi.map(str::to_string)))
+            .collect()
+    }
+
+    fn client() -> ClientWithMiddleware {
+        reqwest::Client::new().into()
+    }
+
+    #[test]
+    fn test_reverse_lookup() {
+        let mapping = mapping(&[
+            ("pytorch", Some("torch")),
+            ("pytorch-cpu", Some("torch")),
+            ("numpy", Some("numpy")),
+            ("libtorch", None),
+        ]);
+        assert_eq!(
+            reverse_lookup(&mapping, &pypi_name("torch")),
+            conda_names(&["pytorch", "pytorch-cpu"])
+        );
+        // The PyPI names are compared normalized.
+        assert_eq!(
+            reverse_lookup(&mapping, &pypi_name("NumPy")),
+            conda_names(&["numpy"])
+        );
+        assert!(reverse_lookup(&mapping, &pypi_name("libtorch")).is_empty());
+    }
+
+    #[test]
+    fn test_identity_conda_name() {
+        assert_eq!(
+            identity_conda_name(&pypi_name("Requests")),
+            conda_names(&["requests"]).pop()
+        );
+        // The name is taken in its normalized form.
+        assert_eq!(
+            identity_conda_name(&pypi_name("typing_extensions")),
+            conda_names(&["typing-extensions"]).pop()
+        );
+    }
+
+    #[test]
+    fn test_conda_names_in_compressed_mapping() {
+        let compressed = mapping(&[("pytorch", Some("torch")), ("requests", Some("requests"))]);
+        let channels = [CONDA_FORGE.to_string()];
+
+        assert_eq!(
+            conda_names_in_mappings(&channels, &pypi_name("torch"), None, Some(&compressed)),
+            conda_names(&["pytorch"])
+        );
+        // A package that is not in the mapping has the same name on conda-forge.
+        assert_eq!(
+            conda_names_in_mappings(&channels, &pypi_name("rich"), None, Some(&compressed)),
+            conda_names(&["rich"])
+        );
+        // Other channels don't use the compressed mapping.
+        assert!(conda_names_in_mappings(
+            &[OTHER_CHANNEL.to_string()],
+            &pypi_name("torch"),
+            None,
+            Some(&compressed)
+        )
+        .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conda_names_for_pypi_package_custom_mapping() {
+        let dir = tempfile::tempdir().unwrap();
+        let mapping_path = dir.path().join("mapping.json");
+        fs_err::write(&mapping_path, r#"{"my-torch": "torch", "libfoo": null}"#).unwrap();
+        let source = MappingSource::Custom(Arc::new(CustomMapping::new(HashMap::from([(
+            OTHER_CHANNEL.trim_end_matches('/').to_string(),
+            MappingLocation::Path(mapping_path),
+        )]))));
+
+        let names = conda_names_for_pypi_package(
+            client(),
+            &source,
+            &[OTHER_CHANNEL.to_string()],
+            &pypi_name("torch"),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(names, conda_names(&["my-torch"]));
+
+        // A package that is not in the mapping of a channel other than
+        // conda-forge is not assumed to have the same name.
+        let names = conda_names_for_pypi_package(
+            client(),
+            &source,
+            &[OTHER_CHANNEL.to_string()],
+            &pypi_name("rich"),
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(names.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conda_names_for_pypi_package_disabled_mapping() {
+        let names = conda_names_for_pypi_package(
+            client(),
+            &MappingSource::Disabled,
+            &[OTHER_CHANNEL.to_string(), CONDA_FORGE.to_string()],
+            &pypi_name("Requests"),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(names, conda_names(&["requests"]));
+    }
+
+    #[tokio::test]
+    async fn test_conda_names_for_pypi_package_without_mapping() {
+        // A channel other than conda-forge without a mapping doesn't need the
+        // compressed mapping, so this works offline.
+        let names = conda_names_for_pypi_package(
+            client(),
+            &MappingSource::Prefix,
+            &[OTHER_CHANNEL.to_string()],
+            &pypi_name("requests"),
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(names.is_empty());
+    }
+}
@@ -13,7 +13,9 @@ from pydantic import (
     AnyHttpUrl,
     BaseModel,
     Field,
+    NonNegativeInt,
     PositiveFloat,
+    PositiveInt,
     StringConstraints,
 )
 
@@ -344,6 +346,67 @@ Dependencies = dict[CondaPackageName, MatchSpec] | None
 # Task section #
 ################
 TaskName = Annotated[str, Field(pattern=r"^[^\s\$]+$", description="A valid task name.")]
+TaskArgName = Annotated[
+    str,
+    Field(
+        pattern=r"^[a-zA-Z_][a-zA-Z0-9_]*$",
+        description="The name of a task argument, referenced in the command as `{{ name }}`.",
+    ),
+]
+
+
+class TaskArg(StrictBaseModel):
+    """The name of an argument of a task and its default value."""
+
+    arg: TaskArgName = Field(description="The name of the argument")
+    default: str | None = Field(
+        None, description="The value of the argument when no value is passed"
+    )
+
+
+class DependsOn(StrictBaseModel):
+    """A task that this task depends on and the arguments to pass to it."""
+
+    task: TaskName = Field(description="The name of the task")
+    args: list[str] | None = Field(
+        None,
+        description="The values of the arguments of the task, these can reference the arguments of the depending task using `{{ name }}`",
+    )
+    environment: EnvironmentName | None = Field(
+        None,
+        description="The environment to run the task in, by default the environment is determined the same way as for a task that is run from the command line",
+    )
+
+
+
+class TaskSandbox(StrictBaseModel):
+    """The filesystem and network access of a sandboxed task."""
+
+    read: list[PathNoBackslash] | None = Field(
+        None,
+        description="The paths the task can read, relative to the project root",
+    )
+    write: list[PathNoBackslash] | None = Field(
+        None,
+        description="The paths the task can read and write, relative to the project root. Paths that end with a `/` are created as directories, for a missing file the existing parent directory is writable instead",
+    )
+    network: bool | None = Field(
+        None,
+        description="Whether the task can access the network. Only TCP is blocked, UDP and unix sockets stay available",
+    )
+
+
+class TaskLimits(StrictBaseModel):
+    """Resource limits that apply to every process of a task."""
+
+    memory: PositiveInt | None = Field(
+        None,
+        description="The maximum virtual memory of a process in megabytes",
+    )
+    cpu_time: PositiveInt | None = Field(
+        None,
+        description="The maximum CPU time of a process in seconds",
+    )
 
 
 class TaskInlineTable(StrictBaseModel):
@@ -360,10 +423,14 @@ class TaskInlineTable(StrictBaseModel):
         alias="depends_on",
         description="The tasks that this task depends on. Environment variables will **not** be expanded. Deprecated in favor of `depends-on` from v0.21.0 onward.",
     )
-    depends_on: list[TaskName] | TaskName | None = Field(
+    depends_on: list[DependsOn | TaskName] | DependsOn | TaskName | None = Field(
         None,
         description="The tasks that this task depends on. Environment variables will **not** be expanded.",
     )
+    args: list[TaskArg | TaskArgName] | None = Field(
+        None,
+        description="The arguments of the task, these can be used in the command as `{{ name }}`",
+    )
     inputs: list[Glob] | None = Field(
         None,
         description="A list of `.gitignore`-style glob patterns that should be watched for changes before this command is run. Environment variables _will_ be expanded.",
@@ -386,6 +453,22 @@ class TaskInlineTable(StrictBaseModel):
         None,
         description="Whether to run in a clean environment, removing all environment variables except those defined in `env` and by pixi itself.",
     )
+    sandbox: TaskSandbox | None = Field(
+        None,
+        description="Run the task with only the declared filesystem and network access. Only supported on Linux.",
+    )
+    timeout: PositiveInt | None = Field(
+        None,
+        description="The maximum time in seconds the task can run, after which its processes are killed",
+    )
+    retries: NonNegativeInt | None = Field(
+        None,
+        description="The number of times the task is retried when it fails or times out",
+    )
+    limits: TaskLimits | None = Field(
+        None,
+        description="Resource limits for the processes of the task. Only supported on Unix.",
+    )
 
 
 #######################
@@ -457,6 +540,11 @@ class Activation(StrictBaseModel):
         description="A map of environment variables to values, used in the activation of the environment. These will be set in the shell. Thus these variables are shell specific. Using '$' might not expand to a value in different shells.",
         examples=[{"key": "value"}, {"ARGUMENT": "value"}],
     )
+    env_file: list[NonEmptyStr] | None = Field(
+        None,
+        description="The dotenv files to load the environment variables from, relative to the project root. Later files take precedence over earlier ones.",
+        examples=[[".env", ".env.local"]],
+    )
 
 
 ##################
@@ -3,21 +3,21 @@ use crate::{project::HasProjectRef, task::EnvironmentHash};
 use fs_err::tokio as tokio_fs;
 use indexmap::IndexMap;
 use itertools::Itertools;
-use miette::IntoDiagnostic;
+use miette::{IntoDiagnostic, WrapErr};
 use pixi_manifest::EnvironmentName;
 use pixi_manifest::FeaturesExt;
 use rattler_conda_types::Platform;
 use rattler_lock::LockFile;
 use rattler_shell::{
     activation::{
-        ActivationError, ActivationError::FailedToRunActivationScript, ActivationVariables,
-        Activator, PathModificationBehavior,
+        ActivationError::FailedToRunActivationScript, ActivationVariables, Activator,
+        PathModificationBehavior,
     },
     shell::ShellEnum,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Setting a base prefix for the pixi package
 const PROJECT_PREFIX: &str = "PIXI_PROJECT_";
@@ -108,7 +108,7 @@ impl Environment<'_> {
 pub(crate) fn get_activator<'p>(
     environment: &'p Environment<'p>,
     shell: ShellEnum,
-) -> Result<Activator<ShellEnum>, ActivationError> {
+) -> miette::Result<Activator<ShellEnum>> {
     let platform = Platform::current();
     let additional_activation_scripts = environment.activation_scripts(Some(platform));
 
@@ -137,13 +137,20 @@ pub(crate) fn get_activator<'p>(
     }
 
     let mut activator =
-        Activator::from_path(environment.dir().as_path(), shell, Platform::current())?;
+        Activator::from_path(environment.dir().as_path(), shell, Platform::current())
+            .into_diagnostic()?;
 
     // Add the custom activation scripts from the environment
     activator
         .activation_scripts
         .extend(additional_activation_scripts);
 
+    // Add the environment variables from the env-files, the variables from the
+    // project take precedence over them.
+    activator
+        .env_vars
+        .extend(get_env_file_variables(environment, platform)?);
+
     // Add the environment variables from the project.
     activator
         .env_vars
@@ -248,13 +255,8 @@ pub async fn run_activation(
     }
     tracing::debug!("Running activation script for {:?}", environment.name());
 
-    let activator = get_activator(environment, ShellEnum::default()).map_err(|e| {
-        miette::miette!(format!(
-            "failed to create activator for {:?}\n{}",
-            environment.name(),
-            e
-        ))
-    })?;
+    let activator = get_activator(environment, ShellEnum::default())
+        .wrap_err_with(|| format!("failed to create activator for {:?}", environment.name()))?;
 
     let path_modification_behavior = match env_var_behavior {
         // We need to replace the full environment path with the new one.
@@ -374,6 +376,194 @@ pub(crate) fn get_static_environment_variables<'p>(
         .collect()
 }
 
+/// Get the environment variables of a unix environment that is installed in
+/// `prefix` but will be used from `target_prefix`, e.g. inside a container
+/// image.
+///
+/// The activation scripts can not be executed for a foreign platform, so only
+/// the variables that are known statically are returned: `PATH`, the variables
+/// from the conda packages and the activation variables from the manifest.
+/// The `env-file` variables are left out, these often contain secrets or
+/// values of the current machine that should not end up in an image.
+pub(crate) fn get_static_activation_environment_variables(
+    environment: &Environment<'_>,
+    prefix: &Path,
+    target_prefix: &Path,
+    platform: Platform,
+) -> miette::Result<IndexMap<String, String>> {
+    let activator =
+        Activator::from_path(prefix, ShellEnum::default(), platform).into_diagnostic()?;
+
+    let skipped_scripts = activator
+        .activation_scripts
+        .iter()
+        .filter_map(|script| script.file_name())
+        .map(|script| script.to_string_lossy().into_owned())
+        .chain(environment.activation_scripts(Some(platform)))
+        .collect_vec();
+    if !skipped_scripts.is_empty() {
+        tracing::warn!(
+            "the following activation scripts are not executed: {}",
+            skipped_scripts.iter().format(", ")
+        );
+    }
+
+    let env_name = match environment.name() {
+        EnvironmentName::Named(name) => format!("{}:{}", environment.project().name(), name),
+        EnvironmentName::Default => environment.project().name().to_string(),
+    };
+    let target_prefix = target_prefix.to_string_lossy().into_owned();
+    let path =
+        format!("{target_prefix}/bin:/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin");
+
+    let mut env = IndexMap::from_iter([
+        (String::from("PATH"), path),
+        (String::from("CONDA_PREFIX"), target_prefix),
+        (String::from("CONDA_DEFAULT_ENV"), env_name),
+        (format!("{ENV_PREFIX}NAME"), environment.name().to_string()),
+    ]);
+    env.extend(activator.env_vars);
+    env.extend(environment.activation_env(Some(platform)));
+    Ok(env)
+}
+
+/// Get the environment variables from the activation env-files of the
+/// environment. Files that do not exist are skipped and later files take
+/// precedence over earlier ones.
+///
+/// Values can reference other variables with `$VAR` or `${VAR}`, these are
+/// resolved against the variables that are already loaded, the pixi project
+/// variables and the current shell environment.
+pub(crate) fn get_env_file_variables(
+    environment: &Environment<'_>,
+    platform: Platform,
+) -> miette::Result<IndexMap<String, String>> {
+    let project_env = environment.project().get_metadata_env();
+    let mut env = IndexMap::new();
+    for env_file in environment.activation_env_files(Some(platform)) {
+        let path = environment.project().root().join(&env_file);
+        let contents = match fs_err::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::debug!(
+                    "Skipping env-file '{}' as it does not exist",
+                    path.display()
+                );
+                continue;
+            }
+            Err(e) => return Err(e).into_diagnostic(),
+        };
+        parse_env_file(&contents, &mut env, |name| {
+            project_env
+                .get(name)
+                .cloned()
+                .or_else(|| std::env::var(name).ok())
+        })
+        .wrap_err_with(|| format!("failed to parse env-file '{}'", path.display()))?;
+    }
+    Ok(env)
+}
+
+/// Parses the `KEY=VALUE` lines of a dotenv file into `env`.
+///
+/// Single quoted values are taken literally, double quoted values support
+/// escape sequences and interpolation, unquoted values support interpolation
+/// and end at a ` #` comment. Variables that can not be found in `env` are
+/// looked up with `fallback` and are empty if they are not found there either.
+fn parse_env_file(
+    contents: &str,
+    env: &mut IndexMap<String, String>,
+    fallback: impl Fn(&str) -> Option<String>,
+) -> miette::Result<()> {
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+
+        let Some((key, value)) = line.split_once('=') else {
+            miette::bail!("line {line_number}: expected `KEY=VALUE`");
+        };
+        let key = key.trim();
+        if key.is_empty()
+            || key.starts_with(|c: char| c.is_ascii_digit())
+            || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            miette::bail!("line {line_number}: '{key}' is not a valid variable name");
+        }
+
+        let lookup = |name: &str| env.get(name).cloned().or_else(|| fallback(name));
+        let value = value.trim_start();
+        let value = if let Some(quoted) = value.strip_prefix('\'') {
+            let Some((literal, _)) = quoted.split_once('\'') else {
+                miette::bail!("line {line_number}: missing closing quote");
+            };
+            literal.to_string()
+        } else if let Some(quoted) = value.strip_prefix('"') {
+            let Some(end) = closing_double_quote(quoted) else {
+                miette::bail!("line {line_number}: missing closing quote");
+            };
+            interpolate(&quoted[..end], true, lookup)
+        } else {
+            let value = value.split_once(" #").map_or(value, |(value, _)| value);
+            interpolate(value.trim_end(), false, lookup)
+        };
+        env.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Returns the byte offset of the first double quote that is not escaped.
+fn closing_double_quote(value: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (offset, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(offset),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replaces `$VAR` and `${VAR}` in the value with the result of `lookup`. If
+/// `escapes` is set, backslash escape sequences are processed as well.
+fn interpolate(value: &str, escapes: bool, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if escapes => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('r') => result.push('\r'),
+                Some('t') => result.push('\t'),
+                Some(c) => result.push(c),
+                None => result.push('\\'),
+            },
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                result.push_str(&lookup(&name).unwrap_or_default());
+            }
+            '$' if chars
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphabetic() || *c == '_') =>
+            {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                result.push_str(&lookup(&name).unwrap_or_default());
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
 /// Get the environment variables that are set in the current shell
 /// and strip them down to the minimal set required to run a command.
 pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
@@ -556,6 +746,72 @@ mod tests {
         assert!(env.keys().position(|key| key == "ZZZ") < env.keys().position(|key| key == "ZAB"));
     }
 
+    #[test]
+    fn test_parse_env_file() {
+        let contents = r#"
+# Service endpoints
+export HOST=localhost
+PORT=5432 # the default port
+URL="postgres://${HOST}:$PORT/db"
+LITERAL='$HOST\n'
+ESCAPED="line\n\"quoted\" \$HOST"
+FROM_FALLBACK=$PIXI_PROJECT_ROOT/data
+MISSING=${DOES_NOT_EXIST}
+"#;
+        let mut env = IndexMap::new();
+        parse_env_file(contents, &mut env, |name| {
+            (name == "PIXI_PROJECT_ROOT").then(|| "/project".to_string())
+        })
+        .unwrap();
+
+        assert_eq!(
+            env,
+            IndexMap::from_iter(
+                [
+                    ("HOST", "localhost"),
+                    ("PORT", "5432"),
+                    ("URL", "postgres://localhost:5432/db"),
+                    ("LITERAL", "$HOST\\n"),
+                    ("ESCAPED", "line\n\"quoted\" $HOST"),
+                    ("FROM_FALLBACK", "/project/data"),
+                    ("MISSING", ""),
+                ]
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+            )
+        );
+
+        let mut env = IndexMap::new();
+        assert!(parse_env_file("NO_VALUE", &mut env, |_| None).is_err());
+        assert!(parse_env_file("1KEY=value", &mut env, |_| None).is_err());
+        assert!(parse_env_file("KEY=\"unterminated", &mut env, |_| None).is_err());
+    }
+
+    #[test]
+    fn test_env_file_variables() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs_err::write(temp_dir.path().join(".env"), "A=1\nB=$A-env").unwrap();
+        fs_err::write(temp_dir.path().join(".env.linux"), "B=$A-linux").unwrap();
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64"]
+
+        [activation]
+        env-file = [".env", ".env.local"]
+
+        [target.linux-64.activation]
+        env-file = [".env", ".env.linux"]
+        "#;
+        let project = Project::from_str(&temp_dir.path().join("pixi.toml"), project).unwrap();
+        let environment = project.default_environment();
+
+        let env = get_env_file_variables(&environment, Platform::Osx64).unwrap();
+        assert_eq!(env.get("B").map(String::as_str), Some("1-env"));
+        let env = get_env_file_variables(&environment, Platform::Linux64).unwrap();
+        assert_eq!(env.get("B").map(String::as_str), Some("1-linux"));
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn test_get_linux_clean_environment_variables() {
@@ -754,6 +1010,57 @@ packages:
         );
     }
 
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_run_activation_cache_based_on_script_contents() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let script = temp_dir.path().join("activate.sh");
+        tokio_fs::write(&script, "export TEST=SCRIPT123\n")
+            .await
+            .unwrap();
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = []
+
+        [activation]
+        scripts = ["activate.sh"]
+        "#;
+        let project =
+            Project::from_str(temp_dir.path().join("pixi.toml").as_path(), project).unwrap();
+        let default_env = project.default_environment();
+        let env = run_activation(
+            &default_env,
+            &CurrentEnvVarBehavior::Include,
+            Some(&LockFile::default()),
+            false,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(env.get("TEST").unwrap(), "SCRIPT123");
+
+        // Check that the cache is invalidated when the contents of the script change.
+        tokio_fs::write(&script, "export TEST=SCRIPT456\n")
+            .await
+            .unwrap();
+        let env = run_activation(
+            &default_env,
+            &CurrentEnvVarBehavior::Include,
+            Some(&LockFile::default()),
+            false,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            env.get("TEST").unwrap(),
+            "SCRIPT456",
+            "The script should be run again"
+        );
+    }
+
     // This test works, most of the times.., so this is a good test to run locally.
     // But it is to flaky for CI unfortunately!
     // #[tokio::test]
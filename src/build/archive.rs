@@ -0,0 +1,435 @@
+use std::path::{Path, PathBuf};
+
+use pixi_record::PinnedUrlSpec;
+use pixi_spec::UrlSourceSpec;
+use rattler_digest::{compute_file_digest, Md5, Md5Hash, Sha256, Sha256Hash};
+use reqwest_middleware::ClientWithMiddleware;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+/// A content addressed cache of unpacked source archives.
+///
+/// Archives are downloaded, verified and unpacked into a directory that is
+/// named after the sha256 hash of the archive. Because the directory is
+/// identified by the hash of its contents it never changes once it has been
+/// created.
+#[derive(Clone)]
+pub struct UrlCheckoutCache {
+    root: PathBuf,
+}
+
+#[derive(Debug, Error)]
+pub enum UrlError {
+    #[error("an IO error occurred while {0} {1}")]
+    IoError(String, PathBuf, #[source] std::io::Error),
+
+    #[error("failed to download '{0}'")]
+    Download(Url, #[source] reqwest_middleware::Error),
+
+    #[error("the {hash} of '{url}' ({actual}) does not match the expected {hash} ({expected})")]
+    HashMismatch {
+        url: Url,
+        hash: &'static str,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("'{0}' does not point to a supported archive (.tar, .tar.gz, .tgz, .tar.zst or .zip)")]
+    UnsupportedArchive(Url),
+
+    #[error("failed to unpack '{0}'")]
+    Unpack(Url, #[source] std::io::Error),
+}
+
+/// The archive formats that can be unpacked.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ArchiveType {
+    Tar,
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+impl ArchiveType {
+    /// Determines the type of archive from the file name in the url.
+    fn from_url(url: &Url) -> Option<Self> {
+        let file_name = url.path_segments()?.last()?.to_lowercase();
+        if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if file_name.ends_with(".tar.zst") || file_name.ends_with(".tzst") {
+            Some(Self::TarZst)
+        } else if file_name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if file_name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+impl UrlCheckoutCache {
+    /// Constructs a new instance.
+    ///
+    /// An additional directory is created by this cache inside the passed root
+    /// which includes a version number. This is to ensure that the cache is
+    /// never corrupted if the format changes in the future.
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root: root.join("url-v0"),
+        }
+    }
+
+    /// Downloads and unpacks the archive referenced by the spec. Returns the
+    /// path to the unpacked archive together with the pinned spec.
+    ///
+    /// If the spec declares a sha256 hash and an archive with that hash was
+    /// unpacked before, the archive is not downloaded again. Otherwise, the
+    /// archive has to be downloaded to determine its hash.
+    pub async fn fetch(
+        &self,
+        client: &ClientWithMiddleware,
+        spec: &UrlSourceSpec,
+    ) -> Result<(PathBuf, PinnedUrlSpec), UrlError> {
+        if let Some(sha256) = spec.sha256 {
+            let pinned = PinnedUrlSpec {
+                url: spec.url.clone(),
+                sha256,
+                md5: spec.md5,
+            };
+            let path = self.fetch_pinned(client, &pinned).await?;
+            return Ok((path, pinned));
+        }
+
+        let archive_type = ArchiveType::from_url(&spec.url)
+            .ok_or_else(|| UrlError::UnsupportedArchive(spec.url.clone()))?;
+        let download = self.download(client, &spec.url).await?;
+        let (sha256, md5) = download.hashes().await?;
+        verify_hash(&spec.url, "md5", spec.md5, md5)?;
+
+        let path = self
+            .unpack(download, &spec.url, archive_type, sha256)
+            .await?;
+        Ok((
+            path,
+            PinnedUrlSpec {
+                url: spec.url.clone(),
+                sha256,
+                md5: Some(md5),
+            },
+        ))
+    }
+
+    /// Returns the path to the unpacked archive referenced by the pinned
+    /// spec. The archive is only downloaded if it is not already available
+    /// locally.
+    pub async fn fetch_pinned(
+        &self,
+        client: &ClientWithMiddleware,
+        spec: &PinnedUrlSpec,
+    ) -> Result<PathBuf, UrlError> {
+        let checkout_dir = self.checkout_dir(spec.sha256);
+        if checkout_dir.is_dir() {
+            return Ok(checkout_dir);
+        }
+
+        let archive_type = ArchiveType::from_url(&spec.url)
+            .ok_or_else(|| UrlError::UnsupportedArchive(spec.url.clone()))?;
+        let download = self.download(client, &spec.url).await?;
+        let (sha256, md5) = download.hashes().await?;
+        verify_hash(&spec.url, "sha256", Some(spec.sha256), sha256)?;
+        verify_hash(&spec.url, "md5", spec.md5, md5)?;
+
+        self.unpack(download, &spec.url, archive_type, sha256).await
+    }
+
+    /// Returns the directory in which the archive with the given hash is
+    /// unpacked.
+    fn checkout_dir(&self, sha256: Sha256Hash) -> PathBuf {
+        self.root.join("checkouts").join(format!("{:x}", sha256))
+    }
+
+    /// Downloads the archive at the given url into a temporary file.
+    async fn download(
+        &self,
+        client: &ClientWithMiddleware,
+        url: &Url,
+    ) -> Result<Download, UrlError> {
+        let download_dir = self.root.join("downloads");
+        tokio::fs::create_dir_all(&download_dir)
+            .await
+            .map_err(|e| {
+                UrlError::IoError(
+                    "creating download directory".to_string(),
+                    download_dir.clone(),
+                    e,
+                )
+            })?;
+        let file = tempfile::NamedTempFile::new_in(&download_dir).map_err(|e| {
+            UrlError::IoError("creating temporary file".to_string(), download_dir, e)
+        })?;
+
+        // Local archives don't have to be downloaded.
+        if url.scheme() == "file" {
+            let path = url
+                .to_file_path()
+                .map_err(|_| UrlError::UnsupportedArchive(url.clone()))?;
+            tokio::fs::copy(&path, file.path())
+                .await
+                .map_err(|e| UrlError::IoError("copying archive".to_string(), path, e))?;
+            return Ok(Download { file });
+        }
+
+        tracing::debug!("downloading {url}");
+        let mut response = client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status().map_err(Into::into))
+            .map_err(|e| UrlError::Download(url.clone(), e))?;
+
+        let mut writer = tokio::fs::File::from_std(file.reopen().map_err(|e| {
+            UrlError::IoError(
+                "opening temporary file".to_string(),
+                file.path().to_path_buf(),
+                e,
+            )
+        })?);
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| UrlError::Download(url.clone(), e.into()))?
+        {
+            writer.write_all(&chunk).await.map_err(|e| {
+                UrlError::IoError("writing archive".to_string(), file.path().to_path_buf(), e)
+            })?;
+        }
+        writer.flush().await.map_err(|e| {
+            UrlError::IoError("writing archive".to_string(), file.path().to_path_buf(), e)
+        })?;
+
+        Ok(Download { file })
+    }
+
+    /// Unpacks the downloaded archive into the content addressed checkout
+    /// directory.
+    async fn unpack(
+        &self,
+        download: Download,
+        url: &Url,
+        archive_type: ArchiveType,
+        sha256: Sha256Hash,
+    ) -> Result<PathBuf, UrlError> {
+        let checkout_dir = self.checkout_dir(sha256);
+        if checkout_dir.is_dir() {
+            return Ok(checkout_dir);
+        }
+
+        let checkouts_root = self.root.join("checkouts");
+        let url = url.clone();
+        tokio::task::spawn_blocking(move || {
+            fs_err::create_dir_all(&checkouts_root).map_err(|e| {
+                UrlError::IoError(
+                    "creating checkout directory".to_string(),
+                    checkouts_root.clone(),
+                    e,
+                )
+            })?;
+
+            // Unpack into a temporary directory first and move it into place once
+            // it is complete. This ensures that a checkout directory is never
+            // observed half-way through unpacking.
+            let temp_dir = tempfile::TempDir::new_in(&checkouts_root).map_err(|e| {
+                UrlError::IoError(
+                    "creating temporary directory".to_string(),
+                    checkouts_root.clone(),
+                    e,
+                )
+            })?;
+            unpack_archive(download.file.path(), temp_dir.path(), archive_type)
+                .map_err(|e| UrlError::Unpack(url.clone(), e))?;
+
+            let source_dir = strip_single_root_dir(temp_dir.path())
+                .map_err(|e| UrlError::Unpack(url.clone(), e))?;
+            if let Err(err) = fs_err::rename(&source_dir, &checkout_dir) {
+                // Another process might have unpacked the same archive in the
+                // meantime.
+                if !checkout_dir.is_dir() {
+                    return Err(UrlError::IoError(
+                        "moving unpacked archive".to_string(),
+                        checkout_dir,
+                        err,
+                    ));
+                }
+            }
+
+            Ok(checkout_dir)
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+}
+
+/// A downloaded archive in a temporary file.
+struct Download {
+    file: tempfile::NamedTempFile,
+}
+
+impl Download {
+    /// Computes the sha256 and md5 hashes of the downloaded archive.
+    async fn hashes(&self) -> Result<(Sha256Hash, Md5Hash), UrlError> {
+        let path = self.file.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let sha256 = compute_file_digest::<Sha256>(&path)
+                .map_err(|e| UrlError::IoError("hashing archive".to_string(), path.clone(), e))?;
+            let md5 = compute_file_digest::<Md5>(&path)
+                .map_err(|e| UrlError::IoError("hashing archive".to_string(), path.clone(), e))?;
+            Ok((sha256, md5))
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+}
+
+/// Verifies that the hash of the archive matches the expected hash, if any.
+fn verify_hash<T: PartialEq + std::fmt::LowerHex>(
+    url: &Url,
+    hash: &'static str,
+    expected: Option<T>,
+    actual: T,
+) -> Result<(), UrlError> {
+    match expected {
+        Some(expected) if expected != actual => Err(UrlError::HashMismatch {
+            url: url.clone(),
+            hash,
+            expected: format!("{:x}", expected),
+            actual: format!("{:x}", actual),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Unpacks the archive at `archive` into the `destination` directory.
+fn unpack_archive(
+    archive: &Path,
+    destination: &Path,
+    archive_type: ArchiveType,
+) -> Result<(), std::io::Error> {
+    let file = fs_err::File::open(archive)?;
+    match archive_type {
+        ArchiveType::Tar => tar::Archive::new(file).unpack(destination),
+        ArchiveType::TarGz => {
+            tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(destination)
+        }
+        ArchiveType::TarZst => {
+            tar::Archive::new(zstd::stream::read::Decoder::new(file)?).unpack(destination)
+        }
+        ArchiveType::Zip => zip::ZipArchive::new(file)
+            .and_then(|mut archive| archive.extract(destination))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// Source archives usually contain a single directory that contains the
+/// actual sources (e.g. `package-1.0.0/`). If that is the case, that directory
+/// is returned, otherwise the directory itself is returned.
+fn strip_single_root_dir(dir: &Path) -> Result<PathBuf, std::io::Error> {
+    let mut entries = fs_err::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    if entries.len() == 1 && entries[0].file_type()?.is_dir() {
+        Ok(entries.remove(0).path())
+    } else {
+        Ok(dir.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use pixi_spec::UrlSourceSpec;
+    use rattler_digest::{compute_file_digest, Sha256};
+    use reqwest_middleware::ClientWithMiddleware;
+    use url::Url;
+
+    use super::{UrlCheckoutCache, UrlError};
+
+    /// Creates a `.tar.gz` source archive with a single root directory.
+    fn create_archive(dir: &Path) -> Url {
+        let archive_path = dir.join("package-0.1.0.tar.gz");
+        let file = fs_err::File::create(&archive_path).unwrap();
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            file,
+            flate2::Compression::default(),
+        ));
+        let contents = b"[package]\nname = \"package\"\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "package-0.1.0/pixi.toml", &contents[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        Url::from_file_path(archive_path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_archive() {
+        let archive_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = create_archive(archive_dir.path());
+        let sha256 = compute_file_digest::<Sha256>(url.to_file_path().unwrap()).unwrap();
+
+        let client = ClientWithMiddleware::default();
+        let cache = UrlCheckoutCache::new(cache_dir.path().to_path_buf());
+
+        let (path, pinned) = cache
+            .fetch(
+                &client,
+                &UrlSourceSpec {
+                    url: url.clone(),
+                    md5: None,
+                    sha256: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(pinned.sha256, sha256);
+        assert!(pinned.md5.is_some());
+        assert!(path.join("pixi.toml").is_file());
+        assert!(path.ends_with(format!("{:x}", sha256)));
+
+        // The pinned archive is served from the cache.
+        fs_err::remove_file(url.to_file_path().unwrap()).unwrap();
+        assert_eq!(cache.fetch_pinned(&client, &pinned).await.unwrap(), path);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_archive_hash_mismatch() {
+        let archive_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = create_archive(archive_dir.path());
+
+        let client = ClientWithMiddleware::default();
+        let cache = UrlCheckoutCache::new(cache_dir.path().to_path_buf());
+
+        let err = cache
+            .fetch(
+                &client,
+                &UrlSourceSpec {
+                    url,
+                    md5: None,
+                    sha256: Some(
+                        rattler_digest::parse_digest_from_hex::<Sha256>(
+                            "0000000000000000000000000000000000000000000000000000000000000000",
+                        )
+                        .unwrap(),
+                    ),
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UrlError::HashMismatch { hash: "sha256", .. }));
+    }
+}
@@ -0,0 +1,489 @@
+use std::{
+    ffi::OsStr,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use async_fd_lock::LockWrite;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use pixi_record::PinnedGitSpec;
+use pixi_spec::{GitReference, GitSpec};
+use thiserror::Error;
+use tokio::process::Command;
+use url::Url;
+use xxhash_rust::xxh3::Xxh3;
+
+/// The name of the file inside the `.git` directory of a checkout that marks
+/// the checkout as complete. A checkout directory without this file is
+/// considered incomplete and is recreated.
+const CHECKOUT_READY_FILE: &str = "pixi-checkout-ok";
+
+/// A cache for git repositories and checkouts of specific commits.
+///
+/// Every repository is mirrored once into a bare "database" repository. From
+/// that database, every commit that is requested is checked out into its own
+/// directory. Because a checkout is identified by its commit hash it never
+/// changes once it has been created.
+#[derive(Clone)]
+pub struct GitCheckoutCache {
+    root: PathBuf,
+}
+
+#[derive(Debug, Error)]
+pub enum GitError {
+    #[error("an IO error occurred while {0} {1}")]
+    IoError(String, PathBuf, #[source] std::io::Error),
+
+    #[error("failed to execute `git {args}`, is git installed?")]
+    GitNotFound {
+        args: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("`git {args}` failed: {stderr}")]
+    GitCommandFailed { args: String, stderr: String },
+
+    #[error("could not find {reference} '{name}' in '{url}'")]
+    ReferenceNotFound {
+        url: Url,
+        reference: &'static str,
+        name: String,
+    },
+}
+
+impl GitCheckoutCache {
+    /// Constructs a new instance.
+    ///
+    /// An additional directory is created by this cache inside the passed root
+    /// which includes a version number. This is to ensure that the cache is
+    /// never corrupted if the format changes in the future.
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root: root.join("git-v0"),
+        }
+    }
+
+    /// Fetches the repository referenced by the given spec and resolves the
+    /// requested branch, tag or revision to a commit. Returns the path to a
+    /// checkout of that commit together with the pinned spec.
+    pub async fn fetch(&self, spec: &GitSpec) -> Result<(PathBuf, PinnedGitSpec), GitError> {
+        let db = self.lock_database(&spec.git).await?;
+
+        // Always update the database, a branch or tag might have moved since the
+        // last time we fetched it.
+        db.fetch_all().await?;
+
+        let commit = match db.resolve(spec.rev.as_ref()).await? {
+            Some(commit) => commit,
+            None => match &spec.rev {
+                // A revision might not be reachable from any of the fetched refs. Try to
+                // fetch it directly.
+                Some(GitReference::Rev(rev)) => {
+                    db.fetch_rev(rev).await?;
+                    db.resolve(spec.rev.as_ref())
+                        .await?
+                        .ok_or_else(|| reference_not_found(&spec.git, spec.rev.as_ref()))?
+                }
+                reference => return Err(reference_not_found(&spec.git, reference.as_ref())),
+            },
+        };
+
+        let path = self.checkout(&db, &commit).await?;
+        Ok((
+            path,
+            PinnedGitSpec {
+                git: spec.git.clone(),
+                commit,
+                rev: spec.rev.clone(),
+            },
+        ))
+    }
+
+    /// Returns the path to a checkout of the commit referenced by the pinned
+    /// spec. The repository is only fetched if the commit is not already
+    /// available locally.
+    pub async fn fetch_pinned(&self, spec: &PinnedGitSpec) -> Result<PathBuf, GitError> {
+        let db = self.lock_database(&spec.git).await?;
+
+        let checkout_dir = self.checkout_dir(&spec.git, &spec.commit);
+        if checkout_dir
+            .join(".git")
+            .join(CHECKOUT_READY_FILE)
+            .is_file()
+        {
+            return Ok(checkout_dir);
+        }
+
+        if !db.contains_commit(&spec.commit).await? {
+            db.fetch_all().await?;
+            if !db.contains_commit(&spec.commit).await? {
+                db.fetch_rev(&spec.commit).await?;
+            }
+        }
+
+        self.checkout(&db, &spec.commit).await
+    }
+
+    /// Opens (and creates if it doesn't exist yet) the bare database repository
+    /// for the given url. The returned [`GitDatabase`] holds an exclusive lock
+    /// on the repository to avoid concurrent fetches into the same directory.
+    async fn lock_database(&self, url: &Url) -> Result<GitDatabase, GitError> {
+        let key = repository_cache_key(url);
+        let path = self.root.join("db").join(&key);
+        tokio::fs::create_dir_all(&path).await.map_err(|e| {
+            GitError::IoError(
+                "creating git database directory".to_string(),
+                path.clone(),
+                e,
+            )
+        })?;
+
+        let lock_file_path = self.root.join("db").join(format!("{key}.lock"));
+        let lock_file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .truncate(false)
+            .create(true)
+            .open(&lock_file_path)
+            .await
+            .map_err(|e| {
+                GitError::IoError("opening lock file".to_string(), lock_file_path.clone(), e)
+            })?;
+        let lock = lock_file.lock_write().await.map_err(|e| {
+            GitError::IoError("locking lock file".to_string(), lock_file_path, e.error)
+        })?;
+
+        if !path.join("HEAD").is_file() {
+            run_git(&path, ["init", "--bare", "--quiet"]).await?;
+        }
+
+        Ok(GitDatabase {
+            url: url.clone(),
+            path,
+            _lock: lock,
+        })
+    }
+
+    /// Returns the directory in which the given commit of a repository is
+    /// checked out.
+    fn checkout_dir(&self, url: &Url, commit: &str) -> PathBuf {
+        self.root
+            .join("checkouts")
+            .join(repository_cache_key(url))
+            .join(commit)
+    }
+
+    /// Checks out the given commit from the database into its own directory.
+    /// If the checkout already exists it is reused.
+    async fn checkout(&self, db: &GitDatabase, commit: &str) -> Result<PathBuf, GitError> {
+        let checkout_dir = self.checkout_dir(&db.url, commit);
+        let ready_file = checkout_dir.join(".git").join(CHECKOUT_READY_FILE);
+        if ready_file.is_file() {
+            return Ok(checkout_dir);
+        }
+
+        // Remove any partial checkout left behind by an interrupted process.
+        if checkout_dir.exists() {
+            tokio::fs::remove_dir_all(&checkout_dir)
+                .await
+                .map_err(|e| {
+                    GitError::IoError(
+                        "removing incomplete checkout".to_string(),
+                        checkout_dir.clone(),
+                        e,
+                    )
+                })?;
+        }
+
+        // Create a new repository that borrows all objects from the database
+        // through the alternates mechanism, this avoids copying all objects.
+        tokio::fs::create_dir_all(&checkout_dir)
+            .await
+            .map_err(|e| {
+                GitError::IoError(
+                    "creating checkout directory".to_string(),
+                    checkout_dir.clone(),
+                    e,
+                )
+            })?;
+        run_git(&checkout_dir, ["init", "--quiet"]).await?;
+        let alternates = checkout_dir.join(".git/objects/info/alternates");
+        tokio::fs::write(
+            &alternates,
+            format!("{}\n", db.path.join("objects").display()),
+        )
+        .await
+        .map_err(|e| GitError::IoError("writing alternates".to_string(), alternates, e))?;
+        run_git(
+            &checkout_dir,
+            ["checkout", "--quiet", "--force", "--detach", commit],
+        )
+        .await?;
+
+        tokio::fs::write(&ready_file, commit)
+            .await
+            .map_err(|e| GitError::IoError("writing checkout marker".to_string(), ready_file, e))?;
+
+        Ok(checkout_dir)
+    }
+}
+
+/// A bare repository that mirrors the refs of a remote repository.
+struct GitDatabase {
+    url: Url,
+    path: PathBuf,
+    _lock: async_fd_lock::RwLockWriteGuard<tokio::fs::File>,
+}
+
+impl GitDatabase {
+    /// Fetches all branches, tags and the default branch of the remote.
+    async fn fetch_all(&self) -> Result<(), GitError> {
+        run_git(
+            &self.path,
+            [
+                "fetch",
+                "--quiet",
+                "--force",
+                self.url.as_str(),
+                "+HEAD:refs/remotes/origin/HEAD",
+                "+refs/heads/*:refs/remotes/origin/heads/*",
+                "+refs/tags/*:refs/tags/*",
+            ],
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Fetches a single revision from the remote.
+    async fn fetch_rev(&self, rev: &str) -> Result<(), GitError> {
+        let refspec = format!("+{rev}:refs/remotes/origin/revs/{rev}");
+        run_git(
+            &self.path,
+            [
+                "fetch",
+                "--quiet",
+                "--force",
+                self.url.as_str(),
+                refspec.as_str(),
+            ],
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Returns true if the given commit is present in the database.
+    async fn contains_commit(&self, commit: &str) -> Result<bool, GitError> {
+        Ok(self
+            .rev_parse(&format!("{commit}^{{commit}}"))
+            .await?
+            .is_some())
+    }
+
+    /// Resolves a git reference to a full commit hash. Returns `None` if the
+    /// reference cannot be found in the database.
+    async fn resolve(&self, reference: Option<&GitReference>) -> Result<Option<String>, GitError> {
+        let rev = match reference {
+            None => "refs/remotes/origin/HEAD^{commit}".to_string(),
+            Some(GitReference::Branch(branch)) => {
+                format!("refs/remotes/origin/heads/{branch}^{{commit}}")
+            }
+            Some(GitReference::Tag(tag)) => format!("refs/tags/{tag}^{{commit}}"),
+            Some(GitReference::Rev(rev)) => format!("{rev}^{{commit}}"),
+        };
+        self.rev_parse(&rev).await
+    }
+
+    async fn rev_parse(&self, rev: &str) -> Result<Option<String>, GitError> {
+        match run_git(&self.path, ["rev-parse", "--verify", "--quiet", rev]).await {
+            Ok(output) => Ok(Some(output.trim().to_string())),
+            Err(GitError::GitCommandFailed { .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Runs a git command in the given directory and returns its stdout.
+async fn run_git<I, S>(cwd: &Path, args: I) -> Result<String, GitError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args = args
+        .into_iter()
+        .map(|arg| arg.as_ref().to_os_string())
+        .collect::<Vec<_>>();
+    let display_args = args
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    tracing::debug!("running `git {display_args}` in {}", cwd.display());
+    let output = Command::new("git")
+        .args(&args)
+        .current_dir(cwd)
+        // Never ask for credentials on the terminal, we are not interactive.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .map_err(|source| GitError::GitNotFound {
+            args: display_args.clone(),
+            source,
+        })?;
+
+    if !output.status.success() {
+        return Err(GitError::GitCommandFailed {
+            args: display_args,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn reference_not_found(url: &Url, reference: Option<&GitReference>) -> GitError {
+    let (reference, name) = match reference {
+        None => ("reference", "HEAD".to_string()),
+        Some(GitReference::Branch(branch)) => ("branch", branch.clone()),
+        Some(GitReference::Tag(tag)) => ("tag", tag.clone()),
+        Some(GitReference::Rev(rev)) => ("revision", rev.clone()),
+    };
+    GitError::ReferenceNotFound {
+        url: url.clone(),
+        reference,
+        name,
+    }
+}
+
+/// Constructs a name for the cache directory of a repository. The name
+/// includes the name of the repository to make it easier to identify.
+fn repository_cache_key(url: &Url) -> String {
+    let mut hasher = Xxh3::new();
+    url.as_str().trim_end_matches('/').hash(&mut hasher);
+    let unique_key = URL_SAFE_NO_PAD.encode(hasher.finish().to_ne_bytes());
+    let name = url
+        .path_segments()
+        .and_then(|segments| segments.filter(|s| !s.is_empty()).last())
+        .map(|name| name.trim_end_matches(".git"))
+        .filter(|name| !name.is_empty());
+    match name {
+        Some(name) => format!("{}-{}", name, unique_key),
+        None => unique_key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, path::Path};
+
+    use pixi_spec::{GitReference, GitSpec};
+    use url::Url;
+
+    use super::{run_git, GitCheckoutCache};
+
+    /// Creates a bare repository with two commits on `main`, a tag on the first
+    /// commit and a `feature` branch. Returns the url to the bare repository
+    /// and the commit hashes.
+    async fn create_repository(root: &Path) -> (Url, String, String) {
+        let work = root.join("work");
+        let bare = root.join("remote.git");
+        fs_err::create_dir_all(&work).unwrap();
+
+        run_git(&work, ["init", "--quiet", "--initial-branch=main"])
+            .await
+            .unwrap();
+        for (key, value) in [("user.name", "pixi"), ("user.email", "pixi@example.com")] {
+            run_git(&work, ["config", key, value]).await.unwrap();
+        }
+
+        fs_err::write(work.join("pixi.toml"), "first").unwrap();
+        run_git(&work, ["add", "."]).await.unwrap();
+        run_git(&work, ["commit", "--quiet", "-m", "first"])
+            .await
+            .unwrap();
+        run_git(&work, ["tag", "v1"]).await.unwrap();
+        let first = run_git(&work, ["rev-parse", "HEAD"]).await.unwrap();
+
+        fs_err::write(work.join("pixi.toml"), "second").unwrap();
+        run_git(&work, ["commit", "--quiet", "-am", "second"])
+            .await
+            .unwrap();
+        run_git(&work, ["branch", "feature", "v1"]).await.unwrap();
+        let second = run_git(&work, ["rev-parse", "HEAD"]).await.unwrap();
+
+        run_git(
+            root,
+            [
+                OsStr::new("clone"),
+                OsStr::new("--quiet"),
+                OsStr::new("--bare"),
+                work.as_os_str(),
+                bare.as_os_str(),
+            ],
+        )
+        .await
+        .unwrap();
+
+        (
+            Url::from_directory_path(&bare).unwrap(),
+            first.trim().to_string(),
+            second.trim().to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fetch_git_references() {
+        let remote = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let (url, first, second) = create_repository(remote.path()).await;
+        let cache = GitCheckoutCache::new(cache_dir.path().to_path_buf());
+
+        for (rev, expected_commit, expected_content) in [
+            (None, &second, "second"),
+            (Some(GitReference::Branch("main".into())), &second, "second"),
+            (
+                Some(GitReference::Branch("feature".into())),
+                &first,
+                "first",
+            ),
+            (Some(GitReference::Tag("v1".into())), &first, "first"),
+            (
+                Some(GitReference::Rev(first[..8].to_string())),
+                &first,
+                "first",
+            ),
+        ] {
+            let spec = GitSpec {
+                git: url.clone(),
+                rev: rev.clone(),
+            };
+            let (path, pinned) = cache.fetch(&spec).await.unwrap();
+            assert_eq!(&pinned.commit, expected_commit, "{rev:?}");
+            assert_eq!(pinned.rev, rev);
+            assert_eq!(
+                fs_err::read_to_string(path.join("pixi.toml")).unwrap(),
+                expected_content
+            );
+
+            // Fetching the pinned spec should return the same checkout.
+            assert_eq!(cache.fetch_pinned(&pinned).await.unwrap(), path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_missing_branch() {
+        let remote = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let (url, _, _) = create_repository(remote.path()).await;
+        let cache = GitCheckoutCache::new(cache_dir.path().to_path_buf());
+
+        let err = cache
+            .fetch(&GitSpec {
+                git: url,
+                rev: Some(GitReference::Branch("does-not-exist".into())),
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("could not find branch"), "{err}");
+    }
+}
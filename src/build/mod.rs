@@ -1,4 +1,6 @@
+mod archive;
 mod cache;
+mod git;
 mod reporters;
 
 use std::{
@@ -11,8 +13,12 @@ use std::{
     sync::Arc,
 };
 
+use archive::UrlCheckoutCache;
+pub use archive::UrlError;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::Utc;
+use git::GitCheckoutCache;
+pub use git::GitError;
 use itertools::Itertools;
 use miette::{Diagnostic, IntoDiagnostic};
 use pixi_build_frontend::{BackendOverride, SetupRequest, ToolContext};
@@ -51,6 +57,8 @@ pub struct BuildContext {
     glob_hash_cache: GlobHashCache,
     source_metadata_cache: SourceMetadataCache,
     build_cache: BuildCache,
+    git_checkout_cache: GitCheckoutCache,
+    url_checkout_cache: UrlCheckoutCache,
     cache_dir: PathBuf,
     work_dir: PathBuf,
     tool_context: Arc<ToolContext>,
@@ -86,6 +94,12 @@ pub enum BuildError {
     #[error(transparent)]
     BuildCacheError(#[from] cache::BuildCacheError),
 
+    #[error("failed to fetch git source")]
+    GitFetch(#[from] GitError),
+
+    #[error("failed to fetch url source")]
+    UrlFetch(#[from] UrlError),
+
     #[error(transparent)]
     BuildFolderNotWritable(#[from] std::io::Error),
 }
@@ -125,6 +139,8 @@ impl BuildContext {
             glob_hash_cache: GlobHashCache::default(),
             source_metadata_cache: SourceMetadataCache::new(cache_dir.clone()),
             build_cache: BuildCache::new(cache_dir.clone()),
+            git_checkout_cache: GitCheckoutCache::new(cache_dir.clone()),
+            url_checkout_cache: UrlCheckoutCache::new(cache_dir.clone()),
             cache_dir,
             work_dir: dot_pixi_dir.join("build-v0"),
             tool_context,
@@ -391,15 +407,29 @@ impl BuildContext {
     /// This also pins the source spec to a specific checkout (e.g. git commit
     /// hash).
     ///
-    /// TODO(baszalmstra): Ideally we would cache the result of this on disk
-    /// somewhere.
+    /// Git and url sources are cached on disk in the pixi cache directory.
     pub async fn fetch_source(
         &self,
         source_spec: &SourceSpec,
     ) -> Result<SourceCheckout, BuildError> {
         match source_spec {
-            SourceSpec::Url(_) => unimplemented!("fetching URL sources is not yet implemented"),
-            SourceSpec::Git(_) => unimplemented!("fetching Git sources is not yet implemented"),
+            SourceSpec::Url(url) => {
+                let (path, pinned) = self
+                    .url_checkout_cache
+                    .fetch(&self.tool_context.client, url)
+                    .await?;
+                Ok(SourceCheckout {
+                    path,
+                    pinned: pinned.into(),
+                })
+            }
+            SourceSpec::Git(git) => {
+                let (path, pinned) = self.git_checkout_cache.fetch(git).await?;
+                Ok(SourceCheckout {
+                    path,
+                    pinned: pinned.into(),
+                })
+            }
             SourceSpec::Path(path) => {
                 let source_path = self
                     .resolve_path(path.path.to_path())
@@ -417,19 +447,17 @@ impl BuildContext {
 
     /// Acquires the source from the given source specification.
     ///
-    /// TODO(baszalmstra): Ideally we would cache the result of this on disk
-    /// somewhere.
+    /// Git and url sources are cached on disk in the pixi cache directory.
     pub async fn fetch_pinned_source(
         &self,
         source_spec: &PinnedSourceSpec,
     ) -> Result<PathBuf, BuildError> {
         match source_spec {
-            PinnedSourceSpec::Url(_) => {
-                unimplemented!("fetching URL sources is not yet implemented")
-            }
-            PinnedSourceSpec::Git(_) => {
-                unimplemented!("fetching Git sources is not yet implemented")
-            }
+            PinnedSourceSpec::Url(url) => Ok(self
+                .url_checkout_cache
+                .fetch_pinned(&self.tool_context.client, url)
+                .await?),
+            PinnedSourceSpec::Git(git) => Ok(self.git_checkout_cache.fetch_pinned(git).await?),
             PinnedSourceSpec::Path(path) => self
                 .resolve_path(path.path.to_path())
                 .map_err(|err| BuildError::ResolveSourcePath(path.path.clone(), err)),
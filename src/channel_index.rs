@@ -0,0 +1,399 @@
+//! Indexing of conda channels on the local filesystem.
+//!
+//! The `repodata.json` of a subdirectory is created from the `info/index.json`
+//! of the packages in the directory. Records of packages that are already part
+//! of an existing `repodata.json` are reused if the size of the file didn't
+//! change.
+//!
+//! Next to the `repodata.json` a zstd compressed copy and the sharded
+//! repodata (CEP-16) can be written, these are the variants the repodata
+//! gateway prefers when the channel is served over http.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use miette::{IntoDiagnostic, WrapErr};
+use rattler_conda_types::{
+    package::{ArchiveType, IndexJson},
+    PackageRecord, Platform, Shard, ShardedRepodata, ShardedSubdirInfo,
+};
+use rattler_digest::{compute_bytes_digest, compute_file_digest, Md5, Sha256};
+use serde::{Deserialize, Serialize};
+
+/// The name of the file that contains the records of a subdirectory.
+pub(crate) const REPODATA_JSON: &str = "repodata.json";
+
+/// The name of the zstd compressed `repodata.json`.
+const REPODATA_JSON_ZST: &str = "repodata.json.zst";
+
+/// The name of the index of the sharded repodata.
+const REPODATA_SHARDS: &str = "repodata_shards.msgpack.zst";
+
+/// The directory, relative to the subdirectory, that contains the shards.
+const SHARDS_DIR: &str = "shards";
+
+/// The zstd compression level of the generated files.
+const ZSTD_LEVEL: i32 = 16;
+
+/// Which variants of the repodata are written when indexing a channel.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IndexOptions {
+    /// Write a zstd compressed copy of the `repodata.json`.
+    pub write_zst: bool,
+    /// Write the sharded repodata.
+    pub write_shards: bool,
+}
+
+impl Default for IndexOptions {
+    fn default() -> Self {
+        Self {
+            write_zst: true,
+            write_shards: true,
+        }
+    }
+}
+
+/// The `repodata.json` of a single subdirectory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RepoDataJson {
+    #[serde(default)]
+    info: Option<ChannelInfo>,
+    #[serde(default)]
+    packages: BTreeMap<String, PackageRecord>,
+    #[serde(default, rename = "packages.conda")]
+    conda_packages: BTreeMap<String, PackageRecord>,
+    #[serde(default)]
+    removed: Vec<String>,
+    #[serde(default)]
+    repodata_version: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChannelInfo {
+    subdir: String,
+}
+
+/// Reads the `info/index.json` of a `.conda` or `.tar.bz2` package.
+pub(crate) fn read_index_json(path: &Path) -> miette::Result<IndexJson> {
+    rattler_package_streaming::seek::read_package_file::<IndexJson>(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read the metadata of '{}'", path.display()))
+}
+
+/// Returns the archive type if the file name is the name of a conda package.
+pub(crate) fn package_archive_type(file_name: &str) -> Option<ArchiveType> {
+    ArchiveType::split_str(file_name).map(|(_, archive_type)| archive_type)
+}
+
+/// Creates the record of a package file.
+pub(crate) fn package_record(path: &Path) -> miette::Result<PackageRecord> {
+    let index_json = read_index_json(path)?;
+    let size = fs_err::metadata(path).into_diagnostic()?.len();
+    let sha256 = compute_file_digest::<Sha256>(path).into_diagnostic()?;
+    let md5 = compute_file_digest::<Md5>(path).into_diagnostic()?;
+    PackageRecord::from_index_json(index_json, Some(size), Some(sha256), Some(md5))
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to create the record of '{}'", path.display()))
+}
+
+/// Indexes all subdirectories of the channel. Packages that are placed
+/// directly in the channel directory, like the output of `pixi build`, are
+/// moved into the subdirectory they belong to first.
+///
+/// Returns the number of packages per subdirectory.
+pub(crate) fn index_channel(
+    channel_dir: &Path,
+    options: IndexOptions,
+) -> miette::Result<BTreeMap<String, usize>> {
+    if !channel_dir.is_dir() {
+        miette::bail!("'{}' is not a directory", channel_dir.display());
+    }
+
+    let mut subdirs = BTreeMap::new();
+    for path in sorted_entries(channel_dir)? {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if path.is_dir() {
+            if Platform::from_str(file_name).is_ok() {
+                subdirs.insert(file_name.to_string(), 0);
+            }
+        } else if package_archive_type(file_name).is_some() {
+            let subdir = read_index_json(&path)?
+                .subdir
+                .unwrap_or_else(|| Platform::NoArch.to_string());
+            let destination = channel_dir.join(&subdir).join(file_name);
+            fs_err::create_dir_all(channel_dir.join(&subdir)).into_diagnostic()?;
+            fs_err::rename(&path, &destination).into_diagnostic()?;
+            subdirs.insert(subdir, 0);
+        }
+    }
+
+    // A channel always needs a `noarch` subdirectory.
+    subdirs.insert(Platform::NoArch.to_string(), 0);
+    for (subdir, count) in subdirs.iter_mut() {
+        *count = index_subdir(channel_dir, subdir, options)?;
+    }
+
+    Ok(subdirs)
+}
+
+/// (Re-)creates the `repodata.json` of the `subdir` directory of the channel.
+/// Returns the number of packages in the subdirectory.
+pub(crate) fn index_subdir(
+    channel_dir: &Path,
+    subdir: &str,
+    options: IndexOptions,
+) -> miette::Result<usize> {
+    let subdir_path = channel_dir.join(subdir);
+    fs_err::create_dir_all(&subdir_path).into_diagnostic()?;
+    let repodata_path = subdir_path.join(REPODATA_JSON);
+
+    // Reuse the records of an existing index.
+    let existing = match fs_err::read_to_string(&repodata_path) {
+        Ok(contents) => serde_json::from_str::<RepoDataJson>(&contents).unwrap_or_else(|e| {
+            tracing::warn!("ignoring the invalid '{}': {e}", repodata_path.display());
+            RepoDataJson::default()
+        }),
+        Err(_) => RepoDataJson::default(),
+    };
+
+    let mut repodata = RepoDataJson {
+        info: Some(ChannelInfo {
+            subdir: subdir.to_string(),
+        }),
+        repodata_version: 1,
+        ..RepoDataJson::default()
+    };
+    for path in sorted_entries(&subdir_path)? {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(archive_type) = package_archive_type(file_name) else {
+            continue;
+        };
+        let (existing_records, records) = match archive_type {
+            ArchiveType::TarBz2 => (&existing.packages, &mut repodata.packages),
+            ArchiveType::Conda => (&existing.conda_packages, &mut repodata.conda_packages),
+        };
+
+        let size = fs_err::metadata(&path).into_diagnostic()?.len();
+        let record = match existing_records.get(file_name) {
+            Some(record) if record.size == Some(size) => record.clone(),
+            _ => package_record(&path)?,
+        };
+        if record.subdir != subdir {
+            tracing::warn!(
+                "skipping '{}' because it belongs to the '{}' subdirectory",
+                path.display(),
+                record.subdir
+            );
+            continue;
+        }
+        records.insert(file_name.to_string(), record);
+    }
+
+    let contents = serde_json::to_vec_pretty(&repodata).into_diagnostic()?;
+    write_atomic(&repodata_path, &contents)?;
+
+    let zst_path = subdir_path.join(REPODATA_JSON_ZST);
+    if options.write_zst {
+        let compressed = zstd::encode_all(contents.as_slice(), ZSTD_LEVEL).into_diagnostic()?;
+        write_atomic(&zst_path, &compressed)?;
+    } else if zst_path.exists() {
+        // Never leave a stale variant behind, clients prefer it over the json.
+        fs_err::remove_file(&zst_path).into_diagnostic()?;
+    }
+
+    if options.write_shards {
+        write_shards(&subdir_path, subdir, &repodata)?;
+    } else {
+        remove_shards(&subdir_path)?;
+    }
+
+    Ok(repodata.packages.len() + repodata.conda_packages.len())
+}
+
+/// Writes a shard per package name and the index that refers to them. Shards
+/// that are no longer referenced are removed.
+fn write_shards(subdir_path: &Path, subdir: &str, repodata: &RepoDataJson) -> miette::Result<()> {
+    let mut shards: BTreeMap<String, Shard> = BTreeMap::new();
+    for (file_name, record) in &repodata.packages {
+        shard_for(&mut shards, record)
+            .packages
+            .insert(file_name.clone(), record.clone());
+    }
+    for (file_name, record) in &repodata.conda_packages {
+        shard_for(&mut shards, record)
+            .conda_packages
+            .insert(file_name.clone(), record.clone());
+    }
+
+    let shards_path = subdir_path.join(SHARDS_DIR);
+    fs_err::create_dir_all(&shards_path).into_diagnostic()?;
+    let mut hashes = Vec::with_capacity(shards.len());
+    let mut shard_files = HashSet::new();
+    for (name, shard) in shards {
+        let bytes = rmp_serde::to_vec_named(&shard).into_diagnostic()?;
+        let compressed = zstd::encode_all(bytes.as_slice(), ZSTD_LEVEL).into_diagnostic()?;
+        let hash = compute_bytes_digest::<Sha256>(&compressed);
+        let file_name = format!("{hash:x}.msgpack.zst");
+
+        // Shards are content addressed, an existing file is already up to date.
+        let shard_path = shards_path.join(&file_name);
+        if !shard_path.exists() {
+            write_atomic(&shard_path, &compressed)?;
+        }
+        shard_files.insert(file_name);
+        hashes.push((name, hash));
+    }
+
+    let index = ShardedRepodata {
+        info: ShardedSubdirInfo {
+            subdir: subdir.to_string(),
+            base_url: "./".to_string(),
+            shards_base_url: format!("./{SHARDS_DIR}/"),
+            created_at: Some(chrono::Utc::now()),
+        },
+        shards: hashes.into_iter().collect(),
+    };
+    let bytes = rmp_serde::to_vec_named(&index).into_diagnostic()?;
+    let compressed = zstd::encode_all(bytes.as_slice(), ZSTD_LEVEL).into_diagnostic()?;
+    write_atomic(&subdir_path.join(REPODATA_SHARDS), &compressed)?;
+
+    for path in sorted_entries(&shards_path)? {
+        let referenced = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| shard_files.contains(name));
+        if !referenced {
+            fs_err::remove_file(&path).into_diagnostic()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the shard of the package of the record, creating it if needed.
+fn shard_for<'a>(shards: &'a mut BTreeMap<String, Shard>, record: &PackageRecord) -> &'a mut Shard {
+    shards
+        .entry(record.name.as_normalized().to_string())
+        .or_insert_with(|| Shard {
+            packages: Default::default(),
+            conda_packages: Default::default(),
+            removed: Default::default(),
+        })
+}
+
+/// Removes the sharded repodata of a subdirectory.
+fn remove_shards(subdir_path: &Path) -> miette::Result<()> {
+    let index_path = subdir_path.join(REPODATA_SHARDS);
+    if index_path.exists() {
+        fs_err::remove_file(&index_path).into_diagnostic()?;
+    }
+    let shards_path = subdir_path.join(SHARDS_DIR);
+    if shards_path.is_dir() {
+        fs_err::remove_dir_all(&shards_path).into_diagnostic()?;
+    }
+    Ok(())
+}
+
+/// Returns the entries of a directory sorted by path.
+fn sorted_entries(dir: &Path) -> miette::Result<Vec<PathBuf>> {
+    let mut entries = fs_err::read_dir(dir)
+        .into_diagnostic()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()
+        .into_diagnostic()?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Writes to a temporary file first so readers never see a partial file.
+fn write_atomic(path: &Path, contents: &[u8]) -> miette::Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{file_name}.tmp"));
+    fs_err::write(&temp_path, contents).into_diagnostic()?;
+    fs_err::rename(&temp_path, path).into_diagnostic()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_empty_subdir() {
+        let channel_dir = tempfile::tempdir().unwrap();
+        fs_err::create_dir_all(channel_dir.path().join("noarch")).unwrap();
+        fs_err::write(channel_dir.path().join("noarch/README.md"), "not a package").unwrap();
+
+        assert_eq!(
+            index_subdir(channel_dir.path(), "noarch", IndexOptions::default()).unwrap(),
+            0
+        );
+
+        let subdir_path = channel_dir.path().join("noarch");
+        let repodata: serde_json::Value =
+            serde_json::from_str(&fs_err::read_to_string(subdir_path.join(REPODATA_JSON)).unwrap())
+                .unwrap();
+        assert_eq!(repodata["info"]["subdir"], "noarch");
+        assert_eq!(repodata["packages"], serde_json::json!({}));
+        assert_eq!(repodata["packages.conda"], serde_json::json!({}));
+
+        // The compressed variant contains the same repodata.
+        let decompressed = zstd::decode_all(
+            fs_err::read(subdir_path.join(REPODATA_JSON_ZST))
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&decompressed).unwrap(),
+            repodata
+        );
+        assert!(subdir_path.join(REPODATA_SHARDS).is_file());
+
+        // Disabling the variants removes them.
+        index_subdir(
+            channel_dir.path(),
+            "noarch",
+            IndexOptions {
+                write_zst: false,
+                write_shards: false,
+            },
+        )
+        .unwrap();
+        assert!(!subdir_path.join(REPODATA_JSON_ZST).exists());
+        assert!(!subdir_path.join(REPODATA_SHARDS).exists());
+        assert!(!subdir_path.join(SHARDS_DIR).exists());
+    }
+
+    #[test]
+    fn test_index_channel_creates_noarch() {
+        let channel_dir = tempfile::tempdir().unwrap();
+        fs_err::create_dir_all(channel_dir.path().join("linux-64")).unwrap();
+        fs_err::create_dir_all(channel_dir.path().join("not-a-subdir")).unwrap();
+
+        let subdirs = index_channel(channel_dir.path(), IndexOptions::default()).unwrap();
+        assert_eq!(
+            subdirs.keys().collect::<Vec<_>>(),
+            vec!["linux-64", "noarch"]
+        );
+        assert!(channel_dir
+            .path()
+            .join("noarch")
+            .join(REPODATA_JSON)
+            .is_file());
+        assert!(!channel_dir
+            .path()
+            .join("not-a-subdir")
+            .join(REPODATA_JSON)
+            .exists());
+    }
+}
@@ -1,12 +1,19 @@
+use std::str::FromStr;
+
 use clap::Parser;
 use indexmap::IndexMap;
-use pixi_manifest::FeatureName;
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use pep508_rs::{Requirement, VersionOrUrl};
+use pixi_manifest::{pypi::PyPiPackageName, FeatureName, FeaturesExt, HasFeaturesIter, SpecType};
+use rattler_conda_types::{MatchSpec, PackageName, ParseStrictness, Platform, VersionSpec};
 
 use super::has_specs::HasSpecs;
 use crate::{
     cli::cli_config::{DependencyConfig, PrefixUpdateConfig, ProjectConfig},
     environment::verify_prefix_location_unchanged,
-    project::{DependencyType, Project},
+    project::{DependencyType, Project, PypiDeps},
+    repodata::Repodata,
 };
 
 /// Adds dependencies to the project
@@ -54,6 +61,11 @@ use crate::{
 /// will be added to the `tool.pixi.pypi-dependencies` table instead as native
 /// arrays have no support for platform-specific or editable dependencies.
 ///
+/// When a pypi dependency is also available as a conda package in the channels
+/// of the project, pixi points this out. With `--prefer-conda` the conda
+/// package is added instead:
+/// - `pixi add --pypi numpy --prefer-conda`
+///
 /// These dependencies will then be read by pixi as if they had been added to
 /// the pixi `pypi-dependencies` tables of the default or of a named feature.
 ///
@@ -77,6 +89,11 @@ pub struct Args {
     /// Whether the pypi requirement should be editable
     #[arg(long, requires = "pypi")]
     pub editable: bool,
+
+    /// Add the conda package that provides a pypi dependency instead, when the
+    /// channels of the project contain one
+    #[arg(long, requires = "pypi", conflicts_with = "editable")]
+    pub prefer_conda: bool,
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
@@ -97,7 +114,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         .manifest
         .add_platforms(dependency_config.platforms.iter(), &FeatureName::Default)?;
 
-    let (match_specs, pypi_deps) = match dependency_config.dependency_type() {
+    let (mut match_specs, mut pypi_deps) = match dependency_config.dependency_type() {
         DependencyType::CondaDependency(spec_type) => {
             let match_specs = dependency_config
                 .specs()?
@@ -117,6 +134,61 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             (match_specs, pypi_deps)
         }
     };
+
+    // Look up the conda packages that provide the pypi dependencies, to avoid
+    // installing the same package from conda and PyPI.
+    if !pypi_deps.is_empty() {
+        let substitutes = match conda_substitutes(
+            &project,
+            &dependency_config.feature,
+            &dependency_config.platforms,
+            &pypi_deps,
+        )
+        .await
+        {
+            Ok(substitutes) => substitutes,
+            Err(err) if !args.prefer_conda => {
+                tracing::warn!("failed to look up conda packages for the pypi dependencies: {err}");
+                IndexMap::new()
+            }
+            Err(err) => return Err(err),
+        };
+
+        for (pypi_name, conda_name) in substitutes {
+            let (requirement, _) = pypi_deps
+                .get(&pypi_name)
+                .expect("substitutes are pypi dependencies");
+            if args.prefer_conda && !can_substitute(requirement) {
+                eprintln!(
+                    "{}The pypi package {} has extras or markers that the conda package {} can't express, adding it from PyPI instead",
+                    console::style(console::Emoji("⚠️ ", "")).yellow(),
+                    console::style(pypi_name.as_source()).bold(),
+                    console::style(conda_name.as_source()).bold(),
+                );
+            } else if args.prefer_conda {
+                let (requirement, _) = pypi_deps
+                    .shift_remove(&pypi_name)
+                    .expect("substitutes are pypi dependencies");
+                let spec = conda_spec(conda_name.clone(), &requirement)?;
+                eprintln!(
+                    "{}Adding the conda package {} instead of the pypi package {}",
+                    console::style(console::Emoji("✔ ", "")).green(),
+                    console::style(conda_name.as_source()).bold(),
+                    console::style(pypi_name.as_source()).bold(),
+                );
+                match_specs.insert(conda_name, (spec, SpecType::Run));
+            } else {
+                eprintln!(
+                    "{}The pypi package {} is also available as the conda package {}, use {} to add it from conda instead",
+                    console::style(console::Emoji("💡 ", "")).yellow(),
+                    console::style(pypi_name.as_source()).bold(),
+                    console::style(conda_name.as_source()).bold(),
+                    console::style("--prefer-conda").bold(),
+                );
+            }
+        }
+    }
+
     // TODO: add dry_run logic to add
     let dry_run = false;
 
@@ -140,3 +212,168 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     Project::warn_on_discovered_from_env(project_config.manifest_path.as_deref());
     Ok(())
 }
+
+/// Returns the conda packages in the channels of the project that provide the
+/// pypi dependencies. The candidates are found by using the pypi mapping in
+/// reverse, and are verified by mapping their newest record back to PyPI.
+async fn conda_substitutes(
+    project: &Project,
+    feature: &FeatureName,
+    platforms: &[Platform],
+    pypi_deps: &PypiDeps,
+) -> miette::Result<IndexMap<PyPiPackageName, PackageName>> {
+    let environment = project
+        .environments()
+        .into_iter()
+        .find(|environment| environment.features().any(|f| f.name == *feature))
+        .unwrap_or_else(|| project.default_environment());
+    let channel_config = project.channel_config();
+    let channels = environment
+        .channels()
+        .into_iter()
+        .cloned()
+        .map(|channel| channel.into_channel(&channel_config))
+        .collect::<Result<Vec<_>, _>>()
+        .into_diagnostic()?;
+    let channel_names = channels
+        .iter()
+        .map(|channel| channel.canonical_name())
+        .collect_vec();
+    let platforms = if platforms.is_empty() {
+        environment.platforms().into_iter().collect_vec()
+    } else {
+        platforms.to_vec()
+    };
+    let mapping_source = project.pypi_name_mapping_source()?;
+    let offline = project.config().offline();
+
+    let mut substitutes = IndexMap::new();
+    for (name, (requirement, _)) in pypi_deps {
+        // A package from a url can't be provided by a conda package.
+        if matches!(requirement.version_or_url, Some(VersionOrUrl::Url(_))) {
+            continue;
+        }
+
+        let candidates = pypi_mapping::conda_names_for_pypi_package(
+            project.client().clone().into(),
+            mapping_source,
+            &channel_names,
+            name.as_normalized(),
+            offline,
+        )
+        .await?;
+        if candidates.is_empty() {
+            continue;
+        }
+
+        let repodata = project
+            .repodata_gateway()
+            .query(
+                channels.clone(),
+                platforms.iter().copied().chain([Platform::NoArch]),
+                candidates.iter().map(|candidate| MatchSpec {
+                    name: Some(candidate.clone()),
+                    ..MatchSpec::default()
+                }),
+            )
+            .recursive(false)
+            .await
+            .into_diagnostic()
+            .wrap_err("failed to query the channels for conda packages")?;
+
+        for candidate in candidates {
+            let Some(record) = repodata
+                .iter()
+                .flat_map(|repodata| repodata.iter())
+                .filter(|record| record.package_record.name == candidate)
+                .max_by(|a, b| a.package_record.version.cmp(&b.package_record.version))
+            else {
+                continue;
+            };
+
+            let mut record = record.clone();
+            pypi_mapping::amend_pypi_purls(
+                project.client().clone().into(),
+                mapping_source,
+                [&mut record],
+                None,
+                offline,
+            )
+            .await?;
+            let provides_package = record.package_record.purls.iter().flatten().any(|purl| {
+                purl.package_type() == "pypi"
+                    && pep508_rs::PackageName::from_str(purl.name())
+                        .is_ok_and(|purl_name| &purl_name == name.as_normalized())
+            });
+            if provides_package {
+                substitutes.insert(name.clone(), candidate);
+                break;
+            }
+        }
+    }
+
+    Ok(substitutes)
+}
+
+/// Returns whether the pypi requirement can be replaced by a conda package,
+/// which is not the case when it has extras or markers, as these can't be
+/// expressed in a conda spec.
+fn can_substitute(requirement: &Requirement) -> bool {
+    requirement.extras.is_empty() && requirement.marker.is_true()
+}
+
+/// Returns the conda spec for a package that replaces the pypi requirement,
+/// the version specifiers of the requirement are kept.
+fn conda_spec(name: PackageName, requirement: &Requirement) -> miette::Result<MatchSpec> {
+    let version = match &requirement.version_or_url {
+        Some(VersionOrUrl::VersionSpecifier(specifiers)) if !specifiers.is_empty() => {
+            let specifiers = specifiers.iter().map(ToString::to_string).join(",");
+            Some(
+                VersionSpec::from_str(&specifiers, ParseStrictness::Lenient)
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        format!(
+                            "failed to convert '{specifiers}' of {} to a conda version",
+                            requirement.name
+                        )
+                    })?,
+            )
+        }
+        _ => None,
+    };
+    Ok(MatchSpec {
+        name: Some(name),
+        version,
+        ..MatchSpec::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conda_spec() {
+        let name = PackageName::from_str("numpy").unwrap();
+
+        let requirement = Requirement::from_str("numpy>=1.26,<3").unwrap();
+        let spec = conda_spec(name.clone(), &requirement).unwrap();
+        assert_eq!(spec.to_string(), "numpy >=1.26,<3");
+
+        let requirement = Requirement::from_str("numpy").unwrap();
+        let spec = conda_spec(name, &requirement).unwrap();
+        assert_eq!(spec.to_string(), "numpy");
+    }
+
+    #[test]
+    fn test_can_substitute() {
+        let requirement = Requirement::from_str("numpy>=1.26").unwrap();
+        assert!(can_substitute(&requirement));
+
+        let requirement = Requirement::from_str("dask[dataframe]").unwrap();
+        assert!(!can_substitute(&requirement));
+
+        let requirement = Requirement::from_str("pywin32; sys_platform == 'win32'").unwrap();
+        assert!(!can_substitute(&requirement));
+    }
+}
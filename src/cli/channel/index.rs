@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use miette::IntoDiagnostic;
+
+use crate::channel_index::{self, IndexOptions};
+
+/// Create the `repodata.json` of every subdirectory of a channel directory.
+///
+/// Packages placed directly in the directory, like the output of `pixi
+/// build`, are moved into the subdirectory they belong to.
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The directory of the channel
+    #[arg(default_value = ".")]
+    pub channel_dir: PathBuf,
+
+    #[clap(flatten)]
+    pub index_config: IndexConfig,
+}
+
+/// The repodata variants to write next to the `repodata.json`.
+#[derive(Debug, Parser, Clone, Copy)]
+pub struct IndexConfig {
+    /// Don't write the zstd compressed `repodata.json.zst`
+    #[arg(long)]
+    pub no_zstd: bool,
+
+    /// Don't write the sharded repodata
+    #[arg(long)]
+    pub no_sharded: bool,
+}
+
+impl From<IndexConfig> for IndexOptions {
+    fn from(value: IndexConfig) -> Self {
+        Self {
+            write_zst: !value.no_zstd,
+            write_shards: !value.no_sharded,
+        }
+    }
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    index(args.channel_dir, args.index_config).await
+}
+
+/// Indexes the channel and reports the number of packages per subdirectory.
+pub(super) async fn index(channel_dir: PathBuf, config: IndexConfig) -> miette::Result<()> {
+    let indexed = tokio::task::spawn_blocking({
+        let channel_dir = channel_dir.clone();
+        move || channel_index::index_channel(&channel_dir, config.into())
+    })
+    .await
+    .into_diagnostic()??;
+
+    for (subdir, count) in indexed {
+        eprintln!(
+            "{}Indexed {} in {}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            pluralize_packages(count),
+            console::style(display_subdir(&channel_dir, &subdir)).bold()
+        );
+    }
+    Ok(())
+}
+
+fn display_subdir(channel_dir: &Path, subdir: &str) -> String {
+    channel_dir.join(subdir).display().to_string()
+}
+
+fn pluralize_packages(count: usize) -> String {
+    if count == 1 {
+        "1 package".to_string()
+    } else {
+        format!("{count} packages")
+    }
+}
@@ -0,0 +1,25 @@
+use clap::Parser;
+
+pub mod index;
+pub mod serve;
+
+/// Commands to manage a channel in a local directory.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Parser)]
+pub enum Command {
+    Index(index::Args),
+    Serve(serve::Args),
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Command::Index(args) => index::execute(args).await?,
+        Command::Serve(args) => serve::execute(args).await?,
+    };
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use clap::Parser;
+use miette::IntoDiagnostic;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+use super::index::{self, IndexConfig};
+
+/// Serve a channel directory over http.
+///
+/// The channel is indexed before it is served, the url that is printed can be
+/// added to the channels of another project.
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The directory of the channel
+    #[arg(default_value = ".")]
+    pub channel_dir: PathBuf,
+
+    /// The address to listen on
+    #[arg(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    pub host: IpAddr,
+
+    /// The port to listen on
+    #[arg(long, default_value_t = 8000)]
+    pub port: u16,
+
+    /// Serve the directory as is without indexing it first
+    #[arg(long)]
+    pub no_index: bool,
+
+    #[clap(flatten)]
+    pub index_config: IndexConfig,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    if !args.no_index {
+        index::index(args.channel_dir.clone(), args.index_config).await?;
+    }
+
+    let root = dunce::canonicalize(&args.channel_dir).into_diagnostic()?;
+    let listener = TcpListener::bind(SocketAddr::new(args.host, args.port))
+        .await
+        .into_diagnostic()?;
+    let address = listener.local_addr().into_diagnostic()?;
+    eprintln!(
+        "Serving {} at {}, press Ctrl+C to stop",
+        root.display(),
+        console::style(format!("http://{address}")).bold()
+    );
+
+    tokio::select! {
+        _ = serve(listener, root) => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    Ok(())
+}
+
+/// Answers the requests that are accepted by the listener with the files in
+/// the root directory, until the future is dropped.
+pub async fn serve(listener: TcpListener, root: PathBuf) {
+    let root = Arc::new(root);
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::warn!("failed to accept connection: {e}");
+                continue;
+            }
+        };
+        let root = root.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &root).await {
+                tracing::debug!("connection with {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+/// Answers a single request, the connection is closed afterwards.
+async fn handle_connection(stream: TcpStream, root: &Path) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await? == 0 {
+        return Ok(());
+    }
+    // The headers are not needed, but they have to be read before responding.
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next());
+    let head_only = method == "HEAD";
+    if method != "GET" && !head_only {
+        return write_response(&mut writer, "405 Method Not Allowed", None, head_only).await;
+    }
+
+    let file = match target.and_then(|target| resolve_path(root, target)) {
+        Some(path) if path.is_file() => Some(path),
+        _ => None,
+    };
+    let Some(path) = file else {
+        tracing::debug!("{method} {} -> 404", target.unwrap_or_default());
+        return write_response(&mut writer, "404 Not Found", None, head_only).await;
+    };
+    tracing::debug!("{method} {} -> 200", target.unwrap_or_default());
+    write_response(&mut writer, "200 OK", Some(&path), head_only).await
+}
+
+async fn write_response(
+    writer: &mut (impl AsyncWrite + Unpin),
+    status: &str,
+    file: Option<&Path>,
+    head_only: bool,
+) -> std::io::Result<()> {
+    let (content_type, content_length) = match file {
+        Some(path) => (content_type(path), tokio::fs::metadata(path).await?.len()),
+        None => ("text/plain", status.len() as u64),
+    };
+    writer
+        .write_all(
+            format!(
+                "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {content_length}\r\nConnection: close\r\n\r\n"
+            )
+            .as_bytes(),
+        )
+        .await?;
+
+    if !head_only {
+        match file {
+            Some(path) => {
+                let mut file = tokio::fs::File::open(path).await?;
+                tokio::io::copy(&mut file, writer).await?;
+            }
+            None => writer.write_all(status.as_bytes()).await?,
+        }
+    }
+    writer.shutdown().await
+}
+
+/// Returns the path of the file that is requested. Dot segments are resolved
+/// when the target is parsed, so the path never leaves the root directory.
+fn resolve_path(root: &Path, target: &str) -> Option<PathBuf> {
+    let url = Url::parse("http://localhost/").ok()?.join(target).ok()?;
+    let mut path = root.to_path_buf();
+    for segment in url.path_segments()? {
+        let segment = percent_encoding::percent_decode_str(segment)
+            .decode_utf8()
+            .ok()?;
+        if segment.is_empty() {
+            continue;
+        }
+        if segment == ".." || segment.contains(['/', '\\']) {
+            return None;
+        }
+        path.push(&*segment);
+    }
+    Some(path)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_path() {
+        let root = Path::new("/srv/channel");
+        assert_eq!(
+            resolve_path(root, "/noarch/repodata.json"),
+            Some(root.join("noarch").join("repodata.json"))
+        );
+        assert_eq!(
+            resolve_path(root, "/linux-64/foo-1.0%2Bcpu-0.conda?query=1"),
+            Some(root.join("linux-64").join("foo-1.0+cpu-0.conda"))
+        );
+        assert_eq!(
+            resolve_path(root, "/../../etc/passwd"),
+            Some(root.join("etc").join("passwd"))
+        );
+        assert_eq!(
+            resolve_path(root, "/noarch/%2E%2E/%2E%2E/secret"),
+            Some(root.join("secret"))
+        );
+        assert_eq!(resolve_path(root, "/noarch/..%2F..%2Fsecret"), None);
+    }
+}
@@ -15,7 +15,7 @@ use rattler_virtual_packages::{VirtualPackage, VirtualPackageOverrides};
 use reqwest_middleware::ClientWithMiddleware;
 
 use super::cli_config::ChannelsConfig;
-use crate::prefix::Prefix;
+use crate::{prefix::Prefix, repodata::with_offline_context};
 
 /// Run a command in a temporary environment.
 #[derive(Parser, Debug)]
@@ -143,16 +143,29 @@ pub async fn create_exec_prefix(
         args.specs.clone()
     };
 
-    let channels = args.channels.resolve_from_config(config)?;
+    let channels = args
+        .channels
+        .resolve_from_config(config)?
+        .into_iter()
+        .collect::<Vec<_>>();
 
     // Get the repodata for the specs
     let repodata = await_in_progress("fetching repodata for environment", |_| async {
-        gateway
-            .query(channels, [args.platform, Platform::NoArch], specs.clone())
-            .recursive(true)
-            .execute()
-            .await
-            .into_diagnostic()
+        with_offline_context(
+            gateway
+                .query(
+                    channels.clone(),
+                    [args.platform, Platform::NoArch],
+                    specs.clone(),
+                )
+                .recursive(true)
+                .execute()
+                .await
+                .into_diagnostic(),
+            config.offline(),
+            &channels,
+            args.platform,
+        )
     })
     .await
     .context("failed to get repodata")?;
@@ -7,14 +7,16 @@ use std::{
 };
 
 use clap::{Parser, ValueEnum};
+use indexmap::IndexMap;
 use miette::{Context, IntoDiagnostic};
 use minijinja::{context, Environment};
 use pixi_config::{get_default_author, Config};
 use pixi_consts::consts;
 use pixi_manifest::{
-    pyproject::PyProjectManifest, DependencyOverwriteBehavior, FeatureName, SpecType,
+    pyproject::PyProjectManifest, python_import::ImportedPythonProject,
+    DependencyOverwriteBehavior, FeatureName, SpecType,
 };
-use pixi_utils::conda_environment_file::CondaEnvFile;
+use pixi_utils::conda_environment_file::{CondaEnvFile, PipOptions};
 use rattler_conda_types::{NamedChannelOrUrl, Platform};
 use tokio::fs::OpenOptions;
 use url::Url;
@@ -44,6 +46,9 @@ pub struct Args {
     pub platforms: Vec<String>,
 
     /// Environment.yml file to bootstrap the project.
+    ///
+    /// Also accepts the `pyproject.toml`, `poetry.lock`, `Pipfile`,
+    /// `Pipfile.lock` or `uv.lock` of a poetry, pipenv or uv project.
     #[arg(short = 'i', long = "import")]
     pub env_file: Option<PathBuf>,
 
@@ -74,7 +79,7 @@ name = "{{ name }}"
 platforms = {{ platforms }}
 version = "{{ version }}"
 
-{%- if index_url or extra_indexes %}
+{%- if index_url or extra_index_urls %}
 
 [pypi-options]
 {% if index_url %}index-url = "{{ index_url }}"{% endif %}
@@ -135,7 +140,7 @@ channels = {{ channels }}
 platforms = {{ platforms }}
 
 
-{%- if index_url or extra_indexes %}
+{%- if index_url or extra_index_urls %}
 
 [tool.pixi.pypi-options]
 {% if index_url %}index-url = "{{ index_url }}"{% endif %}
@@ -219,11 +224,40 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             miette::bail!("{} already exists", consts::PROJECT_MANIFEST);
         }
 
+        // Import the dependencies of a poetry, pipenv or uv project.
+        if let Some((kind, imported)) = ImportedPythonProject::from_path(&env_file_path, &dir)? {
+            let name = imported.name.clone().unwrap_or(default_name);
+            let rv = render_project(
+                &env,
+                name,
+                version,
+                author.as_ref(),
+                config.default_channels().to_vec(),
+                &platforms,
+                None,
+                &vec![],
+            );
+            let mut document = toml_edit::DocumentMut::from_str(&rv).into_diagnostic()?;
+            imported.add_to_document(&mut document)?;
+            let mut project = Project::from_str(&pixi_manifest_path, &document.to_string())?;
+            project.save()?;
+
+            eprintln!(
+                "{}Created {} from the {kind} project '{}'",
+                console::style(console::Emoji("✔ ", "")).green(),
+                project.manifest_path().display(),
+                env_file_path.display()
+            );
+            return Ok(());
+        }
+
         let env_file = CondaEnvFile::from_path(&env_file_path)?;
         let name = env_file
             .name()
             .unwrap_or(default_name.clone().as_str())
             .to_string();
+        let variables = env_file.variables().clone();
+        let pip_options = env_file.pip_options()?;
 
         // TODO: Improve this:
         //  - Use .condarc as channel config
@@ -235,9 +269,10 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             author.as_ref(),
             channels,
             &platforms,
-            None,
-            &vec![],
+            pip_options.index_url.as_ref(),
+            &pip_options.extra_index_urls,
         );
+        let rv = add_env_file_tables(&rv, &variables, &pip_options)?;
         let mut project = Project::from_str(&pixi_manifest_path, &rv)?;
         let channel_config = project.channel_config();
         for spec in conda_deps {
@@ -482,6 +517,78 @@ fn render_project(
     .unwrap()
 }
 
+/// Adds the parts of a conda environment file that can't be added through the
+/// manifest: the activation variables, the find-links and the local packages
+/// of the `pip` section.
+fn add_env_file_tables(
+    manifest: &str,
+    variables: &IndexMap<String, String>,
+    pip_options: &PipOptions,
+) -> miette::Result<String> {
+    let mut document = toml_edit::DocumentMut::from_str(manifest).into_diagnostic()?;
+    if !pip_options.find_links.is_empty() {
+        let find_links = pip_options
+            .find_links
+            .iter()
+            .map(|location| {
+                let key = if Url::parse(location).is_ok() {
+                    "url"
+                } else {
+                    "path"
+                };
+                toml_edit::InlineTable::from_iter([(
+                    key,
+                    toml_edit::Value::from(location.as_str()),
+                )])
+            })
+            .collect::<toml_edit::Array>();
+        get_or_insert_table(&mut document, "pypi-options")
+            .insert("find-links", toml_edit::value(find_links));
+    }
+
+    for dependency in &pip_options.path_dependencies {
+        let name = match (&dependency.name, dependency.path.file_name()) {
+            (Some(name), _) => name.clone(),
+            (None, Some(file_name)) => file_name.to_string_lossy().into_owned(),
+            (None, None) => miette::bail!(
+                "Can't determine the name of the pypi dependency at '{}', add '#egg=<name>' to the path",
+                dependency.path.display()
+            ),
+        };
+        let mut requirement = toml_edit::InlineTable::new();
+        requirement.insert(
+            "path",
+            toml_edit::Value::from(dependency.path.to_string_lossy().as_ref()),
+        );
+        if dependency.editable {
+            requirement.insert("editable", toml_edit::Value::from(true));
+        }
+        get_or_insert_table(&mut document, "pypi-dependencies")
+            .insert(&name, toml_edit::value(requirement));
+    }
+
+    if !variables.is_empty() {
+        let mut env = toml_edit::InlineTable::new();
+        for (key, value) in variables {
+            env.insert(key, toml_edit::Value::from(value.as_str()));
+        }
+        get_or_insert_table(&mut document, "activation").insert("env", toml_edit::value(env));
+    }
+
+    Ok(document.to_string())
+}
+
+fn get_or_insert_table<'a>(
+    document: &'a mut toml_edit::DocumentMut,
+    key: &str,
+) -> &'a mut toml_edit::Table {
+    document
+        .entry(key)
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .expect("the entry should be a table")
+}
+
 /// Save the rendered template to a file, and print a message to the user.
 fn save_manifest_file(path: &Path, content: String) -> miette::Result<()> {
     fs_err::write(path, content).into_diagnostic()?;
@@ -1,11 +1,16 @@
+use std::path::PathBuf;
+
 use crate::cli::cli_config::ProjectConfig;
-use crate::environment::get_update_lock_file_and_prefix;
-use crate::lock_file::UpdateMode;
+use crate::environment::{get_update_lock_file_and_prefix, LockFileUsage};
+use crate::lock_file::{ArtifactBundle, UpdateMode};
+use crate::prefix::Prefix;
 use crate::{Project, UpdateLockFileOptions};
 use clap::Parser;
 use fancy_display::FancyDisplay;
 use itertools::Itertools;
+use miette::IntoDiagnostic;
 use pixi_config::ConfigCli;
+use rattler_conda_types::Platform;
 
 /// Install all dependencies
 #[derive(Parser, Debug)]
@@ -25,11 +30,88 @@ pub struct Args {
 
     #[arg(long, short, conflicts_with = "environment")]
     pub all: bool,
+
+    /// Install the packages from a bundle that was created with `pixi lock
+    /// fetch` instead of downloading them. Implies `--frozen` and `--offline`.
+    #[arg(long, value_name = "DIR")]
+    pub from_bundle: Option<PathBuf>,
+
+    /// Install the locked packages of another platform into `--prefix`, e.g.
+    /// to create an environment for a container. Link scripts are not
+    /// executed and only PyPI wheels can be installed.
+    #[arg(long, short, requires = "prefix", conflicts_with = "all")]
+    pub platform: Option<Platform>,
+
+    /// The directory to install the environment into, requires `--platform`.
+    #[arg(long, requires = "platform")]
+    pub prefix: Option<PathBuf>,
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
+    let mut config = args.config;
+    if args.from_bundle.is_some() {
+        config.offline = true;
+    }
     let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
-        .with_cli_config(args.config);
+        .with_cli_config(config);
+
+    // Install into a separate prefix for another platform.
+    if let (Some(platform), Some(prefix)) = (args.platform, &args.prefix) {
+        let environment = match args.environment.as_deref() {
+            None => None,
+            Some([environment]) => Some(environment.clone()),
+            Some(_) => miette::bail!("only a single environment can be installed into `--prefix`"),
+        };
+        let environment = project.environment_from_name_or_env_var(environment)?;
+
+        let lock_file_usage = if args.from_bundle.is_some() {
+            LockFileUsage::Frozen
+        } else {
+            args.lock_file_usage.into()
+        };
+        let mut lock_file = project
+            .update_lock_file(UpdateLockFileOptions {
+                lock_file_usage,
+                no_install: true,
+                max_concurrent_solves: project.config().max_concurrent_solves(),
+            })
+            .await?;
+        if let Some(bundle_dir) = &args.from_bundle {
+            lock_file.artifact_bundle = Some(ArtifactBundle::from_path(bundle_dir)?);
+        }
+        fs_err::create_dir_all(prefix).into_diagnostic()?;
+        let prefix = Prefix::new(dunce::canonicalize(prefix).into_diagnostic()?);
+        lock_file
+            .foreign_prefix(&environment, platform, &prefix)
+            .await?;
+
+        eprintln!(
+            "{}The {} environment has been installed for {} in '{}'.",
+            console::style(console::Emoji("✔ ", "")).green(),
+            environment.name().fancy_display(),
+            console::style(platform).bold(),
+            console::style(prefix.root().display()).bold()
+        );
+        Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+        return Ok(());
+    }
+
+    // When installing from a bundle the lock file is used as is and the
+    // artifacts are taken from the bundle.
+    let mut bundled_lock_file = match &args.from_bundle {
+        Some(bundle_dir) => {
+            let mut lock_file = project
+                .update_lock_file(UpdateLockFileOptions {
+                    lock_file_usage: LockFileUsage::Frozen,
+                    no_install: true,
+                    max_concurrent_solves: project.config().max_concurrent_solves(),
+                })
+                .await?;
+            lock_file.artifact_bundle = Some(ArtifactBundle::from_path(bundle_dir)?);
+            Some(lock_file)
+        }
+        None => None,
+    };
 
     // Install either:
     //
@@ -53,16 +135,25 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         let environment = project.environment_from_name_or_env_var(Some(env))?;
 
         // Update the prefix by installing all packages
-        get_update_lock_file_and_prefix(
-            &environment,
-            UpdateMode::Revalidate,
-            UpdateLockFileOptions {
-                lock_file_usage: args.lock_file_usage.into(),
-                no_install: false,
-                max_concurrent_solves: project.config().max_concurrent_solves(),
-            },
-        )
-        .await?;
+        match &mut bundled_lock_file {
+            Some(lock_file) => {
+                lock_file
+                    .prefix(&environment, UpdateMode::Revalidate)
+                    .await?;
+            }
+            None => {
+                get_update_lock_file_and_prefix(
+                    &environment,
+                    UpdateMode::Revalidate,
+                    UpdateLockFileOptions {
+                        lock_file_usage: args.lock_file_usage.into(),
+                        no_install: false,
+                        max_concurrent_solves: project.config().max_concurrent_solves(),
+                    },
+                )
+                .await?;
+            }
+        }
 
         installed_envs.push(environment.name().clone());
     }
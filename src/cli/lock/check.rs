@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use clap::Parser;
+use itertools::Itertools;
+use pixi_consts::consts;
+use rattler_conda_types::Platform;
+use serde::Serialize;
+
+use crate::{
+    build::GlobHashCache,
+    cli::cli_config::ProjectConfig,
+    load_lock_file,
+    lock_file::{OutdatedEnvironments, OutdatedTarget},
+    Project,
+};
+
+/// Check if the lock file is up-to-date with the manifest.
+///
+/// Exits with a non-zero exit code if the lock file is out of date.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// Explain why every environment and platform is out of date.
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Output the result in JSON format.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// The result of the check in JSON format.
+#[derive(Serialize)]
+struct JsonCheck {
+    up_to_date: bool,
+    outdated: Vec<JsonOutdated>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasons: Option<Vec<JsonReason>>,
+}
+
+/// A platform of an environment that is out of date.
+#[derive(Serialize)]
+struct JsonOutdated {
+    environment: String,
+    platform: Platform,
+    conda: bool,
+    pypi: bool,
+}
+
+/// The reason why an environment, or a platform of an environment, is out of
+/// date.
+#[derive(Serialize)]
+struct JsonReason {
+    environment: String,
+    platform: Option<Platform>,
+    pypi_only: bool,
+    kind: &'static str,
+    message: String,
+}
+
+impl From<&OutdatedTarget<'_>> for JsonReason {
+    fn from(target: &OutdatedTarget<'_>) -> Self {
+        Self {
+            environment: target.environment.name().to_string(),
+            platform: target.platform,
+            pypi_only: target.pypi_only,
+            kind: target.reason.kind(),
+            message: target.reason.to_string(),
+        }
+    }
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?;
+    let lock_file = load_lock_file(&project).await?;
+    let outdated = OutdatedEnvironments::from_project_and_lock_file(
+        &project,
+        &lock_file,
+        GlobHashCache::default(),
+    )
+    .await;
+
+    // Collect the outdated platforms per environment, sorted by name.
+    let mut outdated_platforms: BTreeMap<String, BTreeMap<Platform, (bool, bool)>> =
+        BTreeMap::new();
+    for (environment, platforms) in &outdated.conda {
+        for platform in platforms {
+            outdated_platforms
+                .entry(environment.name().to_string())
+                .or_default()
+                .entry(*platform)
+                .or_default()
+                .0 = true;
+        }
+    }
+    for (environment, platforms) in &outdated.pypi {
+        for platform in platforms {
+            outdated_platforms
+                .entry(environment.name().to_string())
+                .or_default()
+                .entry(*platform)
+                .or_default()
+                .1 = true;
+        }
+    }
+
+    if args.json {
+        let json = JsonCheck {
+            up_to_date: outdated.is_empty(),
+            outdated: outdated_platforms
+                .iter()
+                .flat_map(|(environment, platforms)| {
+                    platforms
+                        .iter()
+                        .map(|(platform, (conda, pypi))| JsonOutdated {
+                            environment: environment.clone(),
+                            platform: *platform,
+                            conda: *conda,
+                            pypi: *pypi,
+                        })
+                })
+                .collect(),
+            reasons: args
+                .explain
+                .then(|| outdated.reasons.iter().map(JsonReason::from).collect()),
+        };
+        let json = serde_json::to_string_pretty(&json).expect("failed to convert to json");
+        println!("{}", json);
+    } else if outdated.is_empty() {
+        eprintln!(
+            "{}The lock file is up-to-date",
+            console::style(console::Emoji("✔ ", "")).green()
+        );
+    } else {
+        eprintln!(
+            "{}The lock file is not up-to-date with the project",
+            console::style(console::Emoji("✘ ", "")).red()
+        );
+        for (environment, platforms) in &outdated_platforms {
+            eprintln!(
+                "  {}: {}",
+                consts::ENVIRONMENT_STYLE.apply_to(environment),
+                platforms
+                    .iter()
+                    .format_with(", ", |(platform, (conda, _)), f| if *conda {
+                        f(&consts::PLATFORM_STYLE.apply_to(platform))
+                    } else {
+                        f(&format_args!(
+                            "{} (pypi)",
+                            consts::PLATFORM_STYLE.apply_to(platform)
+                        ))
+                    })
+            );
+            if args.explain {
+                for target in outdated
+                    .reasons
+                    .iter()
+                    .filter(|target| target.environment.name().as_str() == environment)
+                {
+                    eprintln!("    - {}", format_reason(target));
+                }
+            }
+        }
+    }
+
+    if !outdated.is_empty() {
+        std::process::exit(1);
+    }
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Formats the reason why a target is out of date on a single line.
+fn format_reason(target: &OutdatedTarget<'_>) -> String {
+    let platform = match target.platform {
+        Some(platform) => consts::PLATFORM_STYLE.apply_to(platform).to_string(),
+        None => String::from("all platforms"),
+    };
+    let pypi = if target.pypi_only { " (pypi)" } else { "" };
+    format!("{platform}{pypi}: {}", target.reason)
+}
@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use miette::{Context, IntoDiagnostic};
+use rattler_lock::LockFile;
+use tokio::process::Command;
+
+use crate::{
+    cli::cli_config::ProjectConfig,
+    diff::{LockFileDiff, LockFileJsonDiff},
+    Project,
+};
+
+/// Compare two lock files and show the packages that were added, removed,
+/// upgraded or downgraded per environment and platform.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// The lock file to compare from. Either the path to a lock file or a git
+    /// revision (e.g. `main` or `HEAD~1`) at which the lock file of the
+    /// project is read.
+    #[arg(long, default_value = "HEAD")]
+    pub from: String,
+
+    /// The lock file to compare to. Defaults to the lock file of the project.
+    #[arg(long)]
+    pub to: Option<PathBuf>,
+
+    /// Output the diff in JSON format.
+    #[arg(long, conflicts_with = "markdown")]
+    pub json: bool,
+
+    /// Output the diff as markdown tables, e.g. to post on a pull request.
+    #[arg(long)]
+    pub markdown: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?;
+    let lock_file_path = project.lock_file_path();
+
+    let from = if Path::new(&args.from).is_file() {
+        read_lock_file(Path::new(&args.from)).await?
+    } else {
+        read_lock_file_at_revision(&lock_file_path, &args.from).await?
+    };
+    let to = read_lock_file(args.to.as_deref().unwrap_or(&lock_file_path)).await?;
+
+    let diff = LockFileDiff::from_lock_files(&from, &to);
+    if args.json {
+        let json_diff = LockFileJsonDiff::new(&project, diff);
+        let json = serde_json::to_string_pretty(&json_diff).expect("failed to convert to json");
+        println!("{}", json);
+    } else if args.markdown {
+        print!("{}", diff.to_markdown());
+    } else if diff.is_empty() {
+        eprintln!(
+            "{}The lock files are identical",
+            console::style(console::Emoji("✔ ", "")).green()
+        );
+    } else {
+        diff.print()
+            .into_diagnostic()
+            .context("failed to print lock-file diff")?;
+    }
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Reads a lock file from disk.
+async fn read_lock_file(path: &Path) -> miette::Result<LockFile> {
+    let contents = fs_err::tokio::read_to_string(path)
+        .await
+        .into_diagnostic()?;
+    parse_lock_file(&contents, &path.display().to_string())
+}
+
+/// Reads the contents of the lock file at the given git revision using
+/// `git show`.
+async fn read_lock_file_at_revision(
+    lock_file_path: &Path,
+    revision: &str,
+) -> miette::Result<LockFile> {
+    let directory = lock_file_path
+        .parent()
+        .expect("the lock file must be in a directory");
+    let file_name = lock_file_path
+        .file_name()
+        .expect("the lock file must have a file name")
+        .to_string_lossy();
+
+    // A path starting with `./` is interpreted by git relative to the current
+    // directory instead of the root of the repository.
+    let object = format!("{revision}:./{file_name}");
+    let output = Command::new("git")
+        .arg("show")
+        .arg(&object)
+        .current_dir(directory)
+        .output()
+        .await
+        .into_diagnostic()
+        .wrap_err("failed to execute `git show`, is git installed?")?;
+    if !output.status.success() {
+        miette::bail!(
+            "failed to read the lock file at revision '{revision}': {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let contents = String::from_utf8(output.stdout).into_diagnostic()?;
+    parse_lock_file(&contents, &object)
+}
+
+/// Parses the contents of a lock file, `source` describes where the contents
+/// came from.
+fn parse_lock_file(contents: &str, source: &str) -> miette::Result<LockFile> {
+    contents
+        .parse::<LockFile>()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to parse the lock file from `{source}`"))
+}
@@ -0,0 +1,287 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use futures::{stream, StreamExt, TryStreamExt};
+use indicatif::ProgressBar;
+use miette::{Context, IntoDiagnostic};
+use pixi_record::PixiRecord;
+use rattler_conda_types::Platform;
+use rattler_lock::{PackageHashes, UrlOrPath};
+use reqwest_middleware::ClientWithMiddleware;
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+use crate::{
+    cli::cli_config::ProjectConfig,
+    install_pypi::utils::strip_direct_scheme,
+    lock_file::{conda_hashes, verify_hashes, ArtifactBundle},
+    Project,
+};
+
+/// Download all the artifacts of the lock file into a bundle.
+///
+/// The conda packages and PyPI wheels and sdists of the selected environments
+/// and platforms are downloaded into the output directory and verified against
+/// the hashes in the lock file. The bundle can be installed without network
+/// access with `pixi install --from-bundle`.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// The directory to write the bundle to.
+    #[arg(long, short)]
+    pub output: PathBuf,
+
+    /// The environments to fetch the artifacts of. Defaults to all
+    /// environments.
+    #[arg(long, short)]
+    pub environment: Vec<String>,
+
+    /// The platforms to fetch the artifacts for. Defaults to all platforms of
+    /// the environments.
+    #[arg(long, short)]
+    pub platform: Vec<Platform>,
+}
+
+/// An artifact that is part of the bundle.
+struct Artifact {
+    /// The url to download the artifact from.
+    url: Url,
+    /// The path of the artifact in the bundle.
+    path: PathBuf,
+    /// The hashes of the artifact from the lock file.
+    hashes: Option<PackageHashes>,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?;
+    let lock_file = project.get_lock_file().await?;
+
+    let environments = if args.environment.is_empty() {
+        project.environments()
+    } else {
+        args.environment
+            .iter()
+            .map(|name| {
+                project
+                    .environment(name.as_str())
+                    .ok_or_else(|| miette::miette!("unknown environment '{name}'"))
+            })
+            .collect::<miette::Result<Vec<_>>>()?
+    };
+
+    fs_err::create_dir_all(&args.output).into_diagnostic()?;
+    let mut bundle = ArtifactBundle::new(dunce::canonicalize(&args.output).into_diagnostic()?);
+
+    // Collect the artifacts, packages that are shared between environments
+    // and platforms are only fetched once.
+    let mut artifacts = BTreeMap::new();
+    for environment in environments {
+        let Some(locked_environment) = lock_file.environment(environment.name().as_str()) else {
+            miette::bail!(
+                "the lock file does not contain the {} environment, run `pixi lock` to update it",
+                environment.name().fancy_display()
+            );
+        };
+        let platforms = if args.platform.is_empty() {
+            environment.platforms().into_iter().collect()
+        } else {
+            args.platform.clone()
+        };
+
+        for platform in platforms {
+            for package in locked_environment
+                .conda_packages(platform)
+                .into_iter()
+                .flatten()
+            {
+                let record = match PixiRecord::try_from(package.clone()).into_diagnostic()? {
+                    PixiRecord::Binary(record) => record,
+                    PixiRecord::Source(record) => {
+                        tracing::warn!(
+                            "skipping '{}' because source packages cannot be bundled",
+                            record.package_record.name.as_source()
+                        );
+                        continue;
+                    }
+                };
+                if artifacts.contains_key(&record.url) {
+                    continue;
+                }
+                let path =
+                    bundle.insert_conda(&record.url, &record.package_record, &record.file_name);
+                artifacts.insert(
+                    record.url.clone(),
+                    Artifact {
+                        url: record.url.clone(),
+                        path,
+                        hashes: conda_hashes(&record.package_record),
+                    },
+                );
+            }
+
+            for (package, _) in locked_environment
+                .pypi_packages(platform)
+                .into_iter()
+                .flatten()
+            {
+                let UrlOrPath::Url(url) = &package.location else {
+                    tracing::warn!(
+                        "skipping '{}' because packages from a local path cannot be bundled",
+                        package.name
+                    );
+                    continue;
+                };
+                if artifacts.contains_key(url) {
+                    continue;
+                }
+                let download_url = strip_direct_scheme(url).into_owned();
+                let Some(filename) = download_url
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .map(|segment| {
+                        percent_encoding::percent_decode_str(segment).decode_utf8_lossy()
+                    })
+                    .filter(|filename| !filename.is_empty())
+                    .map(|filename| filename.into_owned())
+                else {
+                    tracing::warn!(
+                        "skipping '{}' because its url does not refer to a file",
+                        package.name
+                    );
+                    continue;
+                };
+                let path = bundle.insert_pypi(url, &filename);
+                artifacts.insert(
+                    url.clone(),
+                    Artifact {
+                        url: download_url,
+                        path,
+                        hashes: package.hash.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    let progress = pixi_progress::global_multi_progress().add(
+        ProgressBar::new(artifacts.len() as u64)
+            .with_style(pixi_progress::default_progress_style())
+            .with_prefix("fetching artifacts"),
+    );
+    let client = project.authenticated_client();
+    let downloaded = stream::iter(artifacts.values())
+        .map(|artifact| {
+            let progress = progress.clone();
+            async move {
+                let downloaded = fetch_artifact(client, artifact).await?;
+                progress.inc(1);
+                Ok::<_, miette::Report>(downloaded)
+            }
+        })
+        .buffer_unordered(project.config().max_concurrent_downloads())
+        .try_fold(0, |count, downloaded| async move {
+            Ok(count + usize::from(downloaded))
+        })
+        .await?;
+    progress.finish_and_clear();
+
+    bundle.write()?;
+
+    eprintln!(
+        "{}Fetched {} artifacts into '{}' ({} downloaded)",
+        console::style(console::Emoji("✔ ", "")).green(),
+        artifacts.len(),
+        args.output.display(),
+        downloaded
+    );
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Downloads the artifact into the bundle unless a valid copy already exists.
+/// Artifacts without hashes can't be checked, so these are always downloaded
+/// again. Returns `true` if the artifact was downloaded.
+async fn fetch_artifact(
+    client: &ClientWithMiddleware,
+    artifact: &Artifact,
+) -> miette::Result<bool> {
+    if artifact.hashes.is_none() {
+        tracing::warn!(
+            "'{}' has no hash in the lock file, its contents can't be verified",
+            artifact.url
+        );
+    } else if artifact.path.is_file() && verify(artifact, artifact.path.clone()).await? {
+        return Ok(false);
+    }
+
+    let file_name = artifact
+        .path
+        .file_name()
+        .expect("artifacts always have a file name")
+        .to_string_lossy();
+    let partial_path = artifact.path.with_file_name(format!("{file_name}.part"));
+    if let Some(parent) = artifact.path.parent() {
+        fs_err::tokio::create_dir_all(parent)
+            .await
+            .into_diagnostic()?;
+    }
+
+    if artifact.url.scheme() == "file" {
+        let Ok(source) = artifact.url.to_file_path() else {
+            miette::bail!("'{}' is not a valid file path", artifact.url);
+        };
+        fs_err::tokio::copy(source, &partial_path)
+            .await
+            .into_diagnostic()?;
+    } else {
+        let response = client
+            .get(artifact.url.clone())
+            .send()
+            .await
+            .into_diagnostic()
+            .and_then(|response| response.error_for_status().into_diagnostic())
+            .wrap_err_with(|| format!("failed to download '{}'", artifact.url))?;
+        let mut file = fs_err::tokio::File::create(&partial_path)
+            .await
+            .into_diagnostic()?;
+        let mut chunks = response.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to download '{}'", artifact.url))?;
+            file.write_all(&chunk).await.into_diagnostic()?;
+        }
+        file.flush().await.into_diagnostic()?;
+    }
+
+    if !verify(artifact, partial_path.clone()).await? {
+        fs_err::tokio::remove_file(&partial_path)
+            .await
+            .into_diagnostic()?;
+        miette::bail!(
+            "the hash of '{}' does not match the hash in the lock file",
+            artifact.url
+        );
+    }
+    fs_err::tokio::rename(&partial_path, &artifact.path)
+        .await
+        .into_diagnostic()?;
+
+    Ok(true)
+}
+
+/// Verifies the file against the hashes of the artifact. Artifacts without
+/// hashes are always considered valid.
+async fn verify(artifact: &Artifact, path: PathBuf) -> miette::Result<bool> {
+    let Some(hashes) = artifact.hashes.clone() else {
+        return Ok(true);
+    };
+    tokio::task::spawn_blocking(move || verify_hashes(&path, &hashes))
+        .await
+        .into_diagnostic()?
+        .into_diagnostic()
+}
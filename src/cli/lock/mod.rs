@@ -0,0 +1,28 @@
+use clap::Parser;
+
+pub mod check;
+pub mod diff;
+pub mod fetch;
+
+/// Commands to inspect the lock file of the project.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Parser)]
+pub enum Command {
+    Check(check::Args),
+    Diff(diff::Args),
+    Fetch(fetch::Args),
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Command::Check(args) => check::execute(args).await?,
+        Command::Diff(args) => diff::execute(args).await?,
+        Command::Fetch(args) => fetch::execute(args).await?,
+    };
+    Ok(())
+}
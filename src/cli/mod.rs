@@ -13,6 +13,7 @@ use tracing_subscriber::{
 
 pub mod add;
 mod build;
+pub mod channel;
 pub mod clean;
 pub mod cli_config;
 pub mod completion;
@@ -24,6 +25,7 @@ pub mod info;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod lock;
 pub mod project;
 pub mod remove;
 pub mod run;
@@ -32,10 +34,12 @@ pub mod self_update;
 pub mod shell;
 pub mod shell_hook;
 pub mod task;
+mod task_shell;
 pub mod tree;
 pub mod update;
 pub mod upgrade;
 pub mod upload;
+pub mod why;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -125,6 +129,8 @@ pub enum Command {
     List(list::Args),
     #[clap(visible_alias = "t")]
     Tree(tree::Args),
+    Why(why::Args),
+    Lock(lock::Args),
 
     // Global level commands
     #[clap(visible_alias = "g")]
@@ -133,6 +139,7 @@ pub enum Command {
     Config(config::Args),
     Info(info::Args),
     Upload(upload::Args),
+    Channel(channel::Args),
     Search(search::Args),
     #[cfg_attr(not(feature = "self_update"), clap(hide = true))]
     SelfUpdate(self_update::Args),
@@ -141,6 +148,10 @@ pub enum Command {
 
     // Build
     Build(build::Args),
+
+    // Used internally to run tasks in a process of their own
+    #[clap(hide = true)]
+    TaskShell(task_shell::Args),
 }
 
 #[derive(Parser, Debug, Default, Copy, Clone)]
@@ -271,6 +282,7 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::Task(cmd) => task::execute(cmd),
         Command::Info(cmd) => info::execute(cmd).await,
         Command::Upload(cmd) => upload::execute(cmd).await,
+        Command::Channel(cmd) => channel::execute(cmd).await,
         Command::Search(cmd) => search::execute(cmd).await,
         Command::Project(cmd) => project::execute(cmd).await,
         Command::Remove(cmd) => remove::execute(cmd).await,
@@ -280,10 +292,13 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::SelfUpdate(cmd) => self_update::execute_stub(cmd).await,
         Command::List(cmd) => list::execute(cmd).await,
         Command::Tree(cmd) => tree::execute(cmd).await,
+        Command::Why(cmd) => why::execute(cmd).await,
+        Command::Lock(cmd) => lock::execute(cmd).await,
         Command::Update(cmd) => update::execute(cmd).await,
         Command::Upgrade(cmd) => upgrade::execute(cmd).await,
         Command::Exec(args) => exec::execute(args).await,
         Command::Build(args) => build::execute(args).await,
+        Command::TaskShell(args) => task_shell::execute(args).await,
     }
 }
 
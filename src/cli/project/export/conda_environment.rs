@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 
 use crate::cli::cli_config::ProjectConfig;
 use crate::{project::Environment, Project};
@@ -67,7 +67,7 @@ fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement)
             }
 
             if let Some(ref subdirectory) = git_url.subdirectory {
-                git_string.push_str(&format!("#subdirectory=={subdirectory}"));
+                git_string.push_str(&format!("#subdirectory={subdirectory}"));
             }
 
             git_string
@@ -77,19 +77,27 @@ fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement)
             editable,
             extras,
         } => {
-            if let Some(_editable) = editable {
-                format!(
-                    "-e {path}{extras}",
-                    path = path.to_string_lossy(),
-                    extras = format_pip_extras(extras),
-                )
-            } else {
-                format!(
-                    "{path}{extras}",
-                    path = path.to_string_lossy(),
-                    extras = format_pip_extras(extras),
-                )
+            let mut path_string = format!(
+                "{editable}{path}{extras}",
+                editable = if editable.unwrap_or_default() {
+                    "-e "
+                } else {
+                    ""
+                },
+                path = path.to_string_lossy(),
+                extras = format_pip_extras(extras),
+            );
+
+            // Pip derives the name from the metadata of the package, the name is
+            // only needed to import the file again.
+            let dir_name = path
+                .file_name()
+                .and_then(|name| PyPiPackageName::from_str(&name.to_string_lossy()).ok());
+            if dir_name.as_ref().map(PyPiPackageName::as_normalized) != Some(name.as_normalized()) {
+                path_string.push_str(&format!("#egg={}", name.as_normalized()));
             }
+
+            path_string
         }
         PyPiRequirement::Url {
             url,
@@ -104,7 +112,7 @@ fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement)
             );
 
             if let Some(ref subdirectory) = subdirectory {
-                url_string.push_str(&format!("#subdirectory=={subdirectory}"));
+                url_string.push_str(&format!("#subdirectory={subdirectory}"));
             }
 
             url_string
@@ -140,6 +148,7 @@ fn build_env_yaml(
     let mut env_yaml = rattler_conda_types::EnvironmentYaml {
         name: Some(environment.name().as_str().to_string()),
         channels,
+        variables: environment.activation_env(Some(*platform)),
         ..Default::default()
     };
 
@@ -174,27 +183,24 @@ fn build_env_yaml(
 
     if !pip_dependencies.is_empty() {
         let pypi_options = environment.pypi_options();
-        if let Some(ref find_links) = pypi_options.find_links {
-            for find_link in find_links {
-                match find_link {
-                    FindLinksUrlOrPath::Url(url) => {
-                        pip_dependencies.insert(0, format!("--find-links {url}"));
-                    }
-                    FindLinksUrlOrPath::Path(path) => {
-                        pip_dependencies
-                            .insert(0, format!("--find-links {}", path.to_string_lossy()));
-                    }
-                }
-            }
+        let mut pip_options = Vec::new();
+        if let Some(ref index_url) = pypi_options.index_url {
+            pip_options.push(format!("--index-url {index_url}"));
         }
-        if let Some(ref extra_index_urls) = pypi_options.extra_index_urls {
-            for extra_index_url in extra_index_urls {
-                pip_dependencies.insert(0, format!("--extra-index-url {extra_index_url}"));
-            }
+        for extra_index_url in pypi_options.extra_index_urls.iter().flatten() {
+            pip_options.push(format!("--extra-index-url {extra_index_url}"));
         }
-        if let Some(ref index_url) = pypi_options.index_url {
-            pip_dependencies.insert(0, format!("--index-url {index_url}"));
+        for find_link in pypi_options.find_links.iter().flatten() {
+            match find_link {
+                FindLinksUrlOrPath::Url(url) => {
+                    pip_options.push(format!("--find-links {url}"));
+                }
+                FindLinksUrlOrPath::Path(path) => {
+                    pip_options.push(format!("--find-links {}", path.to_string_lossy()));
+                }
+            }
         }
+        pip_dependencies.splice(0..0, pip_options);
 
         env_yaml.dependencies.push(MatchSpecOrSubSection::MatchSpec(
             MatchSpec::from_str("pip", ParseStrictness::Lenient).unwrap(),
@@ -248,6 +254,9 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 mod tests {
     use std::path::Path;
 
+    use pixi_utils::conda_environment_file::{CondaEnvFile, PipOptions, PipPathDependency};
+    use url::Url;
+
     use super::*;
 
     #[test]
@@ -443,6 +452,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_export_conda_env_yaml_round_trip() {
+        let toml = r#"
+            [project]
+            name = "test"
+            channels = ["conda-forge"]
+            platforms = ["linux-64"]
+
+            [activation.env]
+            MY_VAR = "some value"
+            DATA_DIR = "data"
+
+            [dependencies]
+            python = "3.12"
+
+            [pypi-dependencies]
+            my-package = { path = "./packages/my_package_src", editable = true }
+            requests = ">=2.31"
+
+            [pypi-options]
+            index-url = "https://pypi.example.com/simple"
+            extra-index-urls = ["https://download.pytorch.org/whl/cpu", "https://mirror.example.com/simple"]
+            find-links = [{ path = "./wheels" }]
+           "#;
+        let project = Project::from_str(Path::new("pixi.toml"), toml).unwrap();
+        let environment = project.default_environment();
+        let env_yaml = build_env_yaml(
+            &Platform::Linux64,
+            &environment,
+            project.config().global_channel_config(),
+        )
+        .unwrap();
+        insta::assert_snapshot!(
+            "test_export_conda_env_yaml_round_trip",
+            env_yaml.to_yaml_string()
+        );
+
+        // Importing the exported file should result in the same project.
+        let file = tempfile::NamedTempFile::new().unwrap();
+        env_yaml.to_path(file.path()).unwrap();
+        let env_file = CondaEnvFile::from_path(file.path()).unwrap();
+        assert_eq!(
+            env_file.variables(),
+            &environment.activation_env(Some(Platform::Linux64))
+        );
+        assert_eq!(
+            env_file.pip_options().unwrap(),
+            PipOptions {
+                index_url: Some(Url::parse("https://pypi.example.com/simple").unwrap()),
+                extra_index_urls: vec![
+                    Url::parse("https://download.pytorch.org/whl/cpu").unwrap(),
+                    Url::parse("https://mirror.example.com/simple").unwrap(),
+                ],
+                find_links: vec!["./wheels".to_string()],
+                path_dependencies: vec![PipPathDependency {
+                    name: Some("my-package".to_string()),
+                    path: PathBuf::from("./packages/my_package_src"),
+                    editable: true,
+                }],
+            }
+        );
+        let (_, pypi_deps, _) = env_file.to_manifest(project.config()).unwrap();
+        assert_eq!(
+            pypi_deps,
+            vec![pep508_rs::Requirement::from_str("requests>=2.31").unwrap()]
+        );
+    }
+
     #[test]
     fn test_channels_with_nodefaults() {
         let channels = vec![NamedChannelOrUrl::Name("main".to_string())];
@@ -1,5 +1,7 @@
 pub mod conda_environment;
 pub mod conda_explicit_spec;
+pub mod oci;
+pub mod requirements;
 
 use clap::Parser;
 
@@ -17,12 +19,19 @@ pub enum Command {
     CondaExplicitSpec(conda_explicit_spec::Args),
     /// Export project environment to a conda environment.yaml file
     CondaEnvironment(conda_environment::Args),
+    /// Export the PyPI packages of project environments to pinned
+    /// requirements.txt files
+    Requirements(requirements::Args),
+    /// Export a project environment as an OCI image layout directory
+    Oci(oci::Args),
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
     match args.command {
         Command::CondaExplicitSpec(args) => conda_explicit_spec::execute(args).await?,
         Command::CondaEnvironment(args) => conda_environment::execute(args).await?,
+        Command::Requirements(args) => requirements::execute(args).await?,
+        Command::Oci(args) => oci::execute(args).await?,
     };
     Ok(())
 }
@@ -0,0 +1,496 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use flate2::{write::GzEncoder, Compression};
+use indexmap::IndexMap;
+use itertools::{Either, Itertools};
+use miette::{Context, IntoDiagnostic};
+use pixi_consts::consts;
+use pixi_progress::global_multi_progress;
+use pixi_record::PixiRecord;
+use rattler::{
+    install::{IndicatifReporter, Installer},
+    package_cache::PackageCache,
+};
+use rattler_conda_types::Platform;
+use rattler_digest::{compute_bytes_digest, HashingWriter, Sha256};
+use serde_json::json;
+
+use crate::{
+    activation::get_static_activation_environment_variables,
+    cli::cli_config::{PrefixUpdateConfig, ProjectConfig},
+    environment::{update_prefix_pypi, PythonStatus},
+    lock_file::{UpdateLockFileOptions, UvResolutionContext},
+    prefix::Prefix,
+    Project,
+};
+
+const OCI_LAYOUT_VERSION: &str = "1.0.0";
+const MEDIA_TYPE_INDEX: &str = "application/vnd.oci.image.index.v1+json";
+const MEDIA_TYPE_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+const MEDIA_TYPE_CONFIG: &str = "application/vnd.oci.image.config.v1+json";
+const MEDIA_TYPE_LAYER: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+
+/// Export an environment as an OCI image layout directory.
+///
+/// The locked packages of the environment are installed into a single image
+/// layer. The resulting directory can be loaded with e.g. `podman load -i
+/// <output-dir>`. Packages are taken from the package cache when available,
+/// combine with `--frozen` to export without network access.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// Output directory for the OCI image layout
+    pub output_dir: PathBuf,
+
+    /// The environment to export
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    /// The platform of the image, only linux platforms are supported
+    #[arg(short, long, default_value_t = Platform::current())]
+    pub platform: Platform,
+
+    /// The path of the environment inside the image. Defaults to
+    /// `/opt/pixi/envs/<environment>`
+    #[arg(long)]
+    pub target_prefix: Option<PathBuf>,
+
+    /// The name of the image. Defaults to `<project>:<environment>`
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// A content descriptor of a blob in the image layout.
+struct Descriptor {
+    digest: String,
+    size: u64,
+}
+
+impl Descriptor {
+    fn to_json(&self, media_type: &str) -> serde_json::Value {
+        json!({
+            "mediaType": media_type,
+            "digest": self.digest,
+            "size": self.size,
+        })
+    }
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
+        .with_cli_config(args.prefix_update_config.config.clone());
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = args.platform;
+
+    let (architecture, variant) = oci_platform(platform).ok_or_else(|| {
+        miette::miette!(
+            "cannot export an image for '{platform}', only linux platforms are supported"
+        )
+    })?;
+    if !environment.platforms().contains(&platform) {
+        miette::bail!(
+            "the environment '{}' does not support the platform '{platform}'",
+            environment.name().fancy_display()
+        );
+    }
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            max_concurrent_solves: project.config().max_concurrent_solves(),
+        })
+        .await?
+        .lock_file;
+    let locked_environment = lock_file
+        .environment(environment.name().as_str())
+        .ok_or_else(|| miette::miette!("unknown environment '{}'", environment.name()))?;
+
+    let pixi_records = locked_environment
+        .conda_packages(platform)
+        .map(|iter| {
+            iter.cloned()
+                .map(PixiRecord::try_from)
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()
+        .into_diagnostic()?
+        .unwrap_or_default();
+    let pypi_records = locked_environment
+        .pypi_packages(platform)
+        .map(|iter| {
+            iter.map(|(data, env_data)| (data.clone(), env_data.clone()))
+                .collect_vec()
+        })
+        .unwrap_or_default();
+
+    let (repodata_records, source_records): (Vec<_>, Vec<_>) =
+        pixi_records.iter().partition_map(|record| match record {
+            PixiRecord::Binary(record) => Either::Left(record.clone()),
+            PixiRecord::Source(record) => Either::Right(record),
+        });
+    if let Some(record) = source_records.first() {
+        miette::bail!(
+            "conda source packages cannot be exported to an image, '{}' is a source package",
+            record.package_record.name.as_source()
+        );
+    }
+    if !pypi_records.is_empty() && platform != Platform::current() {
+        miette::bail!(
+            "the environment '{}' contains PyPI packages, these can only be exported for the current platform '{}'",
+            environment.name().fancy_display(),
+            Platform::current()
+        );
+    }
+
+    let target_prefix = args
+        .target_prefix
+        .unwrap_or_else(|| Path::new("/opt/pixi/envs").join(environment.name().as_str()));
+    if !target_prefix.is_absolute() || target_prefix.to_string_lossy().contains('\\') {
+        miette::bail!(
+            "the target prefix '{}' must be an absolute unix path",
+            target_prefix.display()
+        );
+    }
+
+    // Install the environment in a staging directory. The packages are linked as
+    // if they were installed in the target prefix.
+    let staging_dir = tempfile::tempdir().into_diagnostic()?;
+    let prefix = Prefix::new(staging_dir.path());
+    let cache_dir = pixi_config::get_cache_dir().context("failed to determine cache directory")?;
+    let result = Installer::new()
+        .with_download_client(project.authenticated_client().clone())
+        .with_execute_link_scripts(false)
+        .with_target_platform(platform)
+        .with_alternative_target_prefix(target_prefix.clone())
+        .with_package_cache(PackageCache::new(
+            cache_dir.join(consts::CONDA_PACKAGE_CACHE_DIR),
+        ))
+        .with_reporter(
+            IndicatifReporter::builder()
+                .with_multi_progress(global_multi_progress())
+                .clear_when_done(true)
+                .finish(),
+        )
+        .install(prefix.root(), repodata_records)
+        .await
+        .into_diagnostic()
+        .context("failed to install the conda packages of the image")?;
+
+    if !pypi_records.is_empty() {
+        let uv_context = UvResolutionContext::from_project(&project)?;
+        update_prefix_pypi(
+            environment.name(),
+            &prefix,
+            platform,
+            &pixi_records,
+            &pypi_records,
+            &PythonStatus::from_transaction(&result.transaction),
+            &environment.system_requirements(),
+            &uv_context,
+            locked_environment.pypi_indexes(),
+            &HashMap::new(),
+            project.root(),
+            platform,
+            environment.pypi_options().no_build_isolation,
+        )
+        .await
+        .context("failed to install the PyPI packages of the image")?;
+
+        // The scripts of the PyPI packages refer to the interpreter in the staging
+        // directory.
+        relocate_scripts(prefix.root(), &target_prefix)?;
+    }
+
+    let env = get_static_activation_environment_variables(
+        &environment,
+        prefix.root(),
+        &target_prefix,
+        platform,
+    )?;
+    let tag = args
+        .tag
+        .unwrap_or_else(|| format!("{}:{}", project.name(), environment.name()).to_lowercase());
+
+    write_image_layout(
+        &args.output_dir,
+        prefix.root(),
+        &target_prefix,
+        architecture,
+        variant,
+        &env,
+        &tag,
+    )?;
+
+    eprintln!(
+        "{}Exported the environment '{}' for {} to '{}' as {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        environment.name().fancy_display(),
+        consts::PLATFORM_STYLE.apply_to(platform),
+        args.output_dir.display(),
+        console::style(&tag).bold(),
+    );
+
+    Ok(())
+}
+
+/// Returns the OCI architecture and variant of a linux platform.
+fn oci_platform(platform: Platform) -> Option<(&'static str, Option<&'static str>)> {
+    match platform {
+        Platform::Linux32 => Some(("386", None)),
+        Platform::Linux64 => Some(("amd64", None)),
+        Platform::LinuxAarch64 => Some(("arm64", Some("v8"))),
+        Platform::LinuxArmV6l => Some(("arm", Some("v6"))),
+        Platform::LinuxArmV7l => Some(("arm", Some("v7"))),
+        Platform::LinuxPpc64le => Some(("ppc64le", None)),
+        Platform::LinuxPpc64 => Some(("ppc64", None)),
+        Platform::LinuxS390X => Some(("s390x", None)),
+        Platform::LinuxRiscv64 => Some(("riscv64", None)),
+        _ => None,
+    }
+}
+
+/// Replaces the path of the staging directory in the shebangs of the scripts
+/// in the `bin` directory with the target prefix.
+fn relocate_scripts(prefix: &Path, target_prefix: &Path) -> miette::Result<()> {
+    let bin_dir = prefix.join("bin");
+    if !bin_dir.is_dir() {
+        return Ok(());
+    }
+
+    let staging_path = prefix.to_string_lossy();
+    let target_path = target_prefix.to_string_lossy();
+    for entry in fs_err::read_dir(&bin_dir).into_diagnostic()? {
+        let path = entry.into_diagnostic()?.path();
+        if path.is_symlink() || !path.is_file() {
+            continue;
+        }
+        let Ok(contents) = fs_err::read_to_string(&path) else {
+            continue;
+        };
+        if contents.starts_with("#!") && contents.contains(staging_path.as_ref()) {
+            fs_err::write(
+                &path,
+                contents.replace(staging_path.as_ref(), target_path.as_ref()),
+            )
+            .into_diagnostic()?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes an OCI image layout with a single layer that contains the `prefix`
+/// at `target_prefix` to `output_dir`.
+fn write_image_layout(
+    output_dir: &Path,
+    prefix: &Path,
+    target_prefix: &Path,
+    architecture: &str,
+    variant: Option<&str>,
+    env: &IndexMap<String, String>,
+    tag: &str,
+) -> miette::Result<()> {
+    let blobs_dir = output_dir.join("blobs").join("sha256");
+    fs_err::create_dir_all(&blobs_dir).into_diagnostic()?;
+
+    let (layer, diff_id) = write_layer(&blobs_dir, prefix, target_prefix)?;
+
+    let mut config = json!({
+        "architecture": architecture,
+        "os": "linux",
+        "config": {
+            "Env": env.iter().map(|(key, value)| format!("{key}={value}")).collect_vec(),
+            "WorkingDir": "/",
+        },
+        "rootfs": {
+            "type": "layers",
+            "diff_ids": [diff_id],
+        },
+        "history": [{
+            "created_by": format!("pixi {} project export oci", consts::PIXI_VERSION),
+        }],
+    });
+    if let Some(variant) = variant {
+        config["variant"] = json!(variant);
+    }
+    let config = write_json_blob(&blobs_dir, &config)?;
+
+    let manifest = write_json_blob(
+        &blobs_dir,
+        &json!({
+            "schemaVersion": 2,
+            "mediaType": MEDIA_TYPE_MANIFEST,
+            "config": config.to_json(MEDIA_TYPE_CONFIG),
+            "layers": [layer.to_json(MEDIA_TYPE_LAYER)],
+        }),
+    )?;
+
+    let mut manifest_json = manifest.to_json(MEDIA_TYPE_MANIFEST);
+    manifest_json["annotations"] = json!({ "org.opencontainers.image.ref.name": tag });
+    let index = json!({
+        "schemaVersion": 2,
+        "mediaType": MEDIA_TYPE_INDEX,
+        "manifests": [manifest_json],
+    });
+    fs_err::write(
+        output_dir.join("index.json"),
+        serde_json::to_vec_pretty(&index).into_diagnostic()?,
+    )
+    .into_diagnostic()?;
+    fs_err::write(
+        output_dir.join("oci-layout"),
+        json!({ "imageLayoutVersion": OCI_LAYOUT_VERSION }).to_string(),
+    )
+    .into_diagnostic()?;
+
+    Ok(())
+}
+
+/// Writes the `prefix` as a gzipped tarball to the blobs directory. Returns
+/// the descriptor of the compressed layer and the digest of the uncompressed
+/// tarball.
+fn write_layer(
+    blobs_dir: &Path,
+    prefix: &Path,
+    target_prefix: &Path,
+) -> miette::Result<(Descriptor, String)> {
+    let temp_path = blobs_dir.join("layer.tar.gz.part");
+    let file = fs_err::File::create(&temp_path).into_diagnostic()?;
+    let compressed = HashingWriter::<_, Sha256>::new(file);
+    let uncompressed =
+        HashingWriter::<_, Sha256>::new(GzEncoder::new(compressed, Compression::default()));
+
+    let mut builder = tar::Builder::new(uncompressed);
+    builder.follow_symlinks(false);
+    builder.mode(tar::HeaderMode::Deterministic);
+
+    // Add the parent directories of the prefix before the prefix itself.
+    let layer_path = target_prefix.strip_prefix("/").unwrap_or(target_prefix);
+    for ancestor in layer_path
+        .ancestors()
+        .skip(1)
+        .collect_vec()
+        .into_iter()
+        .rev()
+    {
+        if !ancestor.as_os_str().is_empty() {
+            builder.append_dir(ancestor, prefix).into_diagnostic()?;
+        }
+    }
+    builder
+        .append_dir_all(layer_path, prefix)
+        .into_diagnostic()
+        .context("failed to write the image layer")?;
+
+    let (encoder, diff_id) = builder.into_inner().into_diagnostic()?.finalize();
+    let (file, digest) = encoder.finish().into_diagnostic()?.finalize();
+    let size = file.metadata().into_diagnostic()?.len();
+    drop(file);
+
+    let digest = format!("{digest:x}");
+    fs_err::rename(&temp_path, blobs_dir.join(&digest)).into_diagnostic()?;
+    Ok((
+        Descriptor {
+            digest: format!("sha256:{digest}"),
+            size,
+        },
+        format!("sha256:{diff_id:x}"),
+    ))
+}
+
+/// Writes a JSON document to the blobs directory.
+fn write_json_blob(blobs_dir: &Path, value: &serde_json::Value) -> miette::Result<Descriptor> {
+    let bytes = serde_json::to_vec(value).into_diagnostic()?;
+    let digest = format!("{:x}", compute_bytes_digest::<Sha256>(&bytes));
+    fs_err::write(blobs_dir.join(&digest), &bytes).into_diagnostic()?;
+    Ok(Descriptor {
+        digest: format!("sha256:{digest}"),
+        size: bytes.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rattler_digest::compute_file_digest;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_write_image_layout() {
+        let prefix = tempdir().unwrap();
+        fs_err::create_dir_all(prefix.path().join("bin")).unwrap();
+        fs_err::write(prefix.path().join("bin/hello"), "#!/bin/sh\necho hello\n").unwrap();
+
+        let output_dir = tempdir().unwrap();
+        let env = IndexMap::from_iter([(
+            String::from("PATH"),
+            String::from("/opt/pixi/envs/default/bin:/usr/bin"),
+        )]);
+        write_image_layout(
+            output_dir.path(),
+            prefix.path(),
+            Path::new("/opt/pixi/envs/default"),
+            "amd64",
+            None,
+            &env,
+            "test:default",
+        )
+        .unwrap();
+
+        let index: serde_json::Value =
+            serde_json::from_slice(&fs_err::read(output_dir.path().join("index.json")).unwrap())
+                .unwrap();
+        let manifest_digest = index["manifests"][0]["digest"].as_str().unwrap();
+        assert_eq!(
+            index["manifests"][0]["annotations"]["org.opencontainers.image.ref.name"],
+            "test:default"
+        );
+
+        // Every blob must be stored under its digest.
+        let blobs_dir = output_dir.path().join("blobs/sha256");
+        let manifest_path = blobs_dir.join(manifest_digest.strip_prefix("sha256:").unwrap());
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs_err::read(&manifest_path).unwrap()).unwrap();
+        for descriptor in [&manifest["config"], &manifest["layers"][0]] {
+            let digest = descriptor["digest"].as_str().unwrap();
+            let digest = digest.strip_prefix("sha256:").unwrap();
+            let blob = blobs_dir.join(digest);
+            assert_eq!(
+                format!("{:x}", compute_file_digest::<Sha256>(&blob).unwrap()),
+                digest
+            );
+            assert_eq!(
+                fs_err::metadata(&blob).unwrap().len(),
+                descriptor["size"].as_u64().unwrap()
+            );
+        }
+
+        // The layer contains the prefix at the target location.
+        let layer_digest = manifest["layers"][0]["digest"].as_str().unwrap();
+        let layer =
+            fs_err::File::open(blobs_dir.join(layer_digest.strip_prefix("sha256:").unwrap()))
+                .unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(layer));
+        let paths = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path().unwrap().into_owned();
+                path.to_string_lossy().trim_end_matches('/').to_string()
+            })
+            .collect_vec();
+        assert!(paths.contains(&String::from("opt")));
+        assert!(paths.contains(&String::from("opt/pixi/envs/default/bin/hello")));
+    }
+}
@@ -0,0 +1,519 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_conda_types::Platform;
+use rattler_lock::{
+    Environment, FindLinksUrlOrPath, LockedPackageRef, PypiIndexes, PypiPackageData, UrlOrPath,
+};
+use toml_edit::{value, ArrayOfTables, DocumentMut, InlineTable, Item, Table};
+use url::Url;
+
+use crate::{
+    cli::cli_config::{PrefixUpdateConfig, ProjectConfig},
+    install_pypi::utils::{is_direct_url, strip_direct_scheme},
+    lock_file::UpdateLockFileOptions,
+    Project,
+};
+
+#[derive(Debug, Parser)]
+#[clap(arg_required_else_help = false)]
+pub struct Args {
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// Output directory for the rendered requirements files
+    pub output_dir: PathBuf,
+
+    /// Environment to render. Can be repeated for multiple envs. Defaults to
+    /// all environments.
+    #[arg(short, long)]
+    pub environment: Option<Vec<String>>,
+
+    /// The platform to render. Can be repeated for multiple platforms.
+    /// Defaults to all platforms available for selected environments.
+    #[arg(short, long)]
+    pub platform: Option<Vec<Platform>>,
+
+    /// Also write a `pylock.<environment>.toml` lock file for every
+    /// environment that contains the PyPI packages of all selected platforms.
+    #[arg(long)]
+    pub pylock: bool,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// Returns the PyPI packages of the environment for the given platform,
+/// sorted by name. Conda packages cannot be installed by pip and are skipped.
+fn pypi_packages<'l>(
+    environment: &Environment<'l>,
+    platform: Platform,
+) -> Vec<&'l PypiPackageData> {
+    environment
+        .packages(platform)
+        .into_iter()
+        .flatten()
+        .filter_map(|package| match package {
+            LockedPackageRef::Pypi(data, _) => Some(data),
+            LockedPackageRef::Conda(_) => None,
+        })
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect()
+}
+
+/// Returns the absolute path of a path in the lock file.
+fn absolute_path(lock_file_dir: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        lock_file_dir.join(path)
+    }
+}
+
+/// Renders the options that configure the indexes of pip.
+fn render_index_options(indexes: Option<&PypiIndexes>) -> Vec<String> {
+    let Some(indexes) = indexes else {
+        return Vec::new();
+    };
+    let mut options = Vec::new();
+    for (idx, index) in indexes.indexes.iter().enumerate() {
+        if idx == 0 {
+            options.push(format!("--index-url {index}"));
+        } else {
+            options.push(format!("--extra-index-url {index}"));
+        }
+    }
+    for find_links in &indexes.find_links {
+        match find_links {
+            FindLinksUrlOrPath::Url(url) => options.push(format!("--find-links {url}")),
+            FindLinksUrlOrPath::Path(path) => {
+                options.push(format!("--find-links {}", path.display()))
+            }
+        }
+    }
+    options
+}
+
+/// Returns the sha256 hash of a package that pip can verify, editable packages
+/// are built from their source so they can't be verified.
+fn verifiable_hash(package: &PypiPackageData) -> Option<String> {
+    if package.editable {
+        return None;
+    }
+    let sha256 = package.hash.as_ref()?.sha256()?;
+    Some(format!("{sha256:x}"))
+}
+
+/// Renders the requirement of a single package, pinned to the exact version
+/// or location that is locked. The hash of the package is added if
+/// `with_hash` is true.
+fn render_requirement(
+    package: &PypiPackageData,
+    lock_file_dir: &Path,
+    with_hash: bool,
+) -> miette::Result<String> {
+    let requirement = match &package.location {
+        UrlOrPath::Url(url) if is_direct_url(url.scheme()) => {
+            format!("{} @ {}", package.name, strip_direct_scheme(url))
+        }
+        UrlOrPath::Url(_) => format!("{}=={}", package.name, package.version),
+        UrlOrPath::Path(path) => {
+            let path = absolute_path(lock_file_dir, path.as_str());
+            if package.editable {
+                return Ok(format!("-e {}", path.display()));
+            }
+            let url = Url::from_file_path(&path)
+                .map_err(|_| miette::miette!("invalid path '{}'", path.display()))?;
+            format!("{} @ {url}", package.name)
+        }
+    };
+
+    match verifiable_hash(package).filter(|_| with_hash) {
+        Some(sha256) => Ok(format!("{requirement} \\\n    --hash=sha256:{sha256}")),
+        None => Ok(requirement),
+    }
+}
+
+/// Renders a `requirements.txt` file that pins all the PyPI packages of the
+/// environment for the given platform.
+fn render_requirements(
+    environment: &Environment,
+    platform: Platform,
+    lock_file_dir: &Path,
+) -> miette::Result<String> {
+    let mut requirements = String::new();
+    requirements.push_str("# Generated by `pixi project export`\n");
+    for option in render_index_options(environment.pypi_indexes()) {
+        requirements.push_str(&option);
+        requirements.push('\n');
+    }
+    requirements.push_str(&render_package_requirements(
+        &pypi_packages(environment, platform),
+        lock_file_dir,
+    )?);
+    Ok(requirements)
+}
+
+/// Renders the requirements of the packages, one per line.
+///
+/// Once a single requirement has a hash, pip requires a hash for every
+/// requirement. So the hashes are only added if all the packages have a hash
+/// that pip can verify, which is not the case for editable packages or
+/// packages from git for instance.
+fn render_package_requirements(
+    packages: &[&PypiPackageData],
+    lock_file_dir: &Path,
+) -> miette::Result<String> {
+    let with_hashes = packages
+        .iter()
+        .all(|package| verifiable_hash(package).is_some());
+    let mut requirements = String::new();
+    for package in packages {
+        requirements.push_str(&render_requirement(package, lock_file_dir, with_hashes)?);
+        requirements.push('\n');
+    }
+    Ok(requirements)
+}
+
+/// Returns the environment marker that matches the given platform.
+fn platform_marker(platform: Platform) -> Option<String> {
+    let sys_platform = if platform.is_linux() {
+        "linux"
+    } else if platform.is_osx() {
+        "darwin"
+    } else if platform.is_windows() {
+        "win32"
+    } else {
+        return None;
+    };
+
+    let platform_machine = match platform {
+        Platform::Linux32 => "i386",
+        Platform::Linux64 => "x86_64",
+        Platform::LinuxAarch64 => "aarch64",
+        Platform::LinuxArmV6l => "armv6l",
+        Platform::LinuxArmV7l => "armv7l",
+        Platform::LinuxPpc64le => "ppc64le",
+        Platform::LinuxPpc64 => "ppc64",
+        Platform::LinuxS390X => "s390x",
+        Platform::LinuxRiscv32 => "riscv32",
+        Platform::LinuxRiscv64 => "riscv64",
+        Platform::Osx64 => "x86_64",
+        Platform::OsxArm64 => "arm64",
+        Platform::Win32 => "x86",
+        Platform::Win64 => "AMD64",
+        Platform::WinArm64 => "ARM64",
+        _ => return Some(format!("sys_platform == '{sys_platform}'")),
+    };
+
+    Some(format!(
+        "sys_platform == '{sys_platform}' and platform_machine == '{platform_machine}'"
+    ))
+}
+
+/// Returns a table with the hashes of a package, if it has any.
+fn hashes_table(package: &PypiPackageData) -> Option<InlineTable> {
+    let hash = package.hash.as_ref()?;
+    let mut hashes = InlineTable::new();
+    if let Some(sha256) = hash.sha256() {
+        hashes.insert("sha256", format!("{sha256:x}").into());
+    }
+    if let Some(md5) = hash.md5() {
+        hashes.insert("md5", format!("{md5:x}").into());
+    }
+    Some(hashes)
+}
+
+/// Adds the source of the package, i.e. where it can be installed from, to
+/// the table of the package in the `pylock.toml` format.
+fn add_package_source(
+    table: &mut Table,
+    package: &PypiPackageData,
+    indexes: Option<&PypiIndexes>,
+    lock_file_dir: &Path,
+) {
+    match &package.location {
+        UrlOrPath::Url(url) if url.scheme().starts_with("git+") => {
+            let mut repository = strip_direct_scheme(url).into_owned();
+            let commit = repository.fragment().map(ToOwned::to_owned);
+            repository.set_fragment(None);
+            repository.set_query(None);
+            let repository = repository.as_str().trim_start_matches("git+").to_string();
+
+            let mut vcs = Table::new();
+            vcs.insert("type", value("git"));
+            vcs.insert("url", value(repository));
+            if let Some(commit) = commit {
+                vcs.insert("commit-id", value(commit));
+            }
+            table.insert("vcs", Item::Table(vcs));
+        }
+        UrlOrPath::Url(url) => {
+            let url = strip_direct_scheme(url);
+            let file_name = url
+                .path_segments()
+                .and_then(|segments| segments.last())
+                .map(|name| percent_encoding::percent_decode_str(name).decode_utf8_lossy());
+
+            let mut artifact = Table::new();
+            if !is_direct_url(url.scheme()) {
+                if let Some(name) = &file_name {
+                    artifact.insert("name", value(name.as_ref()));
+                }
+            }
+            artifact.insert("url", value(url.as_str()));
+            if let Some(hashes) = hashes_table(package) {
+                artifact.insert("hashes", value(hashes));
+            }
+
+            let is_wheel = file_name
+                .as_ref()
+                .is_some_and(|name| name.ends_with(".whl"));
+            if is_direct_url(url.scheme()) {
+                table.insert("archive", Item::Table(artifact));
+            } else if is_wheel {
+                // Only the index of packages from a registry is known, and only if
+                // there is a single one.
+                if let Some([index]) = indexes.map(|indexes| indexes.indexes.as_slice()) {
+                    table.insert("index", value(index.as_str()));
+                }
+                let mut wheels = ArrayOfTables::new();
+                wheels.push(artifact);
+                table.insert("wheels", Item::ArrayOfTables(wheels));
+            } else {
+                if let Some([index]) = indexes.map(|indexes| indexes.indexes.as_slice()) {
+                    table.insert("index", value(index.as_str()));
+                }
+                table.insert("sdist", Item::Table(artifact));
+            }
+        }
+        UrlOrPath::Path(path) => {
+            let absolute = absolute_path(lock_file_dir, path.as_str());
+            let mut source = Table::new();
+            source.insert("path", value(absolute.display().to_string()));
+            if let Some(hashes) = hashes_table(package) {
+                source.insert("hashes", value(hashes));
+            }
+            if absolute.is_dir() {
+                source.insert("editable", value(package.editable));
+                table.insert("directory", Item::Table(source));
+            } else {
+                table.insert("archive", Item::Table(source));
+            }
+        }
+    }
+}
+
+/// Renders a `pylock.toml` file that contains the PyPI packages of the
+/// environment for all the given platforms. Packages that are not locked for
+/// every platform get an environment marker that selects the platforms they
+/// are locked for.
+fn render_pylock(
+    environment: &Environment,
+    platforms: &[Platform],
+    lock_file_dir: &Path,
+) -> String {
+    // Group the packages by the platforms they are locked for.
+    let mut packages: BTreeMap<_, (&PypiPackageData, Vec<Platform>)> = BTreeMap::new();
+    for &platform in platforms {
+        for package in pypi_packages(environment, platform) {
+            packages
+                .entry((
+                    package.name.clone(),
+                    package.version.clone(),
+                    package.location.to_string(),
+                ))
+                .or_insert_with(|| (package, Vec::new()))
+                .1
+                .push(platform);
+        }
+    }
+
+    let mut document = DocumentMut::new();
+    document.insert("lock-version", value("1.0"));
+    document.insert("created-by", value("pixi"));
+
+    let indexes = environment.pypi_indexes();
+    let mut package_tables = ArrayOfTables::new();
+    for (package, package_platforms) in packages.values() {
+        let mut table = Table::new();
+        table.insert("name", value(package.name.to_string()));
+        table.insert("version", value(package.version.to_string()));
+        if package_platforms.len() < platforms.len() {
+            let markers = package_platforms
+                .iter()
+                .filter_map(|platform| platform_marker(*platform))
+                .map(|marker| format!("({marker})"))
+                .join(" or ");
+            if !markers.is_empty() {
+                table.insert("marker", value(markers));
+            }
+        }
+        if let Some(requires_python) = &package.requires_python {
+            table.insert("requires-python", value(requires_python.to_string()));
+        }
+        add_package_source(&mut table, package, indexes, lock_file_dir);
+        package_tables.push(table);
+    }
+    document.insert("packages", Item::ArrayOfTables(package_tables));
+
+    format!("# Generated by `pixi project export`\n{document}")
+}
+
+fn write_file(target: &Path, contents: &str) -> miette::Result<()> {
+    fs_err::write(target, contents)
+        .into_diagnostic()
+        .with_context(|| format!("failed to write requirements file: {}", target.display()))
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
+        .with_cli_config(args.prefix_update_config.config.clone());
+
+    let lockfile = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            max_concurrent_solves: project.config().max_concurrent_solves(),
+        })
+        .await?
+        .lock_file;
+
+    let mut environments = Vec::new();
+    if let Some(env_names) = args.environment {
+        for env_name in &env_names {
+            environments.push((
+                env_name.to_string(),
+                lockfile
+                    .environment(env_name)
+                    .ok_or(miette::miette!("unknown environment {}", env_name))?,
+            ));
+        }
+    } else {
+        for (env_name, env) in lockfile.environments() {
+            environments.push((env_name.to_string(), env));
+        }
+    };
+
+    fs_err::create_dir_all(&args.output_dir).ok();
+
+    let lock_file_dir = project.root();
+    for (env_name, env) in environments {
+        let available_platforms: HashSet<Platform> = HashSet::from_iter(env.platforms());
+        let platforms = match &args.platform {
+            Some(platforms) => platforms
+                .iter()
+                .filter(|platform| {
+                    let available = available_platforms.contains(platform);
+                    if !available {
+                        tracing::warn!(
+                            "Platform {} not available for environment {}. Skipping...",
+                            platform,
+                            env_name,
+                        );
+                    }
+                    available
+                })
+                .copied()
+                .collect_vec(),
+            None => available_platforms
+                .into_iter()
+                .sorted_by_key(|platform| platform.as_str())
+                .collect_vec(),
+        };
+
+        for &platform in &platforms {
+            tracing::info!("Creating requirements file for env: {env_name} platform: {platform}");
+            let requirements = render_requirements(&env, platform, lock_file_dir)?;
+            let target = args
+                .output_dir
+                .join(format!("{}_{}_requirements.txt", env_name, platform));
+            write_file(&target, &requirements)?;
+        }
+
+        if args.pylock {
+            tracing::info!("Creating pylock file for env: {env_name}");
+            let pylock = render_pylock(&env, &platforms, lock_file_dir);
+            let target = args.output_dir.join(format!("pylock.{}.toml", env_name));
+            write_file(&target, &pylock)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rattler_lock::LockFile;
+
+    use super::*;
+
+    #[test]
+    fn test_render_requirements() {
+        let project_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/mock-projects/test-project-export");
+        let lockfile = LockFile::from_path(&project_dir.join("pixi.lock")).unwrap();
+        let environment = lockfile.environment("default").unwrap();
+
+        let requirements =
+            render_requirements(&environment, Platform::Linux64, &project_dir).unwrap();
+        assert!(requirements.starts_with(
+            "# Generated by `pixi project export`\n--index-url https://pypi.org/simple\n"
+        ));
+        assert!(requirements.contains(
+            "rich==13.8.0 \\\n    --hash=sha256:2e85306a063b9492dffc86278197a60cbece75bcb766022f3436f567cae11bdc\n"
+        ));
+        // Conda packages are not part of the requirements.
+        assert!(!requirements.contains("python=="));
+
+        // Without a hash for every package, pip would reject the file, so none of the
+        // packages have a hash.
+        let mut packages = pypi_packages(&environment, Platform::Linux64);
+        let mut git_package = packages[0].clone();
+        git_package.location = UrlOrPath::Url(
+            Url::parse("git+https://github.com/prefix-dev/pixi.git#0123456789abcdef").unwrap(),
+        );
+        git_package.hash = None;
+        packages.push(&git_package);
+        let requirements = render_package_requirements(&packages, &project_dir).unwrap();
+        assert!(!requirements.contains("--hash"));
+        assert!(requirements.contains("rich==13.8.0\n"));
+
+        let pylock = render_pylock(
+            &environment,
+            &[Platform::Linux64, Platform::Osx64, Platform::OsxArm64],
+            &project_dir,
+        );
+        let document: DocumentMut = pylock.parse().unwrap();
+        let packages = document["packages"].as_array_of_tables().unwrap();
+        let rich = packages
+            .iter()
+            .filter(|package| package["name"].as_str() == Some("rich"))
+            .collect_vec();
+        assert_eq!(rich.len(), 2);
+        assert_eq!(
+            rich[0]["marker"].as_str(),
+            Some("(sys_platform == 'linux' and platform_machine == 'x86_64') or (sys_platform == 'darwin' and platform_machine == 'x86_64')")
+        );
+        assert_eq!(
+            rich[1]["marker"].as_str(),
+            Some("(sys_platform == 'darwin' and platform_machine == 'arm64')")
+        );
+        assert_eq!(rich[1]["index"].as_str(), Some("https://pypi.org/simple"));
+
+        // Packages that are the same on all platforms do not have a marker.
+        let mdurl = packages
+            .iter()
+            .find(|package| package["name"].as_str() == Some("mdurl"))
+            .unwrap();
+        assert!(mdurl.get("marker").is_none());
+    }
+}
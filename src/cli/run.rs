@@ -1,25 +1,36 @@
 use clap::Parser;
+use deno_task_shell::{parser::SequentialList, ShellPipeReader, ShellPipeWriter, ShellState};
 use dialoguer::theme::ColorfulTheme;
 use fancy_display::FancyDisplay;
+use futures::{stream::FuturesUnordered, StreamExt};
 use itertools::Itertools;
 use miette::{Diagnostic, IntoDiagnostic};
 use std::collections::hash_map::Entry;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::convert::identity;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
 use std::{collections::HashMap, string::String};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use crate::cli::cli_config::{PrefixUpdateConfig, ProjectConfig};
 use crate::environment::verify_prefix_location_unchanged;
-use crate::lock_file::UpdateLockFileOptions;
+use crate::lock_file::{LockFileDerivedData, UpdateLockFileOptions};
 use crate::project::errors::UnsupportedPlatformError;
 use crate::project::virtual_packages::verify_current_platform_has_required_virtual_packages;
 use crate::project::Environment;
 use crate::task::{
-    get_task_env, AmbiguousTask, CanSkip, ExecutableTask, FailedToParseShellScript,
-    InvalidWorkingDirectory, SearchEnvironments, TaskAndEnvironment, TaskGraph,
+    get_task_env, run_sandboxed, AmbiguousTask, CacheUpdateError, CanSkip, ExecutableTask,
+    FailedToParseShellScript, FailedToRenderCommand, InvalidWorkingDirectory, SandboxError,
+    SandboxPolicy, SearchEnvironments, TaskAndEnvironment, TaskGraph, TaskId,
 };
 use crate::Project;
 use pixi_config::ConfigCliActivation;
+use pixi_glob::GlobWatcher;
+use pixi_manifest::task::TaskLimits;
 use pixi_manifest::TaskName;
 use thiserror::Error;
 use tracing::Level;
@@ -51,6 +62,32 @@ pub struct Args {
     /// minimum environment to activate the pixi environment in.
     #[arg(long)]
     pub clean_env: bool,
+
+    /// The number of tasks to run at the same time.
+    ///
+    /// Tasks are started as soon as all the tasks they depend on have
+    /// finished. When running more than one task at a time, every line of
+    /// output is prefixed with the name of the task.
+    #[arg(long, short = 'j')]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Keep running the tasks that do not depend on a failed task instead of
+    /// stopping at the first failure.
+    #[arg(long)]
+    pub keep_going: bool,
+
+    /// Print why the cache of a task could not be used, e.g. which input
+    /// file, environment variable or package changed since the last run.
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Watch the `inputs` of the tasks and run the tasks again whenever one
+    /// of them changes.
+    ///
+    /// Tasks that are still running when a change is detected are killed
+    /// before they are started again.
+    #[arg(long, short = 'w')]
+    pub watch: bool,
 }
 
 /// CLI entry point for `pixi run`
@@ -116,123 +153,412 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     )
     .with_disambiguate_fn(disambiguate_task_interactive);
 
-    let task_graph = TaskGraph::from_cmd_args(&project, &search_environment, args.task)?;
+    let task_graph = TaskGraph::from_cmd_args(&project, &search_environment, args.task.clone())?;
 
     tracing::info!("Task graph: {}", task_graph);
 
-    // Traverse the task graph in topological order and execute each individual
-    // task.
-    let mut task_idx = 0;
-    let mut task_envs = HashMap::new();
-    for task_id in task_graph.topological_order() {
-        let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id);
-
-        // If the task is not executable (e.g. an alias), we skip it. This ensures we
-        // don't instantiate a prefix for an alias.
-        if !executable_task.task().is_executable() {
-            continue;
+    if !args.watch {
+        let cancel = CancellationToken::new();
+        let failed_exit_code = execute_task_graph(
+            &project,
+            &task_graph,
+            &mut lock_file,
+            &args,
+            explicit_environment.clone(),
+            &cancel,
+        )
+        .await?;
+        if let Some(code) = failed_exit_code {
+            std::process::exit(code);
         }
 
-        // Showing which command is being run if the level and type allows it.
-        if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
-            if task_idx > 0 {
-                // Add a newline between task outputs
-                eprintln!();
+        Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+        return Ok(());
+    }
+
+    // Watch the inputs of all the tasks in the graph and run the graph again
+    // whenever one of them changes.
+    let inputs = task_graph
+        .topological_order()
+        .into_iter()
+        .filter_map(|task_id| task_graph[task_id].task.as_execute())
+        .flat_map(|execute| execute.inputs.iter().flatten().cloned())
+        .unique()
+        .collect_vec();
+    if inputs.is_empty() {
+        miette::bail!(
+            "none of the tasks define `inputs`, so there is nothing to watch for changes"
+        );
+    }
+    // The tasks write their outputs while they run, which would otherwise
+    // restart the tasks over and over again when an output matches an input.
+    let excluded_outputs = task_graph
+        .topological_order()
+        .into_iter()
+        .filter_map(|task_id| task_graph[task_id].task.as_execute())
+        .flat_map(|execute| execute.outputs.iter().flatten())
+        .filter(|output| !output.starts_with('!'))
+        .map(|output| {
+            // A directory excludes everything in it, like it does for the task cache.
+            if output.ends_with('/') || project.root().join(output).is_dir() {
+                format!("!{}/**", output.trim_end_matches('/'))
+            } else {
+                format!("!{output}")
             }
-            eprintln!(
-                "{}{}{}{}{}{}{}",
-                console::Emoji("✨ ", ""),
-                console::style("Pixi task (").bold(),
-                console::style(executable_task.name().unwrap_or("unnamed"))
-                    .green()
-                    .bold(),
-                // Only print environment if multiple environments are available
-                if project.environments().len() > 1 {
-                    format!(
-                        " in {}",
-                        executable_task.run_environment.name().fancy_display()
-                    )
-                } else {
-                    "".to_string()
-                },
-                console::style("): ").bold(),
-                executable_task.display_command(),
-                if let Some(description) = executable_task.task().description() {
-                    console::style(format!(": ({})", description)).yellow()
-                } else {
-                    console::style("".to_string()).yellow()
+        })
+        .unique()
+        .collect_vec();
+    let mut watcher = GlobWatcher::new(
+        project.root(),
+        inputs
+            .iter()
+            .chain(excluded_outputs.iter())
+            .map(String::as_str),
+    )
+    .into_diagnostic()?;
+
+    loop {
+        let cancel = CancellationToken::new();
+        let run = execute_task_graph(
+            &project,
+            &task_graph,
+            &mut lock_file,
+            &args,
+            explicit_environment.clone(),
+            &cancel,
+        );
+        tokio::pin!(run);
+
+        // If the inputs change while the tasks are still running, the running
+        // tasks are killed before they are started again.
+        let changed = tokio::select! {
+            result = &mut run => {
+                match result {
+                    Ok(Some(code)) => eprintln!(
+                        "{}The tasks failed with exit code {code}",
+                        console::style(console::Emoji("❌ ", "")).red(),
+                    ),
+                    Ok(None) => {}
+                    Err(err) => eprintln!("{err:?}"),
                 }
-            );
-        }
+                None
+            }
+            changed = watcher.changed() => {
+                cancel.cancel();
+                let _ = run.await;
+                Some(changed.into_diagnostic()?)
+            }
+            // The tasks also receive the CTRL+C, killing them makes sure that
+            // pixi doesn't wait for tasks that ignore it.
+            _ = tokio::signal::ctrl_c() => {
+                cancel.cancel();
+                let _ = run.await;
+                return Ok(());
+            }
+        };
 
-        // check task cache
-        let task_cache = match executable_task
-            .can_skip(&lock_file.lock_file)
-            .await
-            .into_diagnostic()?
-        {
-            CanSkip::No(cache) => cache,
-            CanSkip::Yes => {
+        let changed = match changed {
+            Some(changed) => changed,
+            None => {
                 eprintln!(
-                    "Task '{}' can be skipped (cache hit) 🚀",
-                    console::style(executable_task.name().unwrap_or("")).bold()
+                    "{}Watching {} for changes...",
+                    console::Emoji("👀 ", ""),
+                    inputs.iter().format(", ")
                 );
-                task_idx += 1;
-                continue;
+                tokio::select! {
+                    changed = watcher.changed() => changed.into_diagnostic()?,
+                    _ = tokio::signal::ctrl_c() => return Ok(()),
+                }
             }
         };
+        eprintln!(
+            "\n{}Restarting because {} changed",
+            console::Emoji("🔄 ", ""),
+            changed
+                .iter()
+                .map(|path| path.strip_prefix(project.root()).unwrap_or(path).display())
+                .format(", ")
+        );
+    }
+}
+
+/// Runs the tasks in the graph, at most `args.jobs` at the same time, and
+/// returns the exit code of the first task that failed, if any.
+///
+/// Once `cancel` is triggered, no new tasks are started and the running tasks
+/// are killed.
+async fn execute_task_graph<'p>(
+    project: &'p Project,
+    task_graph: &TaskGraph<'p>,
+    lock_file: &mut LockFileDerivedData<'p>,
+    args: &Args,
+    explicit_environment: Option<Environment<'p>>,
+    cancel: &CancellationToken,
+) -> miette::Result<Option<i32>> {
+    // Schedule the tasks by their position in the topological order of the
+    // graph.
+    let order = task_graph.topological_order();
+    let position: HashMap<TaskId, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(idx, &task_id)| (task_id, idx))
+        .collect();
+    let dependencies = order
+        .iter()
+        .map(|&task_id| {
+            task_graph[task_id]
+                .dependencies
+                .iter()
+                .map(|dependency| position[dependency])
+                .collect_vec()
+        })
+        .collect_vec();
+    let mut schedule = TaskSchedule::new(&dependencies);
+
+    let jobs = args.jobs.map_or(1, NonZeroUsize::get);
+    let prefix_output = jobs > 1;
+
+    // The futures of the running tasks borrow the lock-file while the prefixes
+    // of the environments are updated in between, so they get their own copy.
+    let locked = lock_file.lock_file.clone();
+
+    // Schedule the tasks as soon as all their dependencies have finished and
+    // execute at most `jobs` of them at the same time.
+    let mut task_idx = 0;
+    let mut task_envs = HashMap::new();
+    let mut running = FuturesUnordered::new();
+    let mut failed_exit_code = None;
+    let mut error = None;
+    loop {
+        while running.len() < jobs && !cancel.is_cancelled() {
+            let Some(idx) = schedule.pop_ready() else {
+                break;
+            };
+            let executable_task = ExecutableTask::from_task_graph(&task_graph, order[idx]);
+
+            // If the task is not executable (e.g. an alias), we skip it. This ensures we
+            // don't instantiate a prefix for an alias.
+            if !executable_task.task().is_executable() {
+                schedule.finish(idx);
+                continue;
+            }
+
+            // Showing which command is being run if the level and type allows it.
+            if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
+                if task_idx > 0 && !prefix_output {
+                    // Add a newline between task outputs
+                    eprintln!();
+                }
+                eprintln!(
+                    "{}{}{}{}{}{}{}",
+                    console::Emoji("✨ ", ""),
+                    console::style("Pixi task (").bold(),
+                    console::style(executable_task.name().unwrap_or("unnamed"))
+                        .green()
+                        .bold(),
+                    // Only print environment if multiple environments are available
+                    if project.environments().len() > 1 {
+                        format!(
+                            " in {}",
+                            executable_task.run_environment.name().fancy_display()
+                        )
+                    } else {
+                        "".to_string()
+                    },
+                    console::style("): ").bold(),
+                    executable_task.display_command(),
+                    if let Some(description) = executable_task.task().description() {
+                        console::style(format!(": ({})", description)).yellow()
+                    } else {
+                        console::style("".to_string()).yellow()
+                    }
+                );
+            }
+
+            // check task cache
+            let task_cache = match executable_task.can_skip(&locked).await.into_diagnostic()? {
+                CanSkip::No(cache) => {
+                    if args.explain {
+                        let reasons = executable_task
+                            .explain_cache_miss(cache.as_ref())
+                            .await
+                            .into_diagnostic()?;
+                        eprintln!(
+                            "Task '{}' can not be skipped:\n{}",
+                            console::style(executable_task.name().unwrap_or("")).bold(),
+                            reasons
+                                .iter()
+                                .format_with("\n", |reason, f| f(&format_args!("  - {reason}")))
+                        );
+                    }
+                    cache
+                }
+                CanSkip::Yes => {
+                    eprintln!(
+                        "Task '{}' can be skipped (cache hit) 🚀",
+                        console::style(executable_task.name().unwrap_or("")).bold()
+                    );
+                    task_idx += 1;
+                    schedule.finish(idx);
+                    continue;
+                }
+                CanSkip::Restored => {
+                    eprintln!(
+                        "Task '{}' outputs were restored from the cache 🚀",
+                        console::style(executable_task.name().unwrap_or("")).bold()
+                    );
+                    task_idx += 1;
+                    schedule.finish(idx);
+                    continue;
+                }
+            };
+
+            // If we don't have a command environment yet, we need to compute it. We lazily
+            // compute the task environment because we only need the environment if
+            // a task is actually executed.
+            let task_env: &_ = match task_envs.entry(executable_task.run_environment.clone()) {
+                Entry::Occupied(env) => env.into_mut(),
+                Entry::Vacant(entry) => {
+                    // Ensure there is a valid prefix
+                    lock_file
+                        .prefix(
+                            &executable_task.run_environment,
+                            args.prefix_update_config.update_mode(),
+                        )
+                        .await?;
 
-        // If we don't have a command environment yet, we need to compute it. We lazily
-        // compute the task environment because we only need the environment if
-        // a task is actually executed.
-        let task_env: &_ = match task_envs.entry(executable_task.run_environment.clone()) {
-            Entry::Occupied(env) => env.into_mut(),
-            Entry::Vacant(entry) => {
-                // Ensure there is a valid prefix
-                lock_file
-                    .prefix(
+                    let command_env = get_task_env(
                         &executable_task.run_environment,
-                        args.prefix_update_config.update_mode(),
+                        args.clean_env || executable_task.task().clean_env(),
+                        Some(&lock_file.lock_file),
+                        project.config().force_activate(),
+                        project.config().experimental_activation_cache_usage(),
                     )
                     .await?;
+                    entry.insert(command_env)
+                }
+            };
 
-                let command_env = get_task_env(
-                    &executable_task.run_environment,
-                    args.clean_env || executable_task.task().clean_env(),
-                    Some(&lock_file.lock_file),
-                    project.config().force_activate(),
-                    project.config().experimental_activation_cache_usage(),
-                )
-                .await?;
-                entry.insert(command_env)
-            }
+            // Execute the task itself within the command environment and update the
+            // task cache with the new hash once it succeeded.
+            let command_env = task_env.clone();
+            let prefix =
+                prefix_output.then(|| executable_task.name().unwrap_or("unnamed").to_string());
+            let locked = &locked;
+            task_idx += 1;
+            running.push(async move {
+                let result =
+                    match execute_task(&executable_task, &command_env, prefix, cancel).await {
+                        Ok(()) => executable_task
+                            .save_cache(locked, task_cache)
+                            .await
+                            .map_err(TaskExecutionError::from),
+                        Err(err) => Err(err),
+                    };
+                (idx, result)
+            });
+        }
+
+        let Some((idx, result)) = running.next().await else {
+            break;
         };
 
-        // Execute the task itself within the command environment. If one of the tasks
-        // failed with a non-zero exit code, we exit this parent process with
-        // the same code.
-        match execute_task(&executable_task, task_env).await {
-            Ok(_) => {
-                task_idx += 1;
-            }
-            Err(TaskExecutionError::NonZeroExitCode(code)) => {
-                if code == 127 {
-                    command_not_found(&project, explicit_environment);
+        // The tasks that were killed because of the cancellation did not fail.
+        if cancel.is_cancelled() && failed_exit_code.is_none() && error.is_none() {
+            continue;
+        }
+
+        // If one of the tasks failed, we stop running tasks and report its exit
+        // code. Unless we keep going, in which case only the tasks that depend on
+        // the failed task are not started. Any other error stops all tasks, the
+        // running ones are still awaited so they are killed properly.
+        match result {
+            Ok(()) => schedule.finish(idx),
+            Err(err) => match err.failure_exit_code() {
+                Some(code) => {
+                    if let TaskExecutionError::NonZeroExitCode(127) = err {
+                        command_not_found(project, explicit_environment.clone());
+                    } else if !matches!(err, TaskExecutionError::NonZeroExitCode(_)) {
+                        let task = ExecutableTask::from_task_graph(&task_graph, order[idx]);
+                        eprintln!(
+                            "{}Task '{}' failed: {err}",
+                            console::style(console::Emoji("❌ ", "")).red(),
+                            console::style(task.name().unwrap_or("")).bold(),
+                        );
+                    }
+                    if failed_exit_code.is_none() && !args.keep_going {
+                        cancel.cancel();
+                    }
+                    failed_exit_code.get_or_insert(code);
+                }
+                None => {
+                    cancel.cancel();
+                    error.get_or_insert(err);
+                }
+            },
+        }
+    }
+
+    if let Some(err) = error {
+        return Err(err.into());
+    }
+    Ok(failed_exit_code)
+}
+
+/// Keeps track of which tasks can be started, which is once all the tasks
+/// they depend on have finished.
+///
+/// Tasks are identified by their position in the topological order of the
+/// graph. A task only waits for the tasks it depends on that come before it in
+/// this order. When running one task at a time this results in exactly the
+/// topological order.
+struct TaskSchedule {
+    dependents: Vec<Vec<usize>>,
+    pending_dependencies: Vec<usize>,
+    ready: BTreeSet<usize>,
+}
+
+impl TaskSchedule {
+    /// Creates a schedule from the positions of the dependencies of every
+    /// task.
+    fn new(dependencies: &[Vec<usize>]) -> Self {
+        let mut dependents = vec![Vec::new(); dependencies.len()];
+        let mut pending_dependencies = vec![0usize; dependencies.len()];
+        for (idx, task_dependencies) in dependencies.iter().enumerate() {
+            for &dependency_idx in task_dependencies {
+                if dependency_idx < idx {
+                    dependents[dependency_idx].push(idx);
+                    pending_dependencies[idx] += 1;
                 }
-                std::process::exit(code);
             }
-            Err(err) => return Err(err.into()),
         }
+        let ready = pending_dependencies
+            .iter()
+            .positions(|&pending| pending == 0)
+            .collect();
+        Self {
+            dependents,
+            pending_dependencies,
+            ready,
+        }
+    }
 
-        // Update the task cache with the new hash
-        executable_task
-            .save_cache(&lock_file, task_cache)
-            .await
-            .into_diagnostic()?;
+    /// Returns the first task in the topological order that can be started.
+    fn pop_ready(&mut self) -> Option<usize> {
+        self.ready.pop_first()
     }
 
-    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
-    Ok(())
+    /// Marks the task as finished and makes the tasks that depend on it ready
+    /// once all their dependencies have finished. Tasks that depend on a task
+    /// that is never finished, e.g. because it failed, are never started.
+    fn finish(&mut self, idx: usize) {
+        for &dependent in &self.dependents[idx] {
+            self.pending_dependencies[dependent] -= 1;
+            if self.pending_dependencies[dependent] == 0 {
+                self.ready.insert(dependent);
+            }
+        }
+    }
 }
 
 /// Called when a command was not found.
@@ -267,26 +593,139 @@ enum TaskExecutionError {
     #[error("the script exited with a non-zero exit code {0}")]
     NonZeroExitCode(i32),
 
+    #[error("the task did not finish within {}", humantime::format_duration(*.0))]
+    Timeout(Duration),
+
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
 
+    #[error(transparent)]
+    FailedToRenderCommand(#[from] FailedToRenderCommand),
+
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
 
     #[error(transparent)]
     UnsupportedPlatformError(#[from] UnsupportedPlatformError),
+
+    #[error(transparent)]
+    CacheUpdate(#[from] CacheUpdateError),
+
+    #[error(transparent)]
+    Sandbox(#[from] SandboxError),
+
+    #[cfg(unix)]
+    #[error("a process of the task used more than {} of CPU time", humantime::format_duration(*.0))]
+    CpuTimeLimitExceeded(Duration),
+
+    #[cfg(unix)]
+    #[error("a process of the task used more than {0} MB of memory")]
+    MemoryLimitExceeded(u64),
+
+    #[cfg(unix)]
+    #[error("failed to run the task in a process of its own")]
+    Process(#[source] std::io::Error),
+
+    #[cfg(not(unix))]
+    #[error("resource limits of tasks are only supported on unix")]
+    ResourceLimitsUnsupported,
 }
 
-/// Called to execute a single command.
+impl From<crate::task::TaskExecutionError> for TaskExecutionError {
+    fn from(err: crate::task::TaskExecutionError) -> Self {
+        match err {
+            crate::task::TaskExecutionError::InvalidWorkingDirectory(err) => err.into(),
+            crate::task::TaskExecutionError::FailedToParseShellScript(err) => err.into(),
+            crate::task::TaskExecutionError::FailedToRenderCommand(err) => err.into(),
+        }
+    }
+}
+
+impl TaskExecutionError {
+    /// Returns the exit code to report when the error means that the task
+    /// itself failed, or `None` when pixi could not run the task.
+    fn failure_exit_code(&self) -> Option<i32> {
+        match self {
+            TaskExecutionError::NonZeroExitCode(code) => Some(*code),
+            // The same exit code as the `timeout` command.
+            TaskExecutionError::Timeout(_) => Some(124),
+            TaskExecutionError::Sandbox(_) => Some(1),
+            #[cfg(unix)]
+            TaskExecutionError::CpuTimeLimitExceeded(_)
+            | TaskExecutionError::MemoryLimitExceeded(_) => Some(1),
+            _ => None,
+        }
+    }
+}
+
+/// Called to execute a single command. If a prefix is given, every line that
+/// the task writes to stdout and stderr is prefixed with it. When `cancel` is
+/// triggered while the task is running, the processes of the task are killed.
+///
+/// A task that fails or times out is retried as many times as its `retries`,
+/// waiting a bit longer before every next attempt.
 ///
-/// This function is called from [`execute`].
+/// This function is called from [`execute_task_graph`].
 async fn execute_task<'p>(
     task: &ExecutableTask<'p>,
     command_env: &HashMap<String, String>,
+    prefix: Option<String>,
+    cancel: &CancellationToken,
+) -> Result<(), TaskExecutionError> {
+    let retries = task.task().retries();
+    let mut attempt = 0;
+    loop {
+        match execute_task_once(task, command_env, prefix.clone(), cancel).await {
+            Err(
+                err @ (TaskExecutionError::NonZeroExitCode(_) | TaskExecutionError::Timeout(_)),
+            ) if attempt < retries && !cancel.is_cancelled() => {
+                attempt += 1;
+                let backoff = retry_backoff(attempt);
+                eprintln!(
+                    "{}Task '{}' failed: {err}, retrying in {} ({attempt}/{retries})",
+                    console::style(console::Emoji("⚠️ ", "")).yellow(),
+                    console::style(task.name().unwrap_or("")).bold(),
+                    humantime::format_duration(backoff),
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = cancel.cancelled() => return Err(err),
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns the time to wait before the given retry of a task. The time
+/// doubles with every retry, up to a minute.
+fn retry_backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.saturating_sub(1).min(6)).min(Duration::from_secs(60))
+}
+
+/// Executes a single attempt of a task, see [`execute_task`].
+async fn execute_task_once<'p>(
+    task: &ExecutableTask<'p>,
+    command_env: &HashMap<String, String>,
+    prefix: Option<String>,
+    cancel: &CancellationToken,
 ) -> Result<(), TaskExecutionError> {
     let Some(script) = task.as_deno_script()? else {
         return Ok(());
     };
+    let limits = task.task().limits();
+    let timeout = task.task().timeout();
+    // On unix, the processes a task starts can only all be killed when the
+    // timeout passes if they are in a process group of their own.
+    let script = match task.as_script()? {
+        Some(source) if limits.is_some() || (cfg!(unix) && timeout.is_some()) => {
+            TaskScript::Process {
+                source,
+                limits: limits.cloned(),
+            }
+        }
+        _ => TaskScript::Shell(script),
+    };
     let cwd = task.working_directory()?;
 
     // Ignore CTRL+C
@@ -295,16 +734,67 @@ async fn execute_task<'p>(
     // the runtime of the program which is fine when using run in isolation,
     // however if we start to use run in conjunction with some other command we
     // might want to revaluate this.
-    let ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+    let mut ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+
+    let (stdout, stderr, output_handles) = match prefix {
+        None => (ShellPipeWriter::stdout(), ShellPipeWriter::stderr(), None),
+        Some(prefix) => {
+            let prefix = format!("{} ", console::style(format!("[{prefix}]")).cyan().bold());
+            let (stdout, stdout_handle) = prefixed_output(prefix.clone(), std::io::stdout);
+            let (stderr, stderr_handle) = prefixed_output(prefix, std::io::stderr);
+            (stdout, stderr, Some((stdout_handle, stderr_handle)))
+        }
+    };
 
-    let execute_future =
-        deno_task_shell::execute(script, command_env.clone(), &cwd, Default::default());
-    let status_code = tokio::select! {
-        code = execute_future => code,
+    let execute_future = async move {
+        match task.task().sandbox() {
+            Some(sandbox) => {
+                let mut policy = SandboxPolicy::new(
+                    sandbox,
+                    task.project().root(),
+                    &task.run_environment.dir(),
+                )?;
+                // The script is run by pixi itself in a process of its own, which must be
+                // allowed to start.
+                if let TaskScript::Process { .. } = script {
+                    policy.allow_read(std::env::current_exe().map_err(SandboxError::from)?);
+                }
+                let command_env = command_env.clone();
+                let cancel = cancel.clone();
+                run_sandboxed(policy, move || {
+                    run_script(script, command_env, cwd, stdout, stderr, cancel, timeout)
+                })
+                .await?
+            }
+            None => {
+                run_script(
+                    script,
+                    command_env.clone(),
+                    cwd,
+                    stdout,
+                    stderr,
+                    cancel.clone(),
+                    timeout,
+                )
+                .await
+            }
+        }
+    };
+    let result = tokio::select! {
+        result = execute_future => result,
         // This should never exit
-        _ = ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
+        _ = &mut ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
     };
+    // Stop ignoring CTRL+C, so it can stop `--watch` in between runs.
+    ctrl_c.abort();
 
+    // Wait until all the output of the task has been written.
+    if let Some((stdout_handle, stderr_handle)) = output_handles {
+        let _ = stdout_handle.await;
+        let _ = stderr_handle.await;
+    }
+
+    let status_code = result?;
     if status_code != 0 {
         return Err(TaskExecutionError::NonZeroExitCode(status_code));
     }
@@ -312,6 +802,285 @@ async fn execute_task<'p>(
     Ok(())
 }
 
+/// The script of a task.
+#[cfg_attr(not(unix), allow(dead_code))]
+enum TaskScript {
+    /// A script that is run in the shell of pixi itself.
+    Shell(SequentialList),
+
+    /// A script that is run in a process group of its own, to which the
+    /// resource limits are applied.
+    Process {
+        source: String,
+        limits: Option<TaskLimits>,
+    },
+}
+
+/// Runs the script of a task and returns its exit code. When `cancel` is
+/// triggered, the processes started by the script are killed.
+async fn run_script(
+    script: TaskScript,
+    command_env: HashMap<String, String>,
+    cwd: PathBuf,
+    stdout: ShellPipeWriter,
+    stderr: ShellPipeWriter,
+    cancel: CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<i32, TaskExecutionError> {
+    match script {
+        TaskScript::Shell(script) => {
+            execute_script(script, command_env, cwd, stdout, stderr, cancel, timeout).await
+        }
+        #[cfg(unix)]
+        TaskScript::Process { source, limits } => {
+            execute_script_in_process_group(
+                source,
+                limits.as_ref(),
+                command_env,
+                cwd,
+                stdout,
+                stderr,
+                cancel,
+                timeout,
+            )
+            .await
+        }
+        #[cfg(not(unix))]
+        TaskScript::Process { .. } => Err(TaskExecutionError::ResourceLimitsUnsupported),
+    }
+}
+
+/// Executes the script in the shell and returns its exit code. When `cancel`
+/// is triggered or the timeout passes, the processes started by the shell are
+/// killed.
+async fn execute_script(
+    script: SequentialList,
+    command_env: HashMap<String, String>,
+    cwd: PathBuf,
+    stdout: ShellPipeWriter,
+    stderr: ShellPipeWriter,
+    cancel: CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<i32, TaskExecutionError> {
+    // Cancelling the token of the shell kills the processes it spawned.
+    let state = ShellState::new(command_env, &cwd, Default::default());
+    let shell_token = state.token().clone();
+    let execute_future = deno_task_shell::execute_with_pipes(
+        script,
+        state,
+        ShellPipeReader::stdin(),
+        stdout,
+        stderr,
+    );
+    tokio::pin!(execute_future);
+    tokio::select! {
+        code = &mut execute_future => Ok(code),
+        _ = cancel.cancelled() => {
+            shell_token.cancel();
+            Ok(execute_future.await)
+        }
+        timeout = elapsed(timeout) => {
+            shell_token.cancel();
+            execute_future.await;
+            Err(TaskExecutionError::Timeout(timeout))
+        }
+    }
+}
+
+/// Executes the script in a process group of its own and returns its exit
+/// code.
+///
+/// The script is run by the hidden `task-shell` command of pixi in a process
+/// of its own, to which the limits are applied before it starts. The limits
+/// are inherited by every process the script starts. The process is started
+/// in a new process group, so all the processes of the script can be killed
+/// at once when `cancel` is triggered or the timeout passes.
+#[cfg(unix)]
+#[allow(clippy::too_many_arguments)]
+async fn execute_script_in_process_group(
+    source: String,
+    limits: Option<&TaskLimits>,
+    command_env: HashMap<String, String>,
+    cwd: PathBuf,
+    stdout: ShellPipeWriter,
+    stderr: ShellPipeWriter,
+    cancel: CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<i32, TaskExecutionError> {
+    let current_exe = std::env::current_exe().map_err(TaskExecutionError::Process)?;
+    let mut command = tokio::process::Command::new(current_exe);
+    command
+        .args(["task-shell", "--"])
+        .arg(source)
+        .env_clear()
+        .envs(command_env)
+        .current_dir(cwd)
+        .stdout(stdout.into_stdio())
+        .stderr(stderr.into_stdio());
+    run_in_process_group(command, limits, cancel, timeout).await
+}
+
+/// Runs the command in a new process group with the resource limits applied
+/// and returns its exit code. All the processes in the group are killed when
+/// `cancel` is triggered or the timeout passes.
+#[cfg(unix)]
+async fn run_in_process_group(
+    mut command: tokio::process::Command,
+    limits: Option<&TaskLimits>,
+    cancel: CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<i32, TaskExecutionError> {
+    use rlimit::Resource;
+    use std::os::unix::process::ExitStatusExt;
+
+    let memory_limit = limits.and_then(|limits| limits.memory);
+    let cpu_time_limit = limits.and_then(|limits| limits.cpu_time);
+    let memory = memory_limit.map(|megabytes| megabytes.saturating_mul(1024 * 1024));
+    // The limit is set in whole seconds, round up so it is never zero.
+    let cpu_time = cpu_time_limit.map(|cpu_time| cpu_time.as_secs_f64().ceil() as u64);
+
+    command.process_group(0).kill_on_drop(true);
+
+    // SAFETY: the closure only calls `setrlimit`, which is async-signal-safe.
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(memory) = memory {
+                rlimit::setrlimit(Resource::AS, memory, memory)?;
+            }
+            if let Some(cpu_time) = cpu_time {
+                // A process receives SIGXCPU once it exceeds the soft limit and is killed
+                // when it exceeds the hard limit.
+                rlimit::setrlimit(Resource::CPU, cpu_time, cpu_time + 1)?;
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn().map_err(|err| match memory_limit {
+        // The process can't even be started within the memory limit.
+        Some(memory) if err.raw_os_error() == Some(libc::ENOMEM) => {
+            TaskExecutionError::MemoryLimitExceeded(memory)
+        }
+        _ => TaskExecutionError::Process(err),
+    })?;
+    let process_group = child.id().unwrap_or_default() as libc::pid_t;
+    let kill = |signal| {
+        // SAFETY: `killpg` has no memory safety requirements.
+        unsafe { libc::killpg(process_group, signal) };
+    };
+
+    // Like a shell, report the processes that were killed by a signal with an
+    // exit code of 128 plus the number of the signal.
+    let exit_code = |status: std::process::ExitStatus| {
+        status
+            .code()
+            .or_else(|| status.signal().map(|signal| 128 + signal))
+            .unwrap_or(1)
+    };
+
+    let timed_out = elapsed(timeout);
+    tokio::pin!(timed_out);
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status,
+            // The processes of the script don't receive the CTRL+C of the terminal
+            // because they are in a process group of their own.
+            Ok(()) = tokio::signal::ctrl_c() => kill(libc::SIGINT),
+            _ = cancel.cancelled() => {
+                kill(libc::SIGKILL);
+                let status = child.wait().await.map_err(TaskExecutionError::Process)?;
+                return Ok(exit_code(status));
+            }
+            timeout = &mut timed_out => {
+                kill(libc::SIGKILL);
+                let _ = child.wait().await;
+                return Err(TaskExecutionError::Timeout(timeout));
+            }
+        }
+    }
+    .map_err(TaskExecutionError::Process)?;
+
+    let code = exit_code(status);
+    match cpu_time_limit {
+        Some(cpu_time) if code == 128 + libc::SIGXCPU => {
+            Err(TaskExecutionError::CpuTimeLimitExceeded(cpu_time))
+        }
+        _ => Ok(code),
+    }
+}
+
+/// Waits until the timeout has passed and returns it, or waits forever if
+/// there is no timeout.
+async fn elapsed(timeout: Option<Duration>) -> Duration {
+    match timeout {
+        Some(timeout) => {
+            tokio::time::sleep(timeout).await;
+            timeout
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Returns a pipe for the output of a task and a handle to the thread that
+/// writes every line that is written to the pipe to the given output, prefixed
+/// with the given prefix.
+fn prefixed_output<W: Write>(
+    prefix: String,
+    output: fn() -> W,
+) -> (ShellPipeWriter, JoinHandle<std::io::Result<()>>) {
+    let (reader, writer) = deno_task_shell::pipe();
+    let handle = tokio::task::spawn_blocking(move || {
+        let mut writer = PrefixedWriter {
+            prefix,
+            output,
+            buffer: Vec::new(),
+        };
+        reader.pipe_to(&mut writer).map_err(std::io::Error::other)?;
+        writer.flush()
+    });
+    (writer, handle)
+}
+
+/// A writer that writes complete lines to an output, each prefixed with a
+/// prefix. Writing whole lines at a time ensures the output of concurrently
+/// running tasks is not interleaved within a line.
+struct PrefixedWriter<W> {
+    prefix: String,
+    output: fn() -> W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> PrefixedWriter<W> {
+    fn write_line(&self, line: &[u8]) -> std::io::Result<()> {
+        let mut output = (self.output)();
+        output.write_all(self.prefix.as_bytes())?;
+        output.write_all(line)?;
+        if !line.ends_with(b"\n") {
+            output.write_all(b"\n")?;
+        }
+        output.flush()
+    }
+}
+
+impl<W: Write> Write for PrefixedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.write_line(&line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buffer.is_empty() {
+            let line = std::mem::take(&mut self.buffer);
+            self.write_line(&line)?;
+        }
+        Ok(())
+    }
+}
+
 /// Called to disambiguate between environments to run a task in.
 fn disambiguate_task_interactive<'p>(
     problem: &AmbiguousTask<'p>,
@@ -343,3 +1112,165 @@ fn disambiguate_task_interactive<'p>(
         .map_or(None, identity)
         .map(|idx| problem.environments[idx].clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::VecDeque, io::Write, time::Duration};
+
+    use super::{retry_backoff, PrefixedWriter, TaskSchedule};
+
+    /// Runs the schedule with at most `jobs` tasks at the same time, where the
+    /// task that was started first finishes first, and returns the order in
+    /// which the tasks were started. The tasks in `failing` never finish.
+    fn simulate_schedule(
+        dependencies: &[Vec<usize>],
+        jobs: usize,
+        failing: &[usize],
+    ) -> Vec<usize> {
+        let mut schedule = TaskSchedule::new(dependencies);
+        let mut running = VecDeque::new();
+        let mut finished = Vec::new();
+        let mut started = Vec::new();
+        loop {
+            while running.len() < jobs {
+                let Some(idx) = schedule.pop_ready() else {
+                    break;
+                };
+                assert!(
+                    dependencies[idx].iter().all(|dep| finished.contains(dep)),
+                    "task {idx} was started before its dependencies finished"
+                );
+                started.push(idx);
+                running.push_back(idx);
+            }
+            let Some(idx) = running.pop_front() else {
+                break;
+            };
+            if !failing.contains(&idx) {
+                schedule.finish(idx);
+                finished.push(idx);
+            }
+        }
+        started
+    }
+
+    #[test]
+    fn test_schedule_order() {
+        // 1 and 2 depend on 0, 3 depends on 1 and 2, 4 depends on nothing.
+        let dependencies = vec![vec![], vec![0], vec![0], vec![1, 2], vec![]];
+        assert_eq!(
+            simulate_schedule(&dependencies, 1, &[]),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            simulate_schedule(&dependencies, 2, &[]),
+            vec![0, 4, 1, 2, 3]
+        );
+        assert_eq!(
+            simulate_schedule(&dependencies, 8, &[]),
+            vec![0, 4, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_schedule_skips_dependents_of_failed_task() {
+        let dependencies = vec![vec![], vec![0], vec![0], vec![1, 2], vec![]];
+        assert_eq!(simulate_schedule(&dependencies, 2, &[1]), vec![0, 4, 1, 2]);
+        assert_eq!(simulate_schedule(&dependencies, 2, &[0]), vec![0, 4]);
+    }
+
+    thread_local! {
+        static OUTPUT: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// An output that writes to [`OUTPUT`].
+    struct TestOutput;
+
+    impl Write for TestOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            OUTPUT.with(|output| output.borrow_mut().extend_from_slice(buf));
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_prefixed_writer() {
+        let output = || String::from_utf8(OUTPUT.with(|output| output.borrow().clone())).unwrap();
+        let mut writer = PrefixedWriter {
+            prefix: "[task] ".to_string(),
+            output: || TestOutput,
+            buffer: Vec::new(),
+        };
+
+        writer.write_all(b"hel").unwrap();
+        assert_eq!(output(), "");
+        writer.write_all(b"lo\nwor").unwrap();
+        assert_eq!(output(), "[task] hello\n");
+        writer.write_all(b"ld\nfoo\nba").unwrap();
+        assert_eq!(output(), "[task] hello\n[task] world\n[task] foo\n");
+        writer.flush().unwrap();
+        assert_eq!(
+            output(),
+            "[task] hello\n[task] world\n[task] foo\n[task] ba\n"
+        );
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        assert_eq!(retry_backoff(1), Duration::from_secs(1));
+        assert_eq!(retry_backoff(2), Duration::from_secs(2));
+        assert_eq!(retry_backoff(4), Duration::from_secs(8));
+        assert_eq!(retry_backoff(7), Duration::from_secs(60));
+        assert_eq!(retry_backoff(100), Duration::from_secs(60));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_kills_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+
+        // The shell starts a grandchild that outlives the shell if only the
+        // shell is killed.
+        let mut command = tokio::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg("sleep 30 & echo $! > \"$1\"; wait")
+            .arg("sh")
+            .arg(&pid_file);
+        let result = super::run_in_process_group(
+            command,
+            None,
+            tokio_util::sync::CancellationToken::new(),
+            Some(Duration::from_millis(500)),
+        )
+        .await;
+        assert!(
+            matches!(result, Err(super::TaskExecutionError::Timeout(_))),
+            "expected a timeout, got {result:?}"
+        );
+
+        // The grandchild is gone, or a zombie when there is no init process
+        // that reaps it.
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let is_running = || {
+            let output = std::process::Command::new("ps")
+                .args(["-o", "stat=", "-p", pid.trim()])
+                .output()
+                .unwrap();
+            let stat = String::from_utf8_lossy(&output.stdout);
+            !stat.trim().is_empty() && !stat.trim().starts_with('Z')
+        };
+        for _ in 0..50 {
+            if !is_running() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        panic!("the grandchild {} is still running", pid.trim());
+    }
+}
@@ -69,7 +69,7 @@ async fn generate_activation_script(
             .unwrap_or_else(|| ShellEnum::from_env().unwrap_or_default())
     });
 
-    let activator = get_activator(environment, shell).into_diagnostic()?;
+    let activator = get_activator(environment, shell)?;
 
     let path = std::env::var("PATH")
         .ok()
@@ -6,7 +6,7 @@ use clap::Parser;
 use fancy_display::FancyDisplay;
 use indexmap::IndexMap;
 use itertools::Itertools;
-use pixi_manifest::task::{quote, Alias, CmdArgs, Execute, Task, TaskName};
+use pixi_manifest::task::{quote, Alias, CmdArgs, Dependency, Execute, Task, TaskName};
 use pixi_manifest::EnvironmentName;
 use pixi_manifest::FeatureName;
 use rattler_conda_types::Platform;
@@ -139,7 +139,12 @@ pub struct ListArgs {
 
 impl From<AddArgs> for Task {
     fn from(value: AddArgs) -> Self {
-        let depends_on = value.depends_on.unwrap_or_default();
+        let depends_on = value
+            .depends_on
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dependency::from)
+            .collect_vec();
         // description or none
         let description = value.description;
 
@@ -184,12 +189,17 @@ impl From<AddArgs> for Task {
             Self::Execute(Execute {
                 cmd: CmdArgs::Single(cmd_args),
                 depends_on,
+                args: None,
                 inputs: None,
                 outputs: None,
                 cwd,
                 env,
                 description,
                 clean_env,
+                sandbox: None,
+                timeout: None,
+                retries: 0,
+                limits: None,
             })
         }
     }
@@ -198,7 +208,7 @@ impl From<AddArgs> for Task {
 impl From<AliasArgs> for Task {
     fn from(value: AliasArgs) -> Self {
         Self::Alias(Alias {
-            depends_on: value.depends_on,
+            depends_on: value.depends_on.into_iter().map(Dependency::from).collect(),
             description: value.description,
         })
     }
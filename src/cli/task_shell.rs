@@ -0,0 +1,35 @@
+use clap::Parser;
+use deno_task_shell::{ShellPipeReader, ShellPipeWriter, ShellState};
+use miette::IntoDiagnostic;
+
+/// Runs a task script in the task shell.
+///
+/// This is used by `pixi run` to run tasks with resource limits in a process
+/// of their own, the environment and working directory of the task are taken
+/// from the current process.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The script to run.
+    pub script: String,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let script = deno_task_shell::parser::parse(args.script.trim())
+        .map_err(|e| miette::miette!("failed to parse the task script: {e}"))?;
+    let cwd = std::env::current_dir().into_diagnostic()?;
+
+    // Ignore CTRL+C, `pixi run` forwards it to all the processes of the task
+    // so the processes started by the script handle it themselves.
+    tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+
+    let state = ShellState::new(std::env::vars().collect(), &cwd, Default::default());
+    let code = deno_task_shell::execute_with_pipes(
+        script,
+        state,
+        ShellPipeReader::stdin(),
+        ShellPipeWriter::stdout(),
+        ShellPipeWriter::stderr(),
+    )
+    .await;
+    std::process::exit(code);
+}
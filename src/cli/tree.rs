@@ -58,14 +58,14 @@ pub struct Args {
     pub invert: bool,
 }
 
-struct Symbols {
-    down: &'static str,
-    tee: &'static str,
-    ell: &'static str,
-    empty: &'static str,
+pub(crate) struct Symbols {
+    pub(crate) down: &'static str,
+    pub(crate) tee: &'static str,
+    pub(crate) ell: &'static str,
+    pub(crate) empty: &'static str,
 }
 
-static UTF8_SYMBOLS: Symbols = Symbols {
+pub(crate) static UTF8_SYMBOLS: Symbols = Symbols {
     down: "│  ",
     tee: "├──",
     ell: "└──",
@@ -0,0 +1,316 @@
+//! Uploads packages to anaconda.org.
+//!
+//! An upload consists of staging the distribution, which returns a pre-signed
+//! form to post the file to, and committing the distribution afterwards. The
+//! package and the release are created first if they don't exist yet.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{stream, StreamExt};
+use miette::IntoDiagnostic;
+use rattler_digest::{compute_file_digest, Md5};
+use rattler_networking::Authentication;
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use url::Url;
+
+use super::{
+    check_response, progress_stream, report_uploaded, stored_authentication, PackageFile,
+    UploadError,
+};
+
+/// The response of the `stage` endpoint.
+#[derive(Debug, Deserialize)]
+struct StagedDistribution {
+    post_url: String,
+    form_data: serde_json::Map<String, Value>,
+    dist_id: String,
+}
+
+/// A client for the anaconda.org api.
+struct AnacondaClient {
+    client: reqwest::Client,
+    api_url: Url,
+    token: String,
+}
+
+impl AnacondaClient {
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<reqwest::Response, UploadError> {
+        let host = self.api_url.to_string();
+        let url = format!("{}/{path}", self.api_url.as_str().trim_end_matches('/'));
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("Authorization", format!("token {}", self.token));
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body.to_string());
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| UploadError::RequestFailed {
+                host,
+                source: e.into(),
+            })
+    }
+
+    /// Sends a request and returns the json response.
+    async fn request_json(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> miette::Result<Value> {
+        let response = self.request(method, path, body).await?;
+        let response = check_response(self.api_url.as_str(), response)?;
+        let bytes = response.bytes().await.into_diagnostic()?;
+        if bytes.is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_slice(&bytes).into_diagnostic()
+    }
+
+    /// Returns `true` if the resource exists.
+    async fn exists(&self, path: &str) -> miette::Result<bool> {
+        let response = self.request(Method::GET, path, None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        check_response(self.api_url.as_str(), response)?;
+        Ok(true)
+    }
+}
+
+/// Uploads the packages to the `label` of the `owner` on anaconda.org.
+pub(super) async fn upload(
+    host: &str,
+    owner: Option<&str>,
+    label: &str,
+    packages: &[PackageFile],
+    force: bool,
+) -> miette::Result<()> {
+    let host_url = Url::parse(host).into_diagnostic()?;
+    let api_url = api_url(&host_url)?;
+
+    let token = match stored_authentication(&host_url)? {
+        Some(auth) => Some(auth),
+        None => stored_authentication(&api_url)?,
+    };
+    let token = match token {
+        Some(Authentication::CondaToken(token)) | Some(Authentication::BearerToken(token)) => token,
+        _ => miette::bail!(
+            "no token found for {host}, store it with `pixi auth login {host} --conda-token <TOKEN>`"
+        ),
+    };
+
+    let client = AnacondaClient {
+        client: reqwest::Client::new(),
+        api_url,
+        token,
+    };
+
+    let owner = match owner {
+        Some(owner) => owner.to_string(),
+        None => {
+            let user = client.request_json(Method::GET, "user", None).await?;
+            let Some(login) = user.get("login").and_then(Value::as_str) else {
+                miette::bail!("failed to determine the authenticated user, specify `--owner`");
+            };
+            login.to_string()
+        }
+    };
+
+    for package in packages {
+        upload_package(&client, &owner, label, package, force).await?;
+        report_uploaded(package);
+    }
+
+    Ok(())
+}
+
+async fn upload_package(
+    client: &AnacondaClient,
+    owner: &str,
+    label: &str,
+    package: &PackageFile,
+    force: bool,
+) -> miette::Result<()> {
+    let index_json = &package.index_json;
+    let name = index_json.name.as_normalized();
+    let version = index_json.version.to_string();
+    let subdir = package.subdir();
+
+    let package_path = format!("package/{owner}/{name}");
+    if !client.exists(&package_path).await? {
+        client
+            .request_json(
+                Method::POST,
+                &package_path,
+                Some(json!({ "public": true, "summary": name })),
+            )
+            .await?;
+    }
+
+    let release_path = format!("release/{owner}/{name}/{version}");
+    if !client.exists(&release_path).await? {
+        client
+            .request_json(
+                Method::POST,
+                &release_path,
+                Some(json!({ "requirements": {}, "announce": false, "description": null })),
+            )
+            .await?;
+    }
+
+    let dist_path = format!("{owner}/{name}/{version}/{subdir}/{}", package.filename);
+    if force && client.exists(&format!("dist/{dist_path}")).await? {
+        client
+            .request_json(Method::DELETE, &format!("dist/{dist_path}"), None)
+            .await?;
+    }
+
+    let md5 = STANDARD.encode(compute_file_digest::<Md5>(&package.path).into_diagnostic()?);
+    let staged = client
+        .request_json(
+            Method::POST,
+            &format!("stage/{dist_path}"),
+            Some(json!({
+                "distribution_type": "conda",
+                "attrs": index_json,
+                "channels": [label],
+                "sha256": package.sha256,
+                "size": package.size,
+                "md5": md5,
+            })),
+        )
+        .await?;
+    let staged: StagedDistribution = serde_json::from_value(staged).into_diagnostic()?;
+
+    // Post the file to the pre-signed form.
+    let mut fields = staged
+        .form_data
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect::<Vec<_>>();
+    fields.push(("Content-Length".to_string(), package.size.to_string()));
+    fields.push(("Content-MD5".to_string(), md5));
+
+    let boundary = format!("----pixi-upload-{}", package.sha256);
+    let (head, tail) = multipart_parts(&boundary, &fields, &package.filename);
+    let content_length = head.len() as u64 + package.size + tail.len() as u64;
+    let body = stream::once(async move { Ok(head.into()) })
+        .chain(progress_stream(package)?)
+        .chain(stream::once(async move { Ok(tail.into()) }));
+    let response = client
+        .client
+        .post(&staged.post_url)
+        .header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .header("Content-Length", content_length)
+        .body(reqwest::Body::wrap_stream(body))
+        .send()
+        .await
+        .map_err(|e| UploadError::RequestFailed {
+            host: staged.post_url.clone(),
+            source: e.into(),
+        })?;
+    check_response(&staged.post_url, response)?;
+
+    client
+        .request_json(
+            Method::POST,
+            &format!("commit/{dist_path}"),
+            Some(json!({ "dist_id": staged.dist_id })),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Returns the url of the api that belongs to the given host.
+fn api_url(host: &Url) -> miette::Result<Url> {
+    match host.host_str() {
+        Some("anaconda.org") | Some("www.anaconda.org") => {
+            Url::parse("https://api.anaconda.org").into_diagnostic()
+        }
+        _ => Ok(host.clone()),
+    }
+}
+
+/// Returns the parts of a `multipart/form-data` body that come before and
+/// after the contents of the file.
+fn multipart_parts(
+    boundary: &str,
+    fields: &[(String, String)],
+    filename: &str,
+) -> (Vec<u8>, Vec<u8>) {
+    let mut head = String::new();
+    for (name, value) in fields {
+        head.push_str(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+        ));
+    }
+    head.push_str(&format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+    ));
+    let tail = format!("\r\n--{boundary}--\r\n");
+    (head.into_bytes(), tail.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_parts() {
+        let fields = vec![("key".to_string(), "value".to_string())];
+        let (head, tail) = multipart_parts("boundary", &fields, "foo-1.0-0.conda");
+        let body = format!(
+            "{}<contents>{}",
+            String::from_utf8(head).unwrap(),
+            String::from_utf8(tail).unwrap()
+        );
+        assert_eq!(
+            body,
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"key\"\r\n\r\n\
+             value\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"foo-1.0-0.conda\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n\
+             <contents>\r\n\
+             --boundary--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_api_url() {
+        assert_eq!(
+            api_url(&Url::parse("https://anaconda.org").unwrap())
+                .unwrap()
+                .as_str(),
+            "https://api.anaconda.org/"
+        );
+        assert_eq!(
+            api_url(&Url::parse("https://api.anaconda.org").unwrap())
+                .unwrap()
+                .as_str(),
+            "https://api.anaconda.org/"
+        );
+    }
+}
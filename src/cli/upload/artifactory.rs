@@ -0,0 +1,49 @@
+//! Uploads packages to an artifactory conda repository.
+
+use miette::IntoDiagnostic;
+use rattler_networking::Authentication;
+use url::Url;
+
+use super::{
+    check_response, progress_body, report_uploaded, stored_authentication, PackageFile, UploadError,
+};
+
+/// Uploads the packages into the subdirectories of the artifactory repository
+/// at `host`, e.g. `https://example.jfrog.io/artifactory/my-conda-repo`.
+pub(super) async fn upload(host: &str, packages: &[PackageFile]) -> miette::Result<()> {
+    let host_url = Url::parse(host.trim_end_matches('/')).into_diagnostic()?;
+    let authentication = stored_authentication(&host_url)?;
+
+    let client = reqwest::Client::new();
+    for package in packages {
+        let url = format!("{host_url}/{}/{}", package.subdir(), package.filename);
+        let mut request = client
+            .put(url)
+            .header("X-Checksum-Sha256", &package.sha256)
+            .header("X-Checksum-Md5", &package.md5)
+            .header("Content-Length", package.size)
+            .header("Content-Type", "application/octet-stream");
+        request = match &authentication {
+            Some(Authentication::BasicHTTP { username, password }) => {
+                request.basic_auth(username, Some(password))
+            }
+            Some(Authentication::BearerToken(token)) | Some(Authentication::CondaToken(token)) => {
+                request.bearer_auth(token)
+            }
+            _ => request,
+        };
+
+        let response = request
+            .body(progress_body(package)?)
+            .send()
+            .await
+            .map_err(|e| UploadError::RequestFailed {
+                host: host.to_string(),
+                source: e.into(),
+            })?;
+        check_response(host, response)?;
+        report_uploaded(package);
+    }
+
+    Ok(())
+}
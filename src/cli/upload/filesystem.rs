@@ -0,0 +1,53 @@
+//! Uploads packages to a channel in a directory on the local filesystem.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use miette::IntoDiagnostic;
+use url::Url;
+
+use super::{report_uploaded, PackageFile};
+use crate::channel_index::{self, IndexOptions};
+
+/// Copies the packages into the channel directory and re-indexes the
+/// subdirectories that changed.
+pub(super) fn upload(host: &str, packages: &[PackageFile], force: bool) -> miette::Result<()> {
+    let channel_dir = channel_directory(host)?;
+
+    let mut subdirs = BTreeSet::new();
+    for package in packages {
+        let subdir = package.subdir();
+        let destination = channel_dir.join(subdir).join(&package.filename);
+        if destination.exists() && !force {
+            miette::bail!(
+                "'{}' already exists in the channel, use `--force` to overwrite it",
+                destination.display()
+            );
+        }
+        fs_err::create_dir_all(channel_dir.join(subdir)).into_diagnostic()?;
+        fs_err::copy(&package.path, &destination).into_diagnostic()?;
+        subdirs.insert(subdir.to_string());
+        report_uploaded(package);
+    }
+
+    // A channel always needs a `noarch` subdirectory.
+    subdirs.insert("noarch".to_string());
+    for subdir in subdirs {
+        let count = channel_index::index_subdir(&channel_dir, &subdir, IndexOptions::default())?;
+        tracing::info!("indexed {count} packages in {subdir}");
+    }
+
+    Ok(())
+}
+
+/// Returns the directory of the channel from a `file://` url or a path.
+fn channel_directory(host: &str) -> miette::Result<PathBuf> {
+    match Url::parse(host) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map_err(|_| miette::miette!("'{host}' is not a valid file url")),
+        _ => Ok(Path::new(host).to_path_buf()),
+    }
+}
@@ -0,0 +1,365 @@
+mod anaconda;
+mod artifactory;
+mod filesystem;
+mod prefix;
+mod quetz;
+
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use clap::{Parser, ValueEnum};
+use futures::{Stream, TryStreamExt};
+use indicatif::HumanBytes;
+use itertools::Itertools;
+use miette::{Diagnostic, IntoDiagnostic};
+use pixi_config::Config;
+use rattler_conda_types::package::IndexJson;
+use rattler_digest::{compute_file_digest, Md5, Sha256};
+use rattler_networking::Authentication;
+use reqwest::StatusCode;
+use thiserror::Error;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+use url::Url;
+
+use crate::channel_index;
+
+#[allow(rustdoc::bare_urls)]
+/// Upload conda packages to a channel
+///
+/// With this command, you can upload one or more conda packages to a channel.
+/// Examples:
+///     pixi upload https://prefix.dev/api/v1/upload/my_channel my_package.conda
+///     pixi upload https://quetz.example.com --channel my_channel "output/**/*.conda"
+///     pixi upload file:///srv/channels/my_channel my_package.conda
+///
+/// Use `pixi auth login` to authenticate with the server.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The host + channel to upload to
+    host: String,
+
+    /// The files to upload, glob patterns are expanded
+    #[arg(required = true, num_args = 1..)]
+    package_files: Vec<String>,
+
+    /// The kind of server to upload to, inferred from the host by default
+    #[arg(long, value_enum)]
+    backend: Option<UploadBackend>,
+
+    /// The channel to upload to (quetz), or the label to upload to (anaconda)
+    #[arg(long)]
+    channel: Option<String>,
+
+    /// The owner of the packages on anaconda.org, defaults to the
+    /// authenticated user
+    #[arg(long)]
+    owner: Option<String>,
+
+    /// Overwrite packages that already exist in the channel
+    #[arg(long)]
+    force: bool,
+}
+
+/// The kind of server a package is uploaded to.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadBackend {
+    /// A prefix.dev channel, the host is the upload url of the channel
+    Prefix,
+    /// A quetz server, the channel is selected with `--channel`
+    Quetz,
+    /// An artifactory conda repository
+    Artifactory,
+    /// anaconda.org, the label is selected with `--channel`
+    Anaconda,
+    /// A channel in a directory on the local filesystem
+    File,
+}
+
+impl UploadBackend {
+    /// Infers the backend from the host if it was not specified explicitly.
+    fn infer(host: &str) -> Self {
+        match Url::parse(host) {
+            Ok(url) if url.scheme() == "file" => Self::File,
+            Ok(url) if matches!(url.scheme(), "http" | "https") => match url.host_str() {
+                Some(host) if host == "anaconda.org" || host.ends_with(".anaconda.org") => {
+                    Self::Anaconda
+                }
+                _ => Self::Prefix,
+            },
+            // Anything that is not a url (including windows paths like `C:\channel`)
+            // is a directory on the local filesystem.
+            _ => Self::File,
+        }
+    }
+}
+
+/// A package that is about to be uploaded.
+#[derive(Debug)]
+pub(crate) struct PackageFile {
+    pub path: PathBuf,
+    pub filename: String,
+    pub size: u64,
+    pub sha256: String,
+    pub md5: String,
+    pub index_json: IndexJson,
+}
+
+impl PackageFile {
+    fn from_path(path: PathBuf) -> miette::Result<Self> {
+        let Some(filename) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| channel_index::package_archive_type(name).is_some())
+            .map(ToString::to_string)
+        else {
+            miette::bail!(
+                "'{}' is not a conda package, expected a `.conda` or `.tar.bz2` file",
+                path.display()
+            );
+        };
+        let size = fs_err::metadata(&path).into_diagnostic()?.len();
+        let sha256 = format!(
+            "{:x}",
+            compute_file_digest::<Sha256>(&path).into_diagnostic()?
+        );
+        let md5 = format!("{:x}", compute_file_digest::<Md5>(&path).into_diagnostic()?);
+        let index_json = channel_index::read_index_json(&path)?;
+        Ok(Self {
+            path,
+            filename,
+            size,
+            sha256,
+            md5,
+            index_json,
+        })
+    }
+
+    /// The subdirectory of the channel the package belongs to.
+    pub fn subdir(&self) -> &str {
+        self.index_json.subdir.as_deref().unwrap_or("noarch")
+    }
+}
+
+/// Upload packages to a channel
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let backend = args
+        .backend
+        .unwrap_or_else(|| UploadBackend::infer(&args.host));
+
+    let packages = expand_package_files(&args.package_files)?
+        .into_iter()
+        .map(PackageFile::from_path)
+        .collect::<miette::Result<Vec<_>>>()?;
+
+    println!("Uploading packages to: {}", args.host);
+    for package in &packages {
+        println!(
+            "Package file:          {} ({})",
+            package.path.display(),
+            HumanBytes(package.size)
+        );
+    }
+    println!();
+
+    match backend {
+        UploadBackend::Prefix => prefix::upload(&args.host, &packages).await?,
+        UploadBackend::Quetz => {
+            let Some(channel) = args.channel.as_deref() else {
+                miette::bail!("uploading to quetz requires a channel, specify it with `--channel`");
+            };
+            quetz::upload(&args.host, channel, &packages, args.force).await?
+        }
+        UploadBackend::Artifactory => artifactory::upload(&args.host, &packages).await?,
+        UploadBackend::Anaconda => {
+            anaconda::upload(
+                &args.host,
+                args.owner.as_deref(),
+                args.channel.as_deref().unwrap_or("main"),
+                &packages,
+                args.force,
+            )
+            .await?
+        }
+        UploadBackend::File => filesystem::upload(&args.host, &packages, args.force)?,
+    }
+
+    Ok(())
+}
+
+/// Expands the glob patterns in the given package files. Arguments that refer
+/// to an existing file are used as is.
+fn expand_package_files(package_files: &[String]) -> miette::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for package_file in package_files {
+        let path = Path::new(package_file);
+        if path.is_file() {
+            paths.push(path.to_path_buf());
+            continue;
+        }
+
+        let glob = wax::Glob::new(package_file).into_diagnostic()?;
+        let (prefix, glob) = glob.partition();
+        let root = if prefix.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            prefix
+        };
+        let mut matches = glob
+            .walk(root)
+            .filter_map(|entry| match entry {
+                Ok(entry) if entry.file_type().is_dir() => None,
+                entry => Some(entry.map(|entry| entry.into_path())),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .into_diagnostic()?;
+        if matches.is_empty() {
+            miette::bail!("no package files found that match '{package_file}'");
+        }
+        matches.sort();
+        paths.extend(matches);
+    }
+    Ok(paths.into_iter().unique().collect())
+}
+
+/// Returns the credentials stored for the given url.
+pub(crate) fn stored_authentication(url: &Url) -> miette::Result<Option<Authentication>> {
+    let storage =
+        pixi_utils::reqwest::authentication_storage(&Config::load_global()).into_diagnostic()?;
+    let (_, authentication) = storage.get_by_url(url.clone()).into_diagnostic()?;
+    Ok(authentication)
+}
+
+/// Streams the package from disk and shows the progress of the upload.
+pub(crate) fn progress_stream(
+    package: &PackageFile,
+) -> miette::Result<impl Stream<Item = std::io::Result<Bytes>>> {
+    let (file, _) = fs_err::File::open(&package.path)
+        .into_diagnostic()?
+        .into_parts();
+
+    let progress_bar = indicatif::ProgressBar::new(package.size)
+        .with_prefix("Uploading")
+        .with_style(pixi_progress::default_bytes_style());
+
+    Ok(ReaderStream::new(File::from_std(file))
+        .inspect_ok(move |bytes| {
+            progress_bar.inc(bytes.len() as u64);
+        })
+        .inspect_err(|e| {
+            println!("Error while uploading: {}", e);
+        }))
+}
+
+/// Creates a request body that streams the package from disk.
+pub(crate) fn progress_body(package: &PackageFile) -> miette::Result<reqwest::Body> {
+    Ok(reqwest::Body::wrap_stream(progress_stream(package)?))
+}
+
+/// Prints that the package was uploaded.
+pub(crate) fn report_uploaded(package: &PackageFile) {
+    eprintln!(
+        "{} Uploaded {}",
+        console::style("✔").green(),
+        package.filename
+    );
+}
+
+/// Turns unsuccessful responses into an [`UploadError`].
+pub(crate) fn check_response(
+    host: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, UploadError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let host = host.to_string();
+    let source = response.error_for_status().unwrap_err(); // Capture reqwest error
+    Err(match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            UploadError::Unauthorized { host, source }
+        }
+        StatusCode::INTERNAL_SERVER_ERROR => UploadError::ServerError { host, source },
+        StatusCode::CONFLICT => UploadError::Conflict { host, source },
+        status => UploadError::UnexpectedStatus {
+            host,
+            status,
+            source,
+        },
+    })
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum UploadError {
+    #[error("Failed to send request to {host}")]
+    #[diagnostic(help("Check if the host is correct and reachable."))]
+    RequestFailed {
+        host: String,
+        #[source]
+        source: reqwest_middleware::Error,
+    },
+
+    #[error("Unauthorized request to {host}")]
+    #[diagnostic(help("Try logging in with `pixi auth login`."))]
+    Unauthorized {
+        host: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Server error at {host}")]
+    #[diagnostic(help("The server encountered an internal error. Try again later."))]
+    ServerError {
+        host: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Unexpected response from {host}: {status}")]
+    #[diagnostic(help("Unexpected status code, verify the API specification."))]
+    UnexpectedStatus {
+        host: String,
+        status: StatusCode,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Conflict: The package likely already exists in the channel: {host}")]
+    #[diagnostic(help(
+        "Try changing the package version or build number, or use `--force` to overwrite it."
+    ))]
+    Conflict {
+        host: String,
+        #[source]
+        source: reqwest::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_backend() {
+        assert_eq!(
+            UploadBackend::infer("https://prefix.dev/api/v1/upload/my_channel"),
+            UploadBackend::Prefix
+        );
+        assert_eq!(
+            UploadBackend::infer("https://anaconda.org"),
+            UploadBackend::Anaconda
+        );
+        assert_eq!(
+            UploadBackend::infer("https://api.anaconda.org"),
+            UploadBackend::Anaconda
+        );
+        assert_eq!(
+            UploadBackend::infer("file:///srv/channel"),
+            UploadBackend::File
+        );
+        assert_eq!(UploadBackend::infer("./channel"), UploadBackend::File);
+        assert_eq!(UploadBackend::infer("C:\\channel"), UploadBackend::File);
+    }
+}
@@ -0,0 +1,39 @@
+//! Uploads packages to a prefix.dev channel.
+
+use std::sync::Arc;
+
+use miette::IntoDiagnostic;
+use pixi_config::Config;
+use rattler_networking::AuthenticationMiddleware;
+
+use super::{check_response, progress_body, report_uploaded, PackageFile, UploadError};
+
+/// Uploads the packages to the upload url of a prefix.dev channel, e.g.
+/// `https://prefix.dev/api/v1/upload/my_channel`.
+pub(super) async fn upload(host: &str, packages: &[PackageFile]) -> miette::Result<()> {
+    let storage =
+        pixi_utils::reqwest::authentication_storage(&Config::load_global()).into_diagnostic()?;
+    let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+        .with_arc(Arc::new(AuthenticationMiddleware::new(storage)))
+        .build();
+
+    for package in packages {
+        let response = client
+            .post(host)
+            .header("X-File-Sha256", &package.sha256)
+            .header("X-File-Name", &package.filename)
+            .header("Content-Length", package.size)
+            .header("Content-Type", "application/octet-stream")
+            .body(progress_body(package)?)
+            .send()
+            .await
+            .map_err(|e| UploadError::RequestFailed {
+                host: host.to_string(),
+                source: e,
+            })?;
+        check_response(host, response)?;
+        report_uploaded(package);
+    }
+
+    Ok(())
+}
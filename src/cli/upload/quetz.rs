@@ -0,0 +1,59 @@
+//! Uploads packages to a channel on a quetz server.
+
+use miette::IntoDiagnostic;
+use rattler_networking::Authentication;
+use url::Url;
+
+use super::{
+    check_response, progress_body, report_uploaded, stored_authentication, PackageFile, UploadError,
+};
+
+/// Uploads the packages to `channel` on the quetz server at `host`. The api
+/// key is taken from the credentials stored for the host.
+pub(super) async fn upload(
+    host: &str,
+    channel: &str,
+    packages: &[PackageFile],
+    force: bool,
+) -> miette::Result<()> {
+    let host_url = Url::parse(host).into_diagnostic()?;
+    let api_key = match stored_authentication(&host_url)? {
+        Some(Authentication::CondaToken(token)) | Some(Authentication::BearerToken(token)) => token,
+        Some(_) => miette::bail!(
+            "quetz requires an api key, store it with `pixi auth login {host} --token <API_KEY>`"
+        ),
+        None => miette::bail!(
+            "no api key found for {host}, store it with `pixi auth login {host} --token <API_KEY>`"
+        ),
+    };
+
+    let client = reqwest::Client::new();
+    for package in packages {
+        let mut url = Url::parse(&format!(
+            "{}/api/channels/{channel}/upload/{}",
+            host.trim_end_matches('/'),
+            package.filename
+        ))
+        .into_diagnostic()?;
+        url.query_pairs_mut()
+            .append_pair("sha256", &package.sha256)
+            .append_pair("force", &force.to_string());
+
+        let response = client
+            .post(url)
+            .header("X-API-Key", &api_key)
+            .header("Content-Length", package.size)
+            .header("Content-Type", "application/octet-stream")
+            .body(progress_body(package)?)
+            .send()
+            .await
+            .map_err(|e| UploadError::RequestFailed {
+                host: host.to_string(),
+                source: e.into(),
+            })?;
+        check_response(host, response)?;
+        report_uploaded(package);
+    }
+
+    Ok(())
+}
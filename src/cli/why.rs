@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{StdoutLock, Write},
+    str::FromStr,
+};
+
+use clap::Parser;
+use console::Color;
+use fancy_display::FancyDisplay;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{IntoDiagnostic, WrapErr};
+use pep508_rs::{ExtraName, MarkerEnvironment, MarkerTree};
+use pixi_manifest::FeaturesExt;
+use pixi_uv_conversions::to_marker_environment;
+use pypi_modifiers::{pypi_marker_env::determine_marker_environment, pypi_tags::is_python_record};
+use rattler_conda_types::Platform;
+use rattler_lock::LockedPackageRef;
+use serde::Serialize;
+
+use crate::{
+    cli::{
+        cli_config::{PrefixUpdateConfig, ProjectConfig},
+        tree::UTF8_SYMBOLS,
+    },
+    lock_file::UpdateLockFileOptions,
+    project::{Environment, Project},
+};
+
+/// The maximum number of paths that are collected per environment and
+/// platform.
+const MAX_PATHS: usize = 1000;
+
+/// Explain why a package is installed
+///
+/// Prints every path from a dependency in the manifest to the package, for
+/// all environments and platforms in the lock file. Every package in a path
+/// is annotated with the matchspec or requirement that pulled it in.
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The name of the conda or PyPI package
+    pub package: String,
+
+    /// The environment to explain the package for. Defaults to all
+    /// environments.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    /// The platform to explain the package for. Defaults to all platforms.
+    #[arg(short, long)]
+    pub platform: Option<Platform>,
+
+    /// Output the paths in json format
+    #[arg(long)]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PackageSource {
+    Conda,
+    Pypi,
+}
+
+/// A package in a path from a dependency in the manifest to the package that
+/// is explained.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+struct Step {
+    name: String,
+    version: String,
+    source: PackageSource,
+    /// The matchspec or requirement that pulled in the package. For the first
+    /// package of a path this is the spec from the manifest.
+    requirement: String,
+}
+
+/// The paths to the package in an environment, the platforms are grouped if
+/// the paths are the same.
+#[derive(Debug, Serialize)]
+struct Explanation {
+    environment: String,
+    #[serde(skip)]
+    environment_display: String,
+    platforms: Vec<Platform>,
+    paths: Vec<Vec<Step>>,
+}
+
+type NodeId = (PackageSource, String);
+
+/// An edge of the graph, or a dependency in the manifest.
+struct Dependency {
+    id: NodeId,
+    /// The matchspec or requirement.
+    requirement: String,
+    /// The extras that are requested of a PyPI package.
+    extras: Vec<ExtraName>,
+    /// The marker of a PyPI requirement, it is evaluated with the extras that
+    /// are requested of the package that has the requirement.
+    marker: MarkerTree,
+}
+
+struct Node {
+    version: String,
+    /// The packages this package depends on.
+    dependencies: Vec<Dependency>,
+}
+
+/// The dependency graph of the locked packages of an environment.
+struct Graph {
+    nodes: HashMap<NodeId, Node>,
+    /// The marker environment of the platform, `None` if the environment does
+    /// not contain python.
+    marker_environment: Option<MarkerEnvironment>,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())
+        .wrap_err("Failed to load project")?;
+
+    let environments = match &args.environment {
+        Some(name) => vec![project
+            .environment(name.as_str())
+            .ok_or_else(|| miette::miette!("unknown environment '{name}'"))?],
+        None => project.environments(),
+    };
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            max_concurrent_solves: project.config().max_concurrent_solves(),
+        })
+        .await
+        .wrap_err("Failed to update lock file")?;
+
+    let mut explanations = Vec::new();
+    for environment in environments {
+        let Some(locked_environment) = lock_file.lock_file.environment(environment.name().as_str())
+        else {
+            continue;
+        };
+
+        // Group the platforms that have the same paths.
+        let mut groups: Vec<(Vec<Vec<Step>>, Vec<Platform>)> = Vec::new();
+        let platforms = match args.platform {
+            Some(platform) => vec![platform],
+            None => environment
+                .platforms()
+                .into_iter()
+                .sorted_by_key(|platform| platform.as_str())
+                .collect(),
+        };
+        for platform in platforms {
+            let Some(packages) = locked_environment.packages(platform) else {
+                continue;
+            };
+            let graph = Graph::from_packages(packages, platform);
+            let paths = graph.find_paths(&roots(&environment, platform, &graph), &args.package);
+            if paths.is_empty() {
+                continue;
+            }
+            match groups.iter_mut().find(|(group, _)| *group == paths) {
+                Some((_, platforms)) => platforms.push(platform),
+                None => groups.push((paths, vec![platform])),
+            }
+        }
+
+        explanations.extend(groups.into_iter().map(|(paths, platforms)| Explanation {
+            environment: environment.name().as_str().to_string(),
+            environment_display: environment.name().fancy_display().to_string(),
+            platforms,
+            paths,
+        }));
+    }
+
+    if explanations.is_empty() {
+        miette::bail!(
+            "'{}' is not a dependency of {}",
+            args.package,
+            match &args.environment {
+                Some(environment) => format!("the '{environment}' environment"),
+                None => String::from("any environment"),
+            }
+        );
+    }
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&explanations).into_diagnostic()?;
+        println!("{json}");
+    } else {
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        for (index, explanation) in explanations.iter().enumerate() {
+            print_explanation(&mut handle, explanation, index == 0)?;
+        }
+    }
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Returns the packages of the manifest together with their spec.
+fn roots(environment: &Environment<'_>, platform: Platform, graph: &Graph) -> Vec<Dependency> {
+    let mut roots = Vec::new();
+    for (name, spec) in environment
+        .combined_dependencies(Some(platform))
+        .into_specs()
+    {
+        roots.push(Dependency {
+            id: (PackageSource::Conda, name.as_normalized().to_string()),
+            requirement: toml_value_to_string(spec.to_toml_value()),
+            extras: Vec::new(),
+            marker: MarkerTree::default(),
+        });
+    }
+    for (name, requirement) in environment.pypi_dependencies(Some(platform)).into_specs() {
+        let name = name.as_normalized().to_string();
+        roots.push(Dependency {
+            id: graph.resolve(PackageSource::Pypi, name),
+            extras: requirement.extras().to_vec(),
+            requirement: toml_value_to_string(requirement.into()),
+            marker: MarkerTree::default(),
+        });
+    }
+    roots
+}
+
+fn toml_value_to_string(value: toml_edit::Value) -> String {
+    match value.as_str() {
+        Some(value) => value.to_string(),
+        None => value.to_string().trim().to_string(),
+    }
+}
+
+impl Graph {
+    fn from_packages<'a>(
+        packages: impl IntoIterator<Item = LockedPackageRef<'a>>,
+        platform: Platform,
+    ) -> Self {
+        // Collect the dependencies by name first, the package a dependency
+        // refers to is only known when all packages are collected.
+        let mut unresolved = Vec::new();
+        let mut marker_environment = None;
+        for package in packages {
+            match package {
+                LockedPackageRef::Conda(conda) => {
+                    let record = conda.record();
+                    if is_python_record(record) {
+                        marker_environment = determine_marker_environment(platform, record)
+                            .ok()
+                            .and_then(|environment| to_marker_environment(&environment).ok());
+                    }
+                    let dependencies = record
+                        .depends
+                        .iter()
+                        .map(|spec| {
+                            let name = spec
+                                .split([' ', '=', '<', '>', '!', '['])
+                                .next()
+                                .unwrap_or(spec)
+                                .to_lowercase();
+                            Dependency {
+                                id: (PackageSource::Conda, name),
+                                requirement: spec.clone(),
+                                extras: Vec::new(),
+                                marker: MarkerTree::default(),
+                            }
+                        })
+                        .collect_vec();
+                    unresolved.push((
+                        (
+                            PackageSource::Conda,
+                            record.name.as_normalized().to_string(),
+                        ),
+                        record.version.to_string(),
+                        dependencies,
+                    ));
+                }
+                LockedPackageRef::Pypi(pypi, _) => {
+                    let dependencies = pypi
+                        .requires_dist
+                        .iter()
+                        .map(|requirement| Dependency {
+                            id: (PackageSource::Pypi, requirement.name.as_ref().to_string()),
+                            requirement: requirement.to_string(),
+                            extras: requirement.extras.clone(),
+                            marker: requirement.marker.clone(),
+                        })
+                        .collect_vec();
+                    unresolved.push((
+                        (PackageSource::Pypi, pypi.name.as_ref().to_string()),
+                        pypi.version.to_string(),
+                        dependencies,
+                    ));
+                }
+            }
+        }
+
+        let mut graph = Graph {
+            nodes: unresolved
+                .iter()
+                .map(|(id, version, _)| {
+                    (
+                        id.clone(),
+                        Node {
+                            version: version.clone(),
+                            dependencies: Vec::new(),
+                        },
+                    )
+                })
+                .collect(),
+            marker_environment,
+        };
+        for (id, _, dependencies) in unresolved {
+            let dependencies = dependencies
+                .into_iter()
+                .map(|dependency| Dependency {
+                    id: graph.resolve(dependency.id.0, dependency.id.1),
+                    ..dependency
+                })
+                // Virtual packages are not part of the lock file.
+                .filter(|dependency| graph.nodes.contains_key(&dependency.id))
+                .collect();
+            if let Some(node) = graph.nodes.get_mut(&id) {
+                node.dependencies = dependencies;
+            }
+        }
+        graph
+    }
+
+    /// Returns the node of a package. PyPI dependencies can also be satisfied
+    /// by a conda package.
+    fn resolve(&self, source: PackageSource, name: String) -> NodeId {
+        if source == PackageSource::Pypi
+            && !self
+                .nodes
+                .contains_key(&(PackageSource::Pypi, name.clone()))
+        {
+            return (PackageSource::Conda, name);
+        }
+        (source, name)
+    }
+
+    /// Returns whether the node is the package that is explained.
+    fn is_target((source, name): &NodeId, package: &str) -> bool {
+        match source {
+            PackageSource::Conda => name.eq_ignore_ascii_case(package),
+            PackageSource::Pypi => pep508_rs::PackageName::from_str(package)
+                .is_ok_and(|package| package.as_ref() == name.as_str()),
+        }
+    }
+
+    /// Returns whether a dependency of a package applies to the platform when
+    /// the given extras of the package are requested.
+    fn applies(&self, dependency: &Dependency, extras: &[ExtraName]) -> bool {
+        match &self.marker_environment {
+            Some(marker_environment) => dependency.marker.evaluate(marker_environment, extras),
+            None => dependency.marker.is_true(),
+        }
+    }
+
+    /// Finds all paths from the roots to the package.
+    fn find_paths(&self, roots: &[Dependency], package: &str) -> Vec<Vec<Step>> {
+        // Only follow the packages from which the package can be reached.
+        let mut reverse: HashMap<&NodeId, Vec<&NodeId>> = HashMap::new();
+        for (id, node) in &self.nodes {
+            for dependency in &node.dependencies {
+                reverse.entry(&dependency.id).or_default().push(id);
+            }
+        }
+        let targets: HashSet<&NodeId> = self
+            .nodes
+            .keys()
+            .filter(|id| Self::is_target(id, package))
+            .collect();
+        let mut reachable = targets.clone();
+        let mut queue = targets.iter().copied().collect::<VecDeque<_>>();
+        while let Some(id) = queue.pop_front() {
+            for &dependent in reverse.get(id).into_iter().flatten() {
+                if reachable.insert(dependent) {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        let mut search = PathSearch {
+            graph: self,
+            targets: &targets,
+            reachable: &reachable,
+            stack: Vec::new(),
+            on_stack: HashSet::new(),
+            paths: &mut paths,
+        };
+        for root in roots {
+            if reachable.contains(&root.id) {
+                search.visit(root);
+            }
+        }
+        if paths.len() >= MAX_PATHS {
+            tracing::warn!("only the first {MAX_PATHS} paths to '{package}' are shown");
+        }
+        paths
+    }
+}
+
+struct PathSearch<'a> {
+    graph: &'a Graph,
+    targets: &'a HashSet<&'a NodeId>,
+    reachable: &'a HashSet<&'a NodeId>,
+    stack: Vec<Step>,
+    on_stack: HashSet<&'a NodeId>,
+    paths: &'a mut Vec<Vec<Step>>,
+}
+
+impl<'a> PathSearch<'a> {
+    fn visit(&mut self, dependency: &'a Dependency) {
+        if self.paths.len() >= MAX_PATHS {
+            return;
+        }
+        let graph = self.graph;
+        let id = &dependency.id;
+        let Some(node) = graph.nodes.get(id) else {
+            return;
+        };
+
+        self.stack.push(Step {
+            name: id.1.clone(),
+            version: node.version.clone(),
+            source: id.0,
+            requirement: dependency.requirement.clone(),
+        });
+        self.on_stack.insert(id);
+
+        if self.targets.contains(id) {
+            self.paths.push(self.stack.clone());
+        } else {
+            for next in &node.dependencies {
+                // Skip cycles and requirements whose marker does not match the
+                // platform or the extras that are requested of this package.
+                if self.reachable.contains(&next.id)
+                    && !self.on_stack.contains(&next.id)
+                    && graph.applies(next, &dependency.extras)
+                {
+                    self.visit(next);
+                }
+            }
+        }
+
+        self.on_stack.remove(id);
+        self.stack.pop();
+    }
+}
+
+/// A tree of paths, paths with the same start share their nodes.
+#[derive(Default)]
+struct PathTree<'a> {
+    children: IndexMap<&'a Step, PathTree<'a>>,
+}
+
+fn print_explanation(
+    handle: &mut StdoutLock,
+    explanation: &Explanation,
+    first: bool,
+) -> miette::Result<()> {
+    if !first {
+        write_line(handle, String::new())?;
+    }
+    write_line(
+        handle,
+        format!(
+            "Environment: {}, platforms: {}",
+            explanation.environment_display,
+            explanation.platforms.iter().join(", ")
+        ),
+    )?;
+
+    let mut tree = PathTree::default();
+    for path in &explanation.paths {
+        let mut node = &mut tree;
+        for step in path {
+            node = node.children.entry(step).or_default();
+        }
+    }
+    print_tree(handle, &tree, "", true)
+}
+
+fn print_tree(
+    handle: &mut StdoutLock,
+    tree: &PathTree<'_>,
+    prefix: &str,
+    root: bool,
+) -> miette::Result<()> {
+    let count = tree.children.len();
+    for (index, (step, children)) in tree.children.iter().enumerate() {
+        let last = index == count - 1;
+        let symbol = if last {
+            UTF8_SYMBOLS.ell
+        } else {
+            UTF8_SYMBOLS.tee
+        };
+        write_line(
+            handle,
+            format!(
+                "{prefix}{symbol} {} {} {}",
+                if root {
+                    console::style(&step.name).fg(Color::Green).bold()
+                } else {
+                    console::style(&step.name)
+                },
+                match step.source {
+                    PackageSource::Conda => console::style(&step.version).fg(Color::Yellow),
+                    PackageSource::Pypi => console::style(&step.version).fg(Color::Blue),
+                },
+                console::style(format!("({})", step.requirement)).dim()
+            ),
+        )?;
+
+        let new_prefix = if last {
+            format!("{prefix}{} ", UTF8_SYMBOLS.empty)
+        } else {
+            format!("{prefix}{} ", UTF8_SYMBOLS.down)
+        };
+        print_tree(handle, children, &new_prefix, false)?;
+    }
+    Ok(())
+}
+
+fn write_line(handle: &mut StdoutLock, line: String) -> miette::Result<()> {
+    writeln!(handle, "{line}")
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::BrokenPipe {
+                // Exit gracefully
+                std::process::exit(0);
+            } else {
+                e
+            }
+        })
+        .into_diagnostic()
+        .wrap_err("Failed to write the dependency paths")
+}
+
+#[cfg(test)]
+mod tests {
+    use rattler_conda_types::{PackageRecord, VersionWithSource};
+
+    use super::*;
+
+    fn conda(name: &str) -> NodeId {
+        (PackageSource::Conda, name.to_string())
+    }
+
+    fn pypi(name: &str) -> NodeId {
+        (PackageSource::Pypi, name.to_string())
+    }
+
+    fn dependency(id: NodeId, requirement: &str) -> Dependency {
+        let (extras, marker) = match id.0 {
+            PackageSource::Conda => (Vec::new(), MarkerTree::default()),
+            PackageSource::Pypi => {
+                let requirement = pep508_rs::Requirement::from_str(requirement).unwrap();
+                (requirement.extras, requirement.marker)
+            }
+        };
+        Dependency {
+            id,
+            requirement: requirement.to_string(),
+            extras,
+            marker,
+        }
+    }
+
+    fn graph(edges: &[(&str, &[&str])]) -> Graph {
+        Graph {
+            nodes: edges
+                .iter()
+                .map(|(name, dependencies)| {
+                    (
+                        conda(name),
+                        Node {
+                            version: String::from("1.0"),
+                            dependencies: dependencies
+                                .iter()
+                                .map(|name| dependency(conda(name), &format!("{name} >=1")))
+                                .collect(),
+                        },
+                    )
+                })
+                .collect(),
+            marker_environment: None,
+        }
+    }
+
+    fn format_paths(paths: Vec<Vec<Step>>) -> Vec<String> {
+        paths
+            .into_iter()
+            .map(|path| {
+                path.into_iter()
+                    .map(|step| format!("{} ({})", step.name, step.requirement))
+                    .join(" -> ")
+            })
+            .sorted()
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_find_paths() {
+        let graph = graph(&[
+            ("app", &["lib-a", "lib-b"]),
+            ("lib-a", &["zlib", "lib-b"]),
+            ("lib-b", &["zlib", "app"]),
+            ("tool", &["python"]),
+            ("python", &[]),
+            ("zlib", &[]),
+        ]);
+        let roots = vec![
+            dependency(conda("app"), "*"),
+            dependency(conda("tool"), ">=2"),
+        ];
+
+        assert_eq!(
+            format_paths(graph.find_paths(&roots, "ZLIB")),
+            vec![
+                "app (*) -> lib-a (lib-a >=1) -> lib-b (lib-b >=1) -> zlib (zlib >=1)",
+                "app (*) -> lib-a (lib-a >=1) -> zlib (zlib >=1)",
+                "app (*) -> lib-b (lib-b >=1) -> zlib (zlib >=1)",
+            ]
+        );
+
+        assert!(graph.find_paths(&roots, "openssl").is_empty());
+    }
+
+    #[test]
+    fn test_find_paths_evaluates_markers() {
+        let python = PackageRecord::new(
+            "python".parse().unwrap(),
+            VersionWithSource::from_str("3.12.0").unwrap(),
+            "build_string".to_string(),
+        );
+        let marker_environment = to_marker_environment(
+            &determine_marker_environment(Platform::Linux64, &python).unwrap(),
+        )
+        .unwrap();
+
+        let node = |dependencies: Vec<Dependency>| Node {
+            version: String::from("1.0"),
+            dependencies,
+        };
+        let graph = Graph {
+            nodes: HashMap::from([
+                (
+                    pypi("requests"),
+                    node(vec![
+                        dependency(pypi("urllib3"), "urllib3>=1.21.1"),
+                        dependency(pypi("pysocks"), "pysocks>=1.5.6 ; extra == 'socks'"),
+                        dependency(
+                            pypi("win-inet-pton"),
+                            "win-inet-pton ; sys_platform == 'win32' and extra == 'socks'",
+                        ),
+                    ]),
+                ),
+                (pypi("urllib3"), node(Vec::new())),
+                (pypi("pysocks"), node(Vec::new())),
+                (pypi("win-inet-pton"), node(Vec::new())),
+            ]),
+            marker_environment: Some(marker_environment),
+        };
+
+        // The extra is not requested.
+        let roots = vec![dependency(pypi("requests"), "requests")];
+        assert!(graph.find_paths(&roots, "pysocks").is_empty());
+        assert_eq!(graph.find_paths(&roots, "urllib3").len(), 1);
+
+        // The extra is requested by the manifest.
+        let roots = vec![dependency(pypi("requests"), "requests[socks]")];
+        assert_eq!(
+            format_paths(graph.find_paths(&roots, "PySocks")),
+            vec!["requests (requests[socks]) -> pysocks (pysocks>=1.5.6 ; extra == 'socks')"]
+        );
+
+        // The platform marker does not match linux.
+        assert!(graph.find_paths(&roots, "win-inet-pton").is_empty());
+    }
+}
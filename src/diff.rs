@@ -31,6 +31,57 @@ impl PackagesDiff {
     }
 }
 
+/// The kind of change of a single package between two lock-files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    /// The package was added.
+    Added,
+    /// The package was removed.
+    Removed,
+    /// The package was changed to a higher version.
+    Upgraded,
+    /// The package was changed to a lower version.
+    Downgraded,
+    /// The version of the package stayed the same but something else changed,
+    /// e.g. the build string or the location of the package.
+    Changed,
+}
+
+impl ChangeKind {
+    /// Determines how a package changed from `previous` to `current`.
+    pub(crate) fn from_packages(previous: &LockedPackage, current: &LockedPackage) -> Self {
+        let ordering = match (previous, current) {
+            (LockedPackage::Conda(previous), LockedPackage::Conda(current)) => previous
+                .record()
+                .version
+                .version()
+                .cmp(current.record().version.version()),
+            (LockedPackage::Pypi(previous, _), LockedPackage::Pypi(current, _)) => {
+                previous.version.cmp(&current.version)
+            }
+            _ => std::cmp::Ordering::Equal,
+        };
+        match ordering {
+            std::cmp::Ordering::Less => Self::Upgraded,
+            std::cmp::Ordering::Greater => Self::Downgraded,
+            std::cmp::Ordering::Equal => Self::Changed,
+        }
+    }
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Upgraded => "upgraded",
+            Self::Downgraded => "downgraded",
+            Self::Changed => "changed",
+        })
+    }
+}
+
 /// Contains the changes between two lock-files.
 pub struct LockFileDiff {
     pub environment: IndexMap<String, IndexMap<Platform, PackagesDiff>>,
@@ -247,6 +298,69 @@ impl LockFileDiff {
         Ok(())
     }
 
+    /// Formats the lock-file diff as markdown, with a table of the changed
+    /// packages for every environment and platform.
+    pub(crate) fn to_markdown(&self) -> String {
+        fn format_version(package: &LockedPackage) -> String {
+            match package {
+                LockedPackage::Conda(p) => {
+                    format!("{} {}", p.record().version.as_str(), p.record().build)
+                }
+                LockedPackage::Pypi(p, _) => p.version.to_string(),
+            }
+        }
+
+        fn format_type(package: &LockedPackage) -> &'static str {
+            match package {
+                LockedPackage::Conda(_) => "conda",
+                LockedPackage::Pypi(..) => "pypi",
+            }
+        }
+
+        let mut markdown = String::new();
+        for (environment_name, environment) in
+            self.environment.iter().sorted_by(|(a, _), (b, _)| a.cmp(b))
+        {
+            for (platform, packages) in environment.iter().sorted_by_key(|(p, _)| p.as_str()) {
+                markdown.push_str(&format!(
+                    "### Environment `{environment_name}` on `{platform}`\n\n"
+                ));
+                markdown.push_str("| Package | Type | Change | Before | After |\n");
+                markdown.push_str("|---|---|---|---|---|\n");
+
+                let rows = itertools::chain!(
+                    packages
+                        .added
+                        .iter()
+                        .map(|p| (p, ChangeKind::Added, None, Some(p))),
+                    packages
+                        .removed
+                        .iter()
+                        .map(|p| (p, ChangeKind::Removed, Some(p), None)),
+                    packages.changed.iter().map(|(previous, current)| (
+                        previous,
+                        ChangeKind::from_packages(previous, current),
+                        Some(previous),
+                        Some(current)
+                    )),
+                )
+                .sorted_by_key(|(p, ..)| p.name());
+                for (package, kind, before, after) in rows {
+                    markdown.push_str(&format!(
+                        "| {} | {} | {} | {} | {} |\n",
+                        package.name(),
+                        format_type(package),
+                        kind,
+                        before.map(format_version).unwrap_or_default(),
+                        after.map(format_version).unwrap_or_default(),
+                    ));
+                }
+                markdown.push('\n');
+            }
+        }
+        markdown
+    }
+
     fn format_changes(packages: &PackagesDiff) -> Vec<(&str, String)> {
         enum Change<'i> {
             Added(&'i LockedPackage),
@@ -359,6 +473,7 @@ pub struct JsonPackageDiff {
     after: Option<serde_json::Value>,
     #[serde(rename = "type")]
     ty: JsonPackageType,
+    change: ChangeKind,
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     explicit: bool,
 }
@@ -400,6 +515,7 @@ impl LockFileJsonDiff {
                         before: None,
                         after: Some(serde_json::to_value(&pkg).unwrap()),
                         ty: JsonPackageType::Conda,
+                        change: ChangeKind::Added,
                         explicit: conda_dependencies.contains_key(&pkg.record().name),
                     },
                     LockedPackage::Pypi(pkg, _) => JsonPackageDiff {
@@ -407,6 +523,7 @@ impl LockFileJsonDiff {
                         before: None,
                         after: Some(serde_json::to_value(&pkg).unwrap()),
                         ty: JsonPackageType::Pypi,
+                        change: ChangeKind::Added,
                         explicit: pypi_dependencies.contains_key(&pkg.name),
                     },
                 });
@@ -417,6 +534,7 @@ impl LockFileJsonDiff {
                         before: Some(serde_json::to_value(&pkg).unwrap()),
                         after: None,
                         ty: JsonPackageType::Conda,
+                        change: ChangeKind::Removed,
                         explicit: conda_dependencies.contains_key(&pkg.record().name),
                     },
 
@@ -425,13 +543,15 @@ impl LockFileJsonDiff {
                         before: Some(serde_json::to_value(&pkg).unwrap()),
                         after: None,
                         ty: JsonPackageType::Pypi,
+                        change: ChangeKind::Removed,
                         explicit: pypi_dependencies.contains_key(&pkg.name),
                     },
                 });
 
-                let changed_diffs = packages_diff.changed.into_iter().map(|(old, new)| match (old, new) {
-                    (LockedPackage::Conda(old), LockedPackage::Conda(new)) =>
-                        {
+                let changed_diffs = packages_diff.changed.into_iter().map(|(old, new)| {
+                    let change = ChangeKind::from_packages(&old, &new);
+                    match (old, new) {
+                        (LockedPackage::Conda(old), LockedPackage::Conda(new)) => {
                             let before = serde_json::to_value(&old).unwrap();
                             let after = serde_json::to_value(&new).unwrap();
                             let (before, after) = compute_json_diff(before, after);
@@ -440,22 +560,27 @@ impl LockFileJsonDiff {
                                 before: Some(before),
                                 after: Some(after),
                                 ty: JsonPackageType::Conda,
+                                change,
                                 explicit: conda_dependencies.contains_key(&old.record().name),
                             }
                         }
-                    (LockedPackage::Pypi(old, _), LockedPackage::Pypi(new, _)) => {
-                        let before = serde_json::to_value(&old).unwrap();
-                        let after = serde_json::to_value(&new).unwrap();
-                        let (before, after) = compute_json_diff(before, after);
-                        JsonPackageDiff {
-                            name: old.name.as_dist_info_name().into_owned(),
-                            before: Some(before),
-                            after: Some(after),
-                            ty: JsonPackageType::Pypi,
-                            explicit: pypi_dependencies.contains_key(&old.name),
+                        (LockedPackage::Pypi(old, _), LockedPackage::Pypi(new, _)) => {
+                            let before = serde_json::to_value(&old).unwrap();
+                            let after = serde_json::to_value(&new).unwrap();
+                            let (before, after) = compute_json_diff(before, after);
+                            JsonPackageDiff {
+                                name: old.name.as_dist_info_name().into_owned(),
+                                before: Some(before),
+                                after: Some(after),
+                                ty: JsonPackageType::Pypi,
+                                change,
+                                explicit: pypi_dependencies.contains_key(&old.name),
+                            }
                         }
+                        _ => unreachable!(
+                            "packages cannot change type, they are represented as removals and inserts instead"
+                        ),
                     }
-                    _ => unreachable!("packages cannot change type, they are represented as removals and inserts instead"),
                 });
 
                 let packages_diff_json = add_diffs
@@ -496,3 +621,71 @@ fn compute_json_diff(
     }
     (a, b)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn lock_file(packages: &[&str]) -> LockFile {
+        let urls = packages
+            .iter()
+            .map(|p| format!("https://conda.anaconda.org/conda-forge/linux-64/{p}.conda"))
+            .collect_vec();
+        let environment_packages = urls
+            .iter()
+            .format_with("\n", |url, f| f(&format_args!("      - conda: {url}")));
+        let package_data = urls.iter().format_with("\n", |url, f| {
+            f(&format_args!(
+                "- conda: {url}\n  sha256: {}\n  md5: {}",
+                "0".repeat(64),
+                "0".repeat(32)
+            ))
+        });
+        LockFile::from_str(&format!(
+            r#"
+version: 6
+environments:
+  default:
+    channels:
+    - url: https://conda.anaconda.org/conda-forge/
+    packages:
+      linux-64:
+{environment_packages}
+packages:
+{package_data}
+"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_diff_markdown() {
+        let previous = lock_file(&[
+            "numpy-1.26.0-py311h64a7726_0",
+            "python-3.11.0-h10a6764_1_cpython",
+            "zlib-1.2.13-hd590300_5",
+            "openssl-3.3.0-h4ab18f5_0",
+        ]);
+        let current = lock_file(&[
+            "bzip2-1.0.8-hd590300_5",
+            "numpy-1.25.0-py311h64a7726_0",
+            "python-3.12.0-hab00c5b_0_cpython",
+            "openssl-3.3.0-h4ab18f5_1",
+        ]);
+
+        let diff = LockFileDiff::from_lock_files(&previous, &current);
+        assert_eq!(
+            diff.to_markdown(),
+            "### Environment `default` on `linux-64`\n\n\
+             | Package | Type | Change | Before | After |\n\
+             |---|---|---|---|---|\n\
+             | bzip2 | conda | added |  | 1.0.8 hd590300_5 |\n\
+             | numpy | conda | downgraded | 1.26.0 py311h64a7726_0 | 1.25.0 py311h64a7726_0 |\n\
+             | openssl | conda | changed | 3.3.0 h4ab18f5_0 | 3.3.0 h4ab18f5_1 |\n\
+             | python | conda | upgraded | 3.11.0 h10a6764_1_cpython | 3.12.0 hab00c5b_0_cpython |\n\
+             | zlib | conda | removed | 1.2.13 hd590300_5 |  |\n\n"
+        );
+    }
+}
@@ -57,7 +57,7 @@ use crate::{
         EnvDir,
     },
     prefix::{Executable, Prefix},
-    repodata::Repodata,
+    repodata::{with_offline_context, Repodata},
     rlimit::try_increase_rlimit_to_sensible,
 };
 
@@ -514,11 +514,20 @@ impl Project {
                 env_name.fancy_display()
             ),
             |_| async {
-                self.repodata_gateway()
-                    .query(channels, [platform, Platform::NoArch], match_specs.clone())
-                    .recursive(true)
-                    .await
-                    .into_diagnostic()
+                with_offline_context(
+                    self.repodata_gateway()
+                        .query(
+                            channels.clone(),
+                            [platform, Platform::NoArch],
+                            match_specs.clone(),
+                        )
+                        .recursive(true)
+                        .await
+                        .into_diagnostic(),
+                    self.config().offline(),
+                    &channels,
+                    platform,
+                )
             },
         )
         .await?;
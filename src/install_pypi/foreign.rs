@@ -0,0 +1,288 @@
+//! Installs wheels into a prefix for a platform other than the current one.
+//!
+//! The python interpreter of such a prefix cannot be started, so the layout of
+//! the prefix is derived from the locked python package and the wheels are
+//! checked against the tags of the target platform instead.
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use miette::{IntoDiagnostic, WrapErr};
+use pixi_consts::consts;
+use pixi_manifest::SystemRequirements;
+use pixi_record::PixiRecord;
+use pypi_modifiers::pypi_tags::{get_pypi_tags, is_python_record};
+use rattler_conda_types::{PackageRecord, Platform};
+use rattler_lock::{PypiPackageData, UrlOrPath};
+use reqwest_middleware::ClientWithMiddleware;
+use uv_distribution_filename::WheelFilename;
+use uv_install_wheel::{
+    linker::{install_wheel, LinkMode, Locks},
+    Layout,
+};
+use uv_pypi_types::Scheme;
+
+use super::{utils::strip_direct_scheme, CombinedPypiPackageData};
+use crate::{lock_file::verify_hashes, prefix::Prefix};
+
+/// Installs the wheels of the locked PyPI packages into the prefix of the
+/// given platform. Wheels that are already installed are skipped.
+pub async fn install_foreign_wheels(
+    lock_file_dir: &Path,
+    prefix: &Prefix,
+    platform: Platform,
+    system_requirements: &SystemRequirements,
+    pixi_records: &[PixiRecord],
+    python_packages: &[CombinedPypiPackageData],
+    client: &ClientWithMiddleware,
+) -> miette::Result<()> {
+    if python_packages.is_empty() {
+        return Ok(());
+    }
+
+    let python_record = pixi_records
+        .iter()
+        .find(|r| is_python_record(r))
+        .ok_or_else(|| {
+            miette::miette!("cannot install PyPI packages without a locked python package")
+        })?;
+    let tags = get_pypi_tags(
+        platform,
+        system_requirements,
+        python_record.package_record(),
+    )?;
+    let layout = foreign_layout(prefix.root(), platform, python_record.package_record())?;
+
+    let work_dir = tempfile::tempdir().into_diagnostic()?;
+    let locks = Locks::default();
+    for (package, _) in python_packages {
+        let filename = wheel_filename(package)?;
+        if !filename.is_compatible(&tags) {
+            miette::bail!(
+                "the locked wheel '{filename}' is not compatible with {}",
+                platform
+            );
+        }
+
+        let dist_info = layout.scheme.purelib.join(format!(
+            "{}-{}.dist-info",
+            filename.name.as_dist_info_name(),
+            filename.version
+        ));
+        if dist_info.is_dir() {
+            tracing::debug!("'{filename}' is already installed");
+            continue;
+        }
+
+        let wheel = wheel_path(lock_file_dir, package, &filename, client, work_dir.path()).await?;
+        let unpacked = work_dir.path().join(format!("{}-unpacked", filename.name));
+        let unpack_dir = unpacked.clone();
+        tokio::task::spawn_blocking(move || {
+            let file = fs_err::File::open(&wheel).into_diagnostic()?;
+            zip::ZipArchive::new(file)
+                .and_then(|mut archive| archive.extract(&unpack_dir))
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to unpack '{}'", wheel.display()))
+        })
+        .await
+        .into_diagnostic()??;
+
+        install_wheel(
+            &layout,
+            false,
+            &unpacked,
+            &filename,
+            None,
+            None,
+            Some(consts::PIXI_UV_INSTALLER),
+            LinkMode::Copy,
+            &locks,
+        )
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to install '{filename}'"))?;
+        fs_err::remove_dir_all(&unpacked).into_diagnostic()?;
+    }
+
+    Ok(())
+}
+
+/// Returns the layout of a conda python installation for the platform.
+fn foreign_layout(
+    prefix: &Path,
+    platform: Platform,
+    python_record: &PackageRecord,
+) -> miette::Result<Layout> {
+    let Some((major, minor)) = python_record.version.as_major_minor() else {
+        miette::bail!(
+            "failed to determine the python version from '{}'",
+            python_record.version
+        );
+    };
+    let (Ok(major), Ok(minor)) = (u8::try_from(major), u8::try_from(minor)) else {
+        miette::bail!("unsupported python version '{}'", python_record.version);
+    };
+
+    let (sys_executable, scheme, os_name) = if platform.is_windows() {
+        let site_packages = prefix.join("Lib").join("site-packages");
+        let scheme = Scheme {
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            scripts: prefix.join("Scripts"),
+            data: prefix.to_path_buf(),
+            include: prefix.join("Include"),
+        };
+        (prefix.join("python.exe"), scheme, "nt")
+    } else {
+        let python = format!("python{major}.{minor}");
+        let site_packages = prefix.join("lib").join(&python).join("site-packages");
+        let scheme = Scheme {
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            scripts: prefix.join("bin"),
+            data: prefix.to_path_buf(),
+            include: prefix.join("include").join(&python),
+        };
+        (prefix.join("bin").join(&python), scheme, "posix")
+    };
+
+    Ok(Layout {
+        sys_executable,
+        python_version: (major, minor),
+        os_name: os_name.to_string(),
+        scheme,
+    })
+}
+
+/// Returns the filename of the wheel of the package. Source distributions
+/// cannot be built for another platform.
+fn wheel_filename(package: &PypiPackageData) -> miette::Result<WheelFilename> {
+    let filename = match &package.location {
+        UrlOrPath::Url(url) => strip_direct_scheme(url)
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(|segment| {
+                percent_encoding::percent_decode_str(segment)
+                    .decode_utf8_lossy()
+                    .into_owned()
+            }),
+        UrlOrPath::Path(path) => path.file_name().map(ToString::to_string),
+    };
+    filename
+        .and_then(|filename| WheelFilename::from_str(&filename).ok())
+        .ok_or_else(|| {
+            miette::miette!(
+                "'{}' is not locked as a wheel, only wheels can be installed for another platform",
+                package.name
+            )
+        })
+}
+
+/// Returns the path of the wheel on disk, wheels from a remote location are
+/// downloaded into the work directory.
+async fn wheel_path(
+    lock_file_dir: &Path,
+    package: &PypiPackageData,
+    filename: &WheelFilename,
+    client: &ClientWithMiddleware,
+    work_dir: &Path,
+) -> miette::Result<PathBuf> {
+    let url = match &package.location {
+        UrlOrPath::Path(path) => {
+            let native_path = Path::new(path.as_str());
+            return Ok(if path.is_absolute() {
+                native_path.to_path_buf()
+            } else {
+                lock_file_dir.join(native_path)
+            });
+        }
+        UrlOrPath::Url(url) => strip_direct_scheme(url).into_owned(),
+    };
+    if url.scheme() == "file" {
+        return url
+            .to_file_path()
+            .map_err(|_| miette::miette!("'{url}' is not a valid file path"));
+    }
+
+    let path = work_dir.join(filename.to_string());
+    let bytes = client
+        .get(url.clone())
+        .send()
+        .await
+        .into_diagnostic()
+        .and_then(|response| response.error_for_status().into_diagnostic())
+        .wrap_err_with(|| format!("failed to download '{url}'"))?
+        .bytes()
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to download '{url}'"))?;
+    fs_err::tokio::write(&path, bytes).await.into_diagnostic()?;
+
+    if let Some(hashes) = &package.hash {
+        if !verify_hashes(&path, hashes).into_diagnostic()? {
+            miette::bail!("the hash of '{url}' does not match the hash in the lock file");
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use rattler_conda_types::VersionWithSource;
+
+    use super::*;
+
+    fn python_record(version: &str) -> PackageRecord {
+        PackageRecord::new(
+            "python".parse().unwrap(),
+            VersionWithSource::from_str(version).unwrap(),
+            "h123_0".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_foreign_layout() {
+        let prefix = Path::new("/opt/env");
+
+        let layout =
+            foreign_layout(prefix, Platform::LinuxAarch64, &python_record("3.12.1")).unwrap();
+        assert_eq!(layout.python_version, (3, 12));
+        assert_eq!(layout.os_name, "posix");
+        assert_eq!(layout.sys_executable, prefix.join("bin").join("python3.12"));
+        assert_eq!(
+            layout.scheme.purelib,
+            prefix.join("lib").join("python3.12").join("site-packages")
+        );
+
+        let layout = foreign_layout(prefix, Platform::Win64, &python_record("3.9.18")).unwrap();
+        assert_eq!(layout.os_name, "nt");
+        assert_eq!(layout.scheme.scripts, prefix.join("Scripts"));
+        assert_eq!(
+            layout.scheme.platlib,
+            prefix.join("Lib").join("site-packages")
+        );
+    }
+
+    #[test]
+    fn test_wheel_filename() {
+        let package = |location: &str| PypiPackageData {
+            name: "foo".parse().unwrap(),
+            version: pep440_rs::Version::from_str("1.0").unwrap(),
+            location: UrlOrPath::Url(location.parse().unwrap()),
+            hash: None,
+            requires_dist: vec![],
+            requires_python: None,
+            editable: false,
+        };
+
+        let filename = wheel_filename(&package(
+            "https://example.com/foo-1.0%2Bcpu-cp312-cp312-manylinux_2_17_aarch64.whl",
+        ))
+        .unwrap();
+        assert_eq!(filename.name.as_ref(), "foo");
+        assert_eq!(filename.version.to_string(), "1.0+cpu");
+
+        assert!(wheel_filename(&package("https://example.com/foo-1.0.tar.gz")).is_err());
+    }
+}
@@ -14,7 +14,7 @@ use rattler_conda_types::Platform;
 use rattler_lock::{PypiIndexes, PypiPackageData, PypiPackageEnvironmentData};
 use utils::elapsed;
 use uv_auth::store_credentials_from_url;
-use uv_client::{Connectivity, FlatIndexClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClientBuilder};
 use uv_configuration::{ConfigSettings, Constraints, IndexStrategy, LowerBound};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::{DistributionDatabase, RegistryWheelIndex};
@@ -36,6 +36,7 @@ use plan::{InstallPlanner, InstallReason, NeedReinstall, PixiInstallPlan};
 
 pub(crate) mod conda_pypi_clobber;
 pub(crate) mod conversions;
+pub(crate) mod foreign;
 pub(crate) mod install_wheel;
 pub(crate) mod plan;
 pub(crate) mod utils;
@@ -82,7 +83,7 @@ pub async fn update_python_distributions(
             .allow_insecure_host(uv_context.allow_insecure_host.clone())
             .index_urls(index_locations.index_urls())
             .keyring(uv_context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(uv_context.connectivity)
             .build(),
     );
 
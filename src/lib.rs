@@ -1,4 +1,5 @@
 pub mod activation;
+mod channel_index;
 pub mod cli;
 mod diff;
 pub mod environment;
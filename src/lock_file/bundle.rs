@@ -0,0 +1,237 @@
+//! A bundle is a directory with all the artifacts that are referenced by a
+//! lock-file. It is created with `pixi lock fetch` and can be installed from
+//! without network access with `pixi install --from-bundle`.
+//!
+//! The directory contains a `bundle.json` that maps the urls from the
+//! lock-file to the files in the bundle.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use miette::{Context, IntoDiagnostic};
+use pixi_record::PixiRecord;
+use rattler_conda_types::PackageRecord;
+use rattler_digest::{compute_file_digest, Md5, Sha256};
+use rattler_lock::{PackageHashes, PypiPackageData, UrlOrPath};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The name of the file that describes the contents of a bundle.
+pub const BUNDLE_INDEX_FILE: &str = "bundle.json";
+
+/// The version of the bundle format.
+const BUNDLE_VERSION: u32 = 1;
+
+/// The contents of the `bundle.json` of a bundle. The paths are relative to
+/// the bundle directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BundleIndex {
+    version: u32,
+    #[serde(default)]
+    conda: BTreeMap<Url, String>,
+    #[serde(default)]
+    pypi: BTreeMap<Url, String>,
+}
+
+/// A directory that contains the artifacts of a lock-file.
+#[derive(Debug)]
+pub struct ArtifactBundle {
+    root: PathBuf,
+    index: BundleIndex,
+}
+
+impl ArtifactBundle {
+    /// Creates a new empty bundle in the given directory.
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            index: BundleIndex {
+                version: BUNDLE_VERSION,
+                ..BundleIndex::default()
+            },
+        }
+    }
+
+    /// Reads the bundle in the given directory.
+    pub fn from_path(root: &Path) -> miette::Result<Self> {
+        let index_path = root.join(BUNDLE_INDEX_FILE);
+        let contents = fs_err::read_to_string(&index_path)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!(
+                    "'{}' is not a bundle, create one with `pixi lock fetch`",
+                    root.display()
+                )
+            })?;
+        let index: BundleIndex = serde_json::from_str(&contents)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse '{}'", index_path.display()))?;
+        if index.version != BUNDLE_VERSION {
+            miette::bail!(
+                "'{}' has version {}, but only version {BUNDLE_VERSION} is supported",
+                index_path.display(),
+                index.version
+            );
+        }
+        Ok(Self {
+            root: dunce::canonicalize(root).into_diagnostic()?,
+            index,
+        })
+    }
+
+    /// Writes the `bundle.json` of the bundle.
+    pub fn write(&self) -> miette::Result<()> {
+        let contents = serde_json::to_string_pretty(&self.index).into_diagnostic()?;
+        fs_err::write(self.root.join(BUNDLE_INDEX_FILE), contents).into_diagnostic()
+    }
+
+    /// Adds a conda package to the bundle and returns the path it should be
+    /// stored at. Packages are stored per subdir, packages with the same
+    /// filename from different channels get a directory of their own.
+    pub fn insert_conda(&mut self, url: &Url, record: &PackageRecord, file_name: &str) -> PathBuf {
+        let relative = insert_unique(
+            &mut self.index.conda,
+            url,
+            &format!("conda/{}", record.subdir),
+            file_name,
+        );
+        self.root.join(relative)
+    }
+
+    /// Adds a PyPI artifact to the bundle and returns the path it should be
+    /// stored at.
+    pub fn insert_pypi(&mut self, url: &Url, filename: &str) -> PathBuf {
+        let relative = insert_unique(&mut self.index.pypi, url, "pypi", filename);
+        self.root.join(relative)
+    }
+
+    /// Points the url of a conda record to the file in the bundle.
+    pub fn localize_conda(&self, record: PixiRecord) -> PixiRecord {
+        match record {
+            PixiRecord::Binary(mut record) => {
+                if let Some(url) = self.file_url(&self.index.conda, &record.url) {
+                    record.url = url;
+                }
+                PixiRecord::Binary(record)
+            }
+            record => record,
+        }
+    }
+
+    /// Points the location of a PyPI package to the file in the bundle.
+    pub fn localize_pypi(&self, mut package: PypiPackageData) -> PypiPackageData {
+        if let UrlOrPath::Url(url) = &package.location {
+            if let Some(url) = self.file_url(&self.index.pypi, url) {
+                package.location = UrlOrPath::Url(url);
+            }
+        }
+        package
+    }
+
+    fn file_url(&self, entries: &BTreeMap<Url, String>, url: &Url) -> Option<Url> {
+        let relative = entries.get(url)?;
+        Url::from_file_path(self.root.join(relative)).ok()
+    }
+}
+
+/// Inserts the url with a path in `dir` that is not yet used by another url.
+fn insert_unique(
+    entries: &mut BTreeMap<Url, String>,
+    url: &Url,
+    dir: &str,
+    filename: &str,
+) -> String {
+    if let Some(relative) = entries.get(url) {
+        return relative.clone();
+    }
+    let relative = std::iter::once(format!("{dir}/{filename}"))
+        .chain((1..).map(|n| format!("{dir}/{n}/{filename}")))
+        .find(|candidate| !entries.values().any(|existing| existing == candidate))
+        .expect("there is always an unused path");
+    entries.insert(url.clone(), relative.clone());
+    relative
+}
+
+/// Returns the hashes of a conda package.
+pub fn conda_hashes(record: &PackageRecord) -> Option<PackageHashes> {
+    match (record.md5, record.sha256) {
+        (Some(md5), Some(sha256)) => Some(PackageHashes::Md5Sha256(md5, sha256)),
+        (Some(md5), None) => Some(PackageHashes::Md5(md5)),
+        (None, Some(sha256)) => Some(PackageHashes::Sha256(sha256)),
+        (None, None) => None,
+    }
+}
+
+/// Returns `true` if the file matches the hashes, the sha256 hash is used if
+/// it is available.
+pub fn verify_hashes(path: &Path, hashes: &PackageHashes) -> std::io::Result<bool> {
+    match hashes {
+        PackageHashes::Sha256(sha256) | PackageHashes::Md5Sha256(_, sha256) => {
+            Ok(compute_file_digest::<Sha256>(path)? == *sha256)
+        }
+        PackageHashes::Md5(md5) => Ok(compute_file_digest::<Md5>(path)? == *md5),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_unique() {
+        let mut entries = BTreeMap::new();
+        let conda_forge =
+            Url::parse("https://conda.anaconda.org/conda-forge/noarch/foo-1.0-0.conda").unwrap();
+        let other = Url::parse("https://example.com/channel/noarch/foo-1.0-0.conda").unwrap();
+
+        assert_eq!(
+            insert_unique(
+                &mut entries,
+                &conda_forge,
+                "conda/noarch",
+                "foo-1.0-0.conda"
+            ),
+            "conda/noarch/foo-1.0-0.conda"
+        );
+        assert_eq!(
+            insert_unique(&mut entries, &other, "conda/noarch", "foo-1.0-0.conda"),
+            "conda/noarch/1/foo-1.0-0.conda"
+        );
+        assert_eq!(
+            insert_unique(
+                &mut entries,
+                &conda_forge,
+                "conda/noarch",
+                "foo-1.0-0.conda"
+            ),
+            "conda/noarch/foo-1.0-0.conda"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let url =
+            Url::parse("https://files.pythonhosted.org/packages/foo-1.0-py3-none-any.whl").unwrap();
+
+        let mut bundle = ArtifactBundle::new(dir.path().to_path_buf());
+        let path = bundle.insert_pypi(&url, "foo-1.0-py3-none-any.whl");
+        fs_err::create_dir_all(path.parent().unwrap()).unwrap();
+        fs_err::write(&path, "wheel").unwrap();
+        bundle.write().unwrap();
+
+        let bundle = ArtifactBundle::from_path(dir.path()).unwrap();
+        let file_url = bundle.file_url(&bundle.index.pypi, &url).unwrap();
+        assert_eq!(
+            file_url.to_file_path().unwrap(),
+            dunce::canonicalize(&path).unwrap()
+        );
+
+        let sha256 = compute_file_digest::<Sha256>(&path).unwrap();
+        assert!(verify_hashes(&path, &PackageHashes::Sha256(sha256)).unwrap());
+        let md5 = compute_file_digest::<Md5>(dir.path().join(BUNDLE_INDEX_FILE)).unwrap();
+        assert!(!verify_hashes(&path, &PackageHashes::Md5(md5)).unwrap());
+    }
+}
@@ -1,3 +1,4 @@
+mod bundle;
 mod outdated;
 mod package_identifier;
 mod records_by_name;
@@ -8,7 +9,9 @@ mod update;
 mod utils;
 
 use crate::Project;
+pub(crate) use bundle::{conda_hashes, verify_hashes, ArtifactBundle};
 use miette::{IntoDiagnostic, WrapErr};
+pub(crate) use outdated::{OutdatedEnvironments, OutdatedTarget};
 pub(crate) use package_identifier::PypiPackageIdentifier;
 use pixi_record::PixiRecord;
 use rattler_lock::{LockFile, PypiPackageData, PypiPackageEnvironmentData};
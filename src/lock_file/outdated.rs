@@ -10,7 +10,7 @@ use rattler_lock::{LockFile, LockedPackageRef};
 use super::{verify_environment_satisfiability, verify_platform_satisfiability};
 use crate::{
     build::GlobHashCache,
-    lock_file::satisfiability::EnvironmentUnsat,
+    lock_file::satisfiability::{EnvironmentUnsat, PlatformUnsat},
     project::{Environment, SolveGroup},
     Project,
 };
@@ -34,6 +34,82 @@ pub struct OutdatedEnvironments<'p> {
     /// discarded. This is the case for instance when the order of the
     /// channels changed.
     pub disregard_locked_content: DisregardLockedContent<'p>,
+
+    /// The reasons why the environments are considered out of date, in the
+    /// order in which they were found.
+    pub reasons: Vec<OutdatedTarget<'p>>,
+}
+
+/// An environment, or a single platform of an environment, that is out of
+/// date together with the reason why.
+#[derive(Debug)]
+pub struct OutdatedTarget<'p> {
+    /// The environment that is out of date.
+    pub environment: Environment<'p>,
+
+    /// The platform that is out of date, or `None` if all the platforms of the
+    /// environment are out of date.
+    pub platform: Option<Platform>,
+
+    /// True if only the pypi packages are out of date.
+    pub pypi_only: bool,
+
+    /// Why the target is out of date.
+    pub reason: OutdatedReason,
+}
+
+/// The reason why a target is out of date.
+#[derive(Debug)]
+pub enum OutdatedReason {
+    /// The environment does not exist in the lock-file.
+    MissingEnvironment,
+
+    /// The locked environment does not match the environment in the project.
+    EnvironmentUnsat(EnvironmentUnsat),
+
+    /// The locked packages of a platform do not satisfy the requirements of the
+    /// environment.
+    PlatformUnsat(Box<PlatformUnsat>),
+
+    /// The locked packages are not the same for all the environments in the
+    /// solve group.
+    InconsistentSolveGroup(String),
+
+    /// Another environment in the same solve group is out of date.
+    SolveGroupOutdated(String),
+}
+
+impl OutdatedReason {
+    /// Returns a short identifier of the kind of reason.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutdatedReason::MissingEnvironment => "missing-environment",
+            OutdatedReason::EnvironmentUnsat(_) => "environment-unsat",
+            OutdatedReason::PlatformUnsat(_) => "platform-unsat",
+            OutdatedReason::InconsistentSolveGroup(_) => "inconsistent-solve-group",
+            OutdatedReason::SolveGroupOutdated(_) => "solve-group-outdated",
+        }
+    }
+}
+
+impl std::fmt::Display for OutdatedReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutdatedReason::MissingEnvironment => {
+                write!(f, "the environment does not exist in the lock-file")
+            }
+            OutdatedReason::EnvironmentUnsat(unsat) => write!(f, "{unsat}"),
+            OutdatedReason::PlatformUnsat(unsat) => write!(f, "{unsat}"),
+            OutdatedReason::InconsistentSolveGroup(solve_group) => write!(
+                f,
+                "the locked packages in solve group '{solve_group}' are not consistent for all environments"
+            ),
+            OutdatedReason::SolveGroupOutdated(solve_group) => write!(
+                f,
+                "another environment in solve group '{solve_group}' is out of date"
+            ),
+        }
+    }
 }
 
 /// A struct that stores whether the locked content of certain environments
@@ -69,6 +145,7 @@ impl<'p> OutdatedEnvironments<'p> {
         let mut outdated_conda: HashMap<_, HashSet<_>> = HashMap::new();
         let mut outdated_pypi: HashMap<_, HashSet<_>> = HashMap::new();
         let mut disregard_locked_content = DisregardLockedContent::default();
+        let mut reasons = Vec::new();
 
         // Find all targets that are not satisfied by the lock-file
         find_unsatisfiable_targets(
@@ -77,6 +154,7 @@ impl<'p> OutdatedEnvironments<'p> {
             &mut outdated_conda,
             &mut outdated_pypi,
             &mut disregard_locked_content,
+            &mut reasons,
             glob_hash_cache,
         )
         .await;
@@ -93,24 +171,45 @@ impl<'p> OutdatedEnvironments<'p> {
             &outdated_conda,
             &mut conda_solve_groups_out_of_date,
             &mut pypi_solve_groups_out_of_date,
+            &mut reasons,
         );
 
         // Mark the rest of the environments out of date for all solve groups
         for (solve_group, platforms) in conda_solve_groups_out_of_date {
             for env in solve_group.environments() {
-                outdated_conda
-                    .entry(env.clone())
-                    .or_default()
-                    .extend(platforms.iter().copied());
+                let outdated_platforms = outdated_conda.entry(env.clone()).or_default();
+                for &platform in &platforms {
+                    outdated_platforms.insert(platform);
+                    if !has_reason(&reasons, &env, platform) {
+                        reasons.push(OutdatedTarget {
+                            environment: env.clone(),
+                            platform: Some(platform),
+                            pypi_only: false,
+                            reason: OutdatedReason::SolveGroupOutdated(
+                                solve_group.name().to_string(),
+                            ),
+                        });
+                    }
+                }
             }
         }
 
         for (solve_group, platforms) in pypi_solve_groups_out_of_date {
             for env in solve_group.environments() {
-                outdated_pypi
-                    .entry(env.clone())
-                    .or_default()
-                    .extend(platforms.iter().copied());
+                let outdated_platforms = outdated_pypi.entry(env.clone()).or_default();
+                for &platform in &platforms {
+                    outdated_platforms.insert(platform);
+                    if !has_reason(&reasons, &env, platform) {
+                        reasons.push(OutdatedTarget {
+                            environment: env.clone(),
+                            platform: Some(platform),
+                            pypi_only: true,
+                            reason: OutdatedReason::SolveGroupOutdated(
+                                solve_group.name().to_string(),
+                            ),
+                        });
+                    }
+                }
             }
         }
 
@@ -127,6 +226,7 @@ impl<'p> OutdatedEnvironments<'p> {
             conda: outdated_conda,
             pypi: outdated_pypi,
             disregard_locked_content,
+            reasons,
         }
     }
 
@@ -137,6 +237,18 @@ impl<'p> OutdatedEnvironments<'p> {
     }
 }
 
+/// Returns true if a reason was already recorded why the given platform of the
+/// environment is out of date.
+fn has_reason(
+    reasons: &[OutdatedTarget<'_>],
+    environment: &Environment<'_>,
+    platform: Platform,
+) -> bool {
+    reasons.iter().any(|target| {
+        &target.environment == environment && target.platform.map_or(true, |p| p == platform)
+    })
+}
+
 /// Find all targets (combination of environment and platform) who's
 /// requirements in the `project` are not satisfied by the `lock_file`.
 async fn find_unsatisfiable_targets<'p>(
@@ -145,6 +257,7 @@ async fn find_unsatisfiable_targets<'p>(
     outdated_conda: &mut HashMap<Environment<'p>, HashSet<Platform>>,
     outdated_pypi: &mut HashMap<Environment<'p>, HashSet<Platform>>,
     disregard_locked_content: &mut DisregardLockedContent<'p>,
+    reasons: &mut Vec<OutdatedTarget<'p>>,
     glob_hash_cache: GlobHashCache,
 ) {
     for environment in project.environments() {
@@ -161,6 +274,12 @@ async fn find_unsatisfiable_targets<'p>(
                 .entry(environment.clone())
                 .or_default()
                 .extend(platforms);
+            reasons.push(OutdatedTarget {
+                environment: environment.clone(),
+                platform: None,
+                pypi_only: false,
+                reason: OutdatedReason::MissingEnvironment,
+            });
 
             continue;
         };
@@ -188,6 +307,12 @@ async fn find_unsatisfiable_targets<'p>(
                     disregard_locked_content.pypi.insert(environment.clone());
                 }
             }
+            reasons.push(OutdatedTarget {
+                environment: environment.clone(),
+                platform: None,
+                pypi_only: false,
+                reason: OutdatedReason::EnvironmentUnsat(unsat),
+            });
 
             continue;
         }
@@ -214,6 +339,12 @@ async fn find_unsatisfiable_targets<'p>(
                         .entry(environment.clone())
                         .or_default()
                         .insert(platform);
+                    reasons.push(OutdatedTarget {
+                        environment: environment.clone(),
+                        platform: Some(platform),
+                        pypi_only: true,
+                        reason: OutdatedReason::PlatformUnsat(Box::new(unsat)),
+                    });
                 }
                 Err(unsat) => {
                     tracing::info!(
@@ -225,6 +356,12 @@ async fn find_unsatisfiable_targets<'p>(
                         .entry(environment.clone())
                         .or_default()
                         .insert(platform);
+                    reasons.push(OutdatedTarget {
+                        environment: environment.clone(),
+                        platform: Some(platform),
+                        pypi_only: false,
+                        reason: OutdatedReason::PlatformUnsat(Box::new(unsat)),
+                    });
                 }
             }
         }
@@ -288,6 +425,7 @@ fn find_inconsistent_solve_groups<'p>(
     outdated_conda: &HashMap<Environment<'p>, HashSet<Platform>>,
     conda_solve_groups_out_of_date: &mut HashMap<SolveGroup<'p>, HashSet<Platform>>,
     pypi_solve_groups_out_of_date: &mut HashMap<SolveGroup<'p>, HashSet<Platform>>,
+    reasons: &mut Vec<OutdatedTarget<'p>>,
 ) {
     let solve_groups = project.solve_groups();
     let solve_groups_and_platforms = solve_groups.iter().flat_map(|solve_group| {
@@ -375,6 +513,12 @@ fn find_inconsistent_solve_groups<'p>(
                 .entry(solve_group.clone())
                 .or_default()
                 .insert(platform);
+            reasons.extend(solve_group.environments().map(|env| OutdatedTarget {
+                environment: env,
+                platform: Some(platform),
+                pypi_only: false,
+                reason: OutdatedReason::InconsistentSolveGroup(solve_group.name().to_string()),
+            }));
         }
 
         if pypi_package_mismatch && !conda_package_mismatch {
@@ -385,6 +529,105 @@ fn find_inconsistent_solve_groups<'p>(
                 .entry(solve_group.clone())
                 .or_default()
                 .insert(platform);
+            reasons.extend(solve_group.environments().map(|env| OutdatedTarget {
+                environment: env,
+                platform: Some(platform),
+                pypi_only: true,
+                reason: OutdatedReason::InconsistentSolveGroup(solve_group.name().to_string()),
+            }));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rattler_conda_types::Platform;
+    use rattler_lock::LockFile;
+
+    use super::OutdatedEnvironments;
+    use crate::{build::GlobHashCache, Project};
+
+    /// Returns the environment, platform, kind and message of the reasons why
+    /// the lock-file of the `pypi-extras` satisfiability fixture is out of
+    /// date once `from` is replaced by `to` in its manifest.
+    async fn outdated_reasons(
+        from: &str,
+        to: &str,
+    ) -> Vec<(String, Option<Platform>, &'static str, String)> {
+        let manifest_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/satisfiability/pypi-extras/pixi.toml");
+        let content = fs_err::read_to_string(&manifest_path).unwrap();
+        assert!(content.contains(from));
+        let project = Project::from_str(&manifest_path, &content.replace(from, to)).unwrap();
+        let lock_file = LockFile::from_path(&project.lock_file_path()).unwrap();
+        let outdated = OutdatedEnvironments::from_project_and_lock_file(
+            &project,
+            &lock_file,
+            GlobHashCache::default(),
+        )
+        .await;
+        outdated
+            .reasons
+            .iter()
+            .map(|target| {
+                (
+                    target.environment.name().to_string(),
+                    target.platform,
+                    target.reason.kind(),
+                    target.reason.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_up_to_date() {
+        let reasons = outdated_reasons("[dependencies]", "[dependencies]").await;
+        assert!(reasons.is_empty(), "{reasons:?}");
+    }
+
+    #[tokio::test]
+    async fn test_changed_spec() {
+        let reasons = outdated_reasons(r#"python = "*""#, r#"python = "==2.0""#).await;
+        assert_eq!(reasons.len(), 1, "{reasons:?}");
+        let (environment, platform, kind, message) = &reasons[0];
+        assert_eq!(environment, "default");
+        assert_eq!(*platform, Some(Platform::Win64));
+        assert_eq!(*kind, "platform-unsat");
+        assert!(message.contains("python ==2.0"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn test_missing_platform() {
+        let reasons = outdated_reasons(
+            r#"platforms = ["win-64"]"#,
+            r#"platforms = ["win-64", "linux-64"]"#,
+        )
+        .await;
+        assert_eq!(reasons.len(), 1, "{reasons:?}");
+        let (environment, platform, kind, _) = &reasons[0];
+        assert_eq!(environment, "default");
+        assert_eq!(*platform, Some(Platform::Linux64));
+        assert_eq!(*kind, "platform-unsat");
+    }
+
+    #[tokio::test]
+    async fn test_missing_environment() {
+        let reasons = outdated_reasons(
+            "[pypi-dependencies]",
+            "[feature.test.dependencies]\npython = \"*\"\n\n[environments]\ntest = [\"test\"]\n\n[pypi-dependencies]",
+        )
+        .await;
+        assert_eq!(
+            reasons,
+            [(
+                String::from("test"),
+                None,
+                "missing-environment",
+                String::from("the environment does not exist in the lock-file")
+            )]
+        );
+    }
+}
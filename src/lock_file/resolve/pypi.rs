@@ -30,7 +30,7 @@ use rattler_lock::{
 };
 use typed_path::Utf8TypedPathBuf;
 use url::Url;
-use uv_client::{Connectivity, FlatIndexClient, RegistryClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClient, RegistryClientBuilder};
 use uv_configuration::{ConfigSettings, Constraints, IndexStrategy, LowerBound, Overrides};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::DistributionDatabase;
@@ -289,7 +289,7 @@ pub async fn resolve_pypi(
             .index_strategy(index_strategy)
             .markers(&marker_environment)
             .keyring(context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(context.connectivity)
             .build(),
     );
 
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use miette::{Context, IntoDiagnostic};
 use uv_cache::Cache;
+use uv_client::Connectivity;
 use uv_configuration::{BuildOptions, Concurrency, SourceStrategy, TrustedHost};
 use uv_distribution_types::IndexCapabilities;
 use uv_types::{HashStrategy, InFlight};
@@ -24,6 +25,7 @@ pub struct UvResolutionContext {
     pub source_strategy: SourceStrategy,
     pub capabilities: IndexCapabilities,
     pub allow_insecure_host: Vec<TrustedHost>,
+    pub connectivity: Connectivity,
 }
 
 impl UvResolutionContext {
@@ -75,6 +77,11 @@ impl UvResolutionContext {
             source_strategy: SourceStrategy::Disabled,
             capabilities: IndexCapabilities::default(),
             allow_insecure_host,
+            connectivity: if project.config().offline() {
+                Connectivity::Offline
+            } else {
+                Connectivity::Online
+            },
         })
     }
 }
@@ -49,19 +49,19 @@ use crate::{
         LockedEnvironmentHash, PerEnvironmentAndPlatform, PerGroup, PerGroupAndPlatform,
         PythonStatus,
     },
-    load_lock_file,
+    install_pypi, load_lock_file,
     lock_file::{
         self,
         records_by_name::HasNameVersion,
         reporter::{CondaMetadataProgress, GatewayProgressReporter, SolveProgressBar},
-        PypiRecord,
+        ArtifactBundle, PypiRecord,
     },
     prefix::Prefix,
     project::{
         grouped_environment::{GroupedEnvironment, GroupedEnvironmentName},
         Environment, HasProjectRef,
     },
-    repodata::Repodata,
+    repodata::{with_offline_context, Repodata},
     Project,
 };
 
@@ -135,6 +135,9 @@ pub struct LockFileDerivedData<'p> {
 
     /// An object that caches input hashes
     pub glob_hash_cache: GlobHashCache,
+
+    /// A bundle to install the artifacts from instead of downloading them
+    pub artifact_bundle: Option<ArtifactBundle>,
 }
 
 /// The mode to use when updating a prefix.
@@ -299,6 +302,86 @@ impl<'p> LockFileDerivedData<'p> {
         Ok(prefix)
     }
 
+    /// Installs the environment for a platform other than the current platform
+    /// into the given prefix. Link scripts are not executed and the PyPI wheels
+    /// are installed without running the python interpreter of the prefix.
+    pub async fn foreign_prefix(
+        &self,
+        environment: &Environment<'p>,
+        platform: Platform,
+        prefix: &Prefix,
+    ) -> miette::Result<()> {
+        if !environment.platforms().contains(&platform) {
+            miette::bail!(
+                "the {} environment does not support {platform}",
+                environment.name().fancy_display()
+            );
+        }
+
+        let records = self
+            .pixi_records(environment, platform)
+            .into_diagnostic()?
+            .unwrap_or_default();
+        if let Some(record) = records
+            .iter()
+            .find(|record| matches!(record, PixiRecord::Source(_)))
+        {
+            miette::bail!(
+                "'{}' is a source package, source packages cannot be installed for another platform",
+                record.package_record().name.as_source()
+            );
+        }
+        let pypi_records = self
+            .pypi_records(environment, platform)
+            .into_diagnostic()?
+            .unwrap_or_default();
+
+        let installed_packages = prefix.find_installed_packages(None).await?;
+        let channel_urls = environment
+            .channel_urls(&self.project.channel_config())
+            .into_diagnostic()?;
+        let env_name = GroupedEnvironmentName::Environment(environment.name().clone());
+        environment::update_prefix_conda(
+            prefix,
+            self.package_cache.clone(),
+            self.project.authenticated_client().clone(),
+            installed_packages,
+            records.clone(),
+            environment
+                .virtual_packages(platform)
+                .into_iter()
+                .map(GenericVirtualPackage::from)
+                .collect(),
+            channel_urls,
+            platform,
+            &format!(
+                "installing environment '{}' for {platform}",
+                env_name.fancy_display()
+            ),
+            "",
+            self.io_concurrency_limit.clone().into(),
+            self.build_context.clone(),
+        )
+        .await?;
+
+        install_pypi::foreign::install_foreign_wheels(
+            self.project.root(),
+            prefix,
+            platform,
+            &environment.system_requirements(),
+            &records,
+            &pypi_records,
+            self.project.authenticated_client(),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "{}: error installing PyPI dependencies for {platform}",
+                environment.name()
+            )
+        })
+    }
+
     fn pypi_records(
         &self,
         environment: &Environment<'p>,
@@ -311,8 +394,14 @@ impl<'p> LockFileDerivedData<'p> {
 
         let packages = locked_env.pypi_packages(platform);
         Ok(packages.map(|iter| {
-            iter.map(|(data, env_data)| (data.clone(), env_data.clone()))
-                .collect()
+            iter.map(|(data, env_data)| {
+                let data = match &self.artifact_bundle {
+                    Some(bundle) => bundle.localize_pypi(data.clone()),
+                    None => data.clone(),
+                };
+                (data, env_data.clone())
+            })
+            .collect()
         }))
     }
 
@@ -341,8 +430,14 @@ impl<'p> LockFileDerivedData<'p> {
             .conda_packages(platform)
             .map(|iter| {
                 iter.cloned()
-                    .map(PixiRecord::try_from)
-                    .collect::<Result<Vec<_>, _>>()
+                    .map(|data| {
+                        let record = PixiRecord::try_from(data)?;
+                        Ok(match &self.artifact_bundle {
+                            Some(bundle) => bundle.localize_conda(record),
+                            None => record,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, ParseLockFileError>>()
             })
             .transpose()?)
     }
@@ -681,6 +776,7 @@ pub async fn update_lock_file(
             io_concurrency_limit: IoConcurrencyLimit::default(),
             build_context: BuildContext::from_project(project)?,
             glob_hash_cache,
+            artifact_bundle: None,
         });
     }
 
@@ -705,13 +801,16 @@ pub async fn update_lock_file(
             io_concurrency_limit: IoConcurrencyLimit::default(),
             build_context: BuildContext::from_project(project)?,
             glob_hash_cache,
+            artifact_bundle: None,
         });
     }
 
     // If the lock-file is out of date, but we're not allowed to update it, we
     // should exit.
     if !options.lock_file_usage.allows_lock_file_updates() {
-        miette::bail!("lock-file not up-to-date with the project");
+        miette::bail!(
+            "lock-file not up-to-date with the project, run `pixi lock check --explain` to see why"
+        );
     }
 
     // Construct an update context and perform the actual update.
@@ -1549,6 +1648,7 @@ impl<'p> UpdateContext<'p> {
             io_concurrency_limit: self.io_concurrency_limit,
             build_context: self.build_context,
             glob_hash_cache: self.glob_hash_cache,
+            artifact_bundle: None,
         })
     }
 }
@@ -1683,6 +1783,17 @@ async fn spawn_solve_conda_environment_task(
     // Get the channel configuration
     let channel_config = group.project().channel_config();
 
+    // Whether the repodata can only come from the cache
+    let offline = group.project().config().offline();
+
+    // In offline mode the mapping is read from the http cache, which has to be
+    // consulted before any request is rejected.
+    let mapping_client = if offline {
+        ClientWithMiddleware::from(group.project().client().clone())
+    } else {
+        client
+    };
+
     tokio::spawn(
         async move {
             // Acquire a permit before we are allowed to solve the environment.
@@ -1778,20 +1889,26 @@ async fn spawn_solve_conda_environment_task(
 
             // Extract the repo data records needed to solve the environment.
             let fetch_repodata_start = Instant::now();
-            let available_packages = repodata_gateway
-                .query(
-                    channels
-                        .into_iter()
-                        .map(|c| c.into_channel(&channel_config))
-                        .collect::<Result<Vec<_>, _>>()
-                        .into_diagnostic()?,
-                    [platform, Platform::NoArch],
-                    query_match_specs,
-                )
-                .recursive(true)
-                .with_reporter(GatewayProgressReporter::new(pb.clone()))
-                .await
+            let channels = channels
+                .into_iter()
+                .map(|c| c.into_channel(&channel_config))
+                .collect::<Result<Vec<_>, _>>()
                 .into_diagnostic()?;
+            let available_packages = with_offline_context(
+                repodata_gateway
+                    .query(
+                        channels.clone(),
+                        [platform, Platform::NoArch],
+                        query_match_specs,
+                    )
+                    .recursive(true)
+                    .with_reporter(GatewayProgressReporter::new(pb.clone()))
+                    .await
+                    .into_diagnostic(),
+                offline,
+                &channels,
+                platform,
+            )?;
             let total_records = available_packages.iter().map(RepoData::len).sum::<usize>();
             tracing::info!(
                 "fetched {total_records} records in {:?}",
@@ -1847,10 +1964,11 @@ async fn spawn_solve_conda_environment_task(
             if has_pypi_dependencies {
                 pb.set_message("extracting pypi packages");
                 pypi_mapping::amend_pypi_purls(
-                    client,
+                    mapping_client,
                     &pypi_name_mapping_location,
                     records.iter_mut().filter_map(PixiRecord::as_binary_mut),
                     Some(pb.purl_amend_reporter()),
+                    offline,
                 )
                 .await?;
             }
@@ -2104,6 +2222,7 @@ async fn spawn_solve_pypi_task(
             .iter_mut()
             .filter_map(PixiRecord::as_binary_mut),
         None,
+        environment.project().config().offline(),
     )
     .await?;
 
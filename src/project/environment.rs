@@ -266,6 +266,19 @@ impl<'p> Environment<'p> {
             .collect()
     }
 
+    /// Returns the dotenv files that should be loaded when activating this
+    /// environment, relative to the project root.
+    ///
+    /// The env-files of all features are combined in the order they are
+    /// defined for the environment.
+    pub(crate) fn activation_env_files(&self, platform: Option<Platform>) -> Vec<String> {
+        self.features()
+            .filter_map(|f| f.activation_env_files(platform))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
     /// Returns the environment variables that should be set when activating
     /// this environment.
     ///
@@ -771,6 +771,7 @@ impl Project {
             build_context,
             glob_hash_cache,
             io_concurrency_limit,
+            artifact_bundle,
         } = UpdateContext::builder(self)
             .with_lock_file(unlocked_lock_file)
             .with_no_install(prefix_update_config.no_install() || dry_run)
@@ -818,6 +819,7 @@ impl Project {
             io_concurrency_limit,
             build_context,
             glob_hash_cache,
+            artifact_bundle,
         };
         if !prefix_update_config.no_lockfile_update && !dry_run {
             updated_lock_file.write_to_disk()?;
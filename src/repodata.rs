@@ -1,4 +1,7 @@
+use itertools::Itertools;
+use miette::WrapErr;
 use rattler::package_cache::PackageCache;
+use rattler_conda_types::{Channel, Platform};
 use rattler_repodata_gateway::{ChannelConfig, Gateway};
 use std::path::PathBuf;
 
@@ -35,3 +38,22 @@ pub(crate) trait Repodata {
     /// Returns the [`Gateway`] used by this project.
     fn repodata_gateway(&self) -> &Gateway;
 }
+
+/// Adds the channels and platform of a repodata query to its error when pixi
+/// runs in offline mode, in which case the repodata has to come from the cache.
+pub(crate) fn with_offline_context<T>(
+    result: miette::Result<T>,
+    offline: bool,
+    channels: &[Channel],
+    platform: Platform,
+) -> miette::Result<T> {
+    if !offline {
+        return result;
+    }
+    result.wrap_err_with(|| {
+        format!(
+            "failed to load the repodata of {} for {platform} from the cache, pixi is running in offline mode",
+            channels.iter().map(Channel::canonical_name).join(", ")
+        )
+    })
+}
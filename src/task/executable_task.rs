@@ -1,7 +1,8 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
     path::PathBuf,
 };
 
@@ -13,12 +14,15 @@ use miette::{Context, Diagnostic, IntoDiagnostic};
 use rattler_lock::LockFile;
 use thiserror::Error;
 use tokio::task::JoinHandle;
+use xxhash_rust::xxh3::Xxh3;
 
-use super::task_hash::{InputHashesError, TaskCache, TaskHash};
+use super::{
+    output_store::OutputStore,
+    task_hash::{CacheMissReason, InputHashesError, TaskCache, TaskHash},
+};
 use crate::{
-    lock_file::LockFileDerivedData,
     project::Environment,
-    task::task_graph::{TaskGraph, TaskId},
+    task::task_graph::{render_task_template, ArgValues, TaskGraph, TaskId},
     Project,
 };
 use fs_err::tokio as tokio_fs;
@@ -30,6 +34,10 @@ use crate::project::HasProjectRef;
 use pixi_manifest::{Task, TaskName};
 use pixi_progress::await_in_progress;
 
+/// The number of runs of a task of which the outputs are kept in the output
+/// store.
+const KEPT_OUTPUT_RUNS: usize = 5;
+
 /// Runs task in project.
 #[derive(Default, Debug)]
 pub struct RunOutput {
@@ -45,6 +53,14 @@ pub struct FailedToParseShellScript {
     pub error: String,
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("failed to render the arguments in the command '{command}'")]
+pub struct FailedToRenderCommand {
+    pub command: String,
+    #[source]
+    pub source: minijinja::Error,
+}
+
 #[derive(Debug, Error, Diagnostic)]
 #[error("invalid working directory '{path}'")]
 pub struct InvalidWorkingDirectory {
@@ -58,6 +74,9 @@ pub enum TaskExecutionError {
 
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
+
+    #[error(transparent)]
+    FailedToRenderCommand(#[from] FailedToRenderCommand),
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -74,6 +93,7 @@ pub enum CacheUpdateError {
 
 pub enum CanSkip {
     Yes,
+    Restored,
     No(Option<TaskHash>),
 }
 
@@ -87,6 +107,7 @@ pub struct ExecutableTask<'p> {
     pub task: Cow<'p, Task>,
     pub run_environment: Environment<'p>,
     pub additional_args: Vec<String>,
+    pub args: Option<ArgValues>,
 }
 
 impl<'p> ExecutableTask<'p> {
@@ -99,6 +120,7 @@ impl<'p> ExecutableTask<'p> {
             task: node.task.clone(),
             run_environment: node.run_environment.clone(),
             additional_args: node.additional_args.clone(),
+            args: node.args.clone(),
         }
     }
 
@@ -117,10 +139,29 @@ impl<'p> ExecutableTask<'p> {
         self.project
     }
 
+    /// Returns the command of the task with the placeholders of its arguments
+    /// replaced by their values.
+    fn command(&self) -> Result<Option<Cow<'_, str>>, FailedToRenderCommand> {
+        let Some(cmd) = self.task.as_single_command() else {
+            return Ok(None);
+        };
+        let Some(args) = &self.args else {
+            return Ok(Some(cmd));
+        };
+        render_task_template(&cmd, args)
+            .map(|rendered| Some(Cow::Owned(rendered)))
+            .map_err(|source| FailedToRenderCommand {
+                command: cmd.into_owned(),
+                source,
+            })
+    }
+
     /// Returns the task as script
-    fn as_script(&self) -> Option<String> {
+    pub(crate) fn as_script(&self) -> Result<Option<String>, FailedToRenderCommand> {
         // Convert the task into an executable string
-        let task = self.task.as_single_command()?;
+        let Some(task) = self.command()? else {
+            return Ok(None);
+        };
 
         // Get the export specific environment variables
         let export = get_export_specific_task_env(self.task.as_ref());
@@ -138,23 +179,23 @@ impl<'p> ExecutableTask<'p> {
             format!("{export}\n{task} {cli_args}")
         };
 
-        Some(full_script)
+        Ok(Some(full_script))
     }
 
     /// Returns a [`SequentialList`] which can be executed by deno task shell.
     /// Returns `None` if the command is not executable like in the case of
     /// an alias.
-    pub(crate) fn as_deno_script(
-        &self,
-    ) -> Result<Option<SequentialList>, FailedToParseShellScript> {
-        if let Some(full_script) = self.as_script() {
+    pub(crate) fn as_deno_script(&self) -> Result<Option<SequentialList>, TaskExecutionError> {
+        if let Some(full_script) = self.as_script()? {
             tracing::debug!("Parsing shell script: {}", full_script);
 
             // Parse the shell command
             deno_task_shell::parser::parse(full_script.trim())
-                .map_err(|e| FailedToParseShellScript {
-                    script: full_script,
-                    error: e.to_string(),
+                .map_err(|e| {
+                    TaskExecutionError::FailedToParseShellScript(FailedToParseShellScript {
+                        script: full_script,
+                        error: e.to_string(),
+                    })
                 })
                 .map(Some)
         } else {
@@ -185,15 +226,18 @@ impl<'p> ExecutableTask<'p> {
     ///
     /// This function returns `None` if the task does not define a command to
     /// execute. This is the case for alias only commands.
-    pub(crate) fn full_command(&self) -> Option<String> {
-        let mut cmd = self.task.as_single_command()?.to_string();
+    pub(crate) fn full_command(&self) -> Result<Option<String>, FailedToRenderCommand> {
+        let Some(cmd) = self.command()? else {
+            return Ok(None);
+        };
+        let mut cmd = cmd.into_owned();
 
         if !self.additional_args.is_empty() {
             cmd.push(' ');
             cmd.push_str(&self.additional_args.join(" "));
         }
 
-        Some(cmd)
+        Ok(Some(cmd))
     }
 
     /// Returns an object that implements [`Display`] which outputs the command
@@ -234,37 +278,83 @@ impl<'p> ExecutableTask<'p> {
 
     /// We store the hashes of the inputs and the outputs of the task in a file
     /// in the cache. The current name is something like
-    /// `run_environment-task_name.json`.
+    /// `run_environment-task_name-args_hash.json`, where the hash of the
+    /// values of the arguments of the task makes sure that running a task
+    /// with different arguments does not overwrite the same entry. The
+    /// command itself is not part of the name, so a changed command
+    /// replaces the entry of the previous command.
     pub(crate) fn cache_name(&self) -> String {
+        let mut hasher = Xxh3::new();
+        for (name, value) in self.args.iter().flatten() {
+            name.hash(&mut hasher);
+            value.hash(&mut hasher);
+        }
+        self.additional_args.hash(&mut hasher);
         format!(
-            "{}-{}.json",
+            "{}-{}-{:x}.json",
             self.run_environment.name(),
-            self.name().unwrap_or("default")
+            self.name().unwrap_or("default"),
+            hasher.finish()
         )
     }
 
     /// Checks if the task can be skipped. If the task can be skipped, it
-    /// returns `CanSkip::Yes`. If the task cannot be skipped, it returns
-    /// `CanSkip::No` and includes the hash of the task that caused the task
-    /// to not be skipped - we can use this later to update the cache file
-    /// quickly.
+    /// returns `CanSkip::Yes`. If the outputs of the task were produced
+    /// before by a run with the same inputs, they are restored from the
+    /// output store and `CanSkip::Restored` is returned. If the task cannot
+    /// be skipped, it returns `CanSkip::No` and includes the hash of the task
+    /// that caused the task to not be skipped - we can use this later to
+    /// update the cache file quickly.
     pub(crate) async fn can_skip(&self, lock_file: &LockFile) -> Result<CanSkip, std::io::Error> {
         tracing::info!("Checking if task can be skipped");
+        let Ok(Some(hash)) = TaskHash::from_task(self, lock_file).await else {
+            return Ok(CanSkip::No(None));
+        };
+
         let cache_name = self.cache_name();
         let cache_file = self.project().task_cache_folder().join(cache_name);
         if cache_file.exists() {
             let cache = tokio_fs::read_to_string(&cache_file).await?;
             let cache: TaskCache = serde_json::from_str(&cache)?;
-            let hash = TaskHash::from_task(self, lock_file).await;
-            if let Ok(Some(hash)) = hash {
-                if hash.computation_hash() != cache.hash {
-                    return Ok(CanSkip::No(Some(hash)));
-                } else {
-                    return Ok(CanSkip::Yes);
+            if hash.computation_hash() == cache.hash {
+                return Ok(CanSkip::Yes);
+            }
+        }
+
+        // Restore the outputs if they were produced before with the same inputs.
+        let has_outputs = self
+            .task()
+            .as_execute()
+            .is_some_and(|execute| execute.outputs.is_some());
+        if has_outputs {
+            match self
+                .output_store()
+                .restore(self.project().root(), &hash.input_computation_hash())
+                .await
+            {
+                Ok(true) => {
+                    if let Err(err) = self.save_cache(lock_file, Some(hash)).await {
+                        tracing::warn!("failed to update the cache of the task: {err}");
+                    }
+                    return Ok(CanSkip::Restored);
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    tracing::warn!("failed to restore the outputs of the task: {err}");
                 }
             }
         }
-        Ok(CanSkip::No(None))
+
+        Ok(CanSkip::No(Some(hash)))
+    }
+
+    /// Returns the store that contains the outputs of previous runs of tasks.
+    fn output_store(&self) -> OutputStore {
+        OutputStore::new(
+            self.project()
+                .task_cache_folder()
+                .join(consts::TASK_OUTPUT_STORE_DIR),
+        )
     }
 
     /// Saves the cache of the task. This function will update the cache file
@@ -272,7 +362,7 @@ impl<'p> ExecutableTask<'p> {
     /// no hash, it will not save the cache.
     pub(crate) async fn save_cache(
         &self,
-        lock_file: &LockFileDerivedData<'_>,
+        lock_file: &LockFile,
         previous_hash: Option<TaskHash>,
     ) -> Result<(), CacheUpdateError> {
         let task_cache_folder = self.project().task_cache_folder();
@@ -280,7 +370,7 @@ impl<'p> ExecutableTask<'p> {
         let new_hash = if let Some(mut previous_hash) = previous_hash {
             previous_hash.update_output(self).await?;
             previous_hash
-        } else if let Some(hash) = TaskHash::from_task(self, &lock_file.lock_file).await? {
+        } else if let Some(hash) = TaskHash::from_task(self, lock_file).await? {
             hash
         } else {
             return Ok(());
@@ -288,11 +378,94 @@ impl<'p> ExecutableTask<'p> {
 
         tokio::fs::create_dir_all(&task_cache_folder).await?;
 
-        let cache = TaskCache {
-            hash: new_hash.computation_hash(),
-        };
+        // Remember the outputs of the previous runs, so switching back and forth
+        // between inputs restores the outputs instead of running the task again.
+        let mut cache = TaskCache::from_hash(&new_hash);
+        if let Some(input_hash) = &cache.input_hash {
+            let previous = tokio_fs::read_to_string(&cache_file)
+                .await
+                .ok()
+                .and_then(|previous| serde_json::from_str::<TaskCache>(&previous).ok());
+            if let Some(previous) = previous {
+                cache.previous_input_hashes = previous
+                    .input_hash
+                    .into_iter()
+                    .chain(previous.previous_input_hashes)
+                    .filter(|hash| hash != input_hash)
+                    .unique()
+                    .take(KEPT_OUTPUT_RUNS - 1)
+                    .collect();
+            }
+        }
         let cache = serde_json::to_string(&cache)?;
-        Ok(tokio::fs::write(&cache_file, cache).await?)
+        tokio::fs::write(&cache_file, cache).await?;
+
+        // Store the outputs so they can be restored when the task is run again with the
+        // same inputs.
+        if let Some(outputs) = &new_hash.outputs {
+            self.output_store()
+                .store(
+                    self.project().root(),
+                    &new_hash.input_computation_hash(),
+                    &outputs.files,
+                )
+                .await?;
+            if let Err(err) = self.prune_output_store().await {
+                tracing::warn!("failed to prune the outputs of previous runs of tasks: {err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the outputs from the output store that are not referenced by
+    /// the cache of any task, so the store only keeps the outputs of the
+    /// last [`KEPT_OUTPUT_RUNS`] runs of every task instead of growing
+    /// without bounds.
+    async fn prune_output_store(&self) -> std::io::Result<()> {
+        let mut referenced = HashSet::new();
+        let mut entries = tokio_fs::read_dir(self.project().task_cache_folder()).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+            let Ok(cache) = tokio_fs::read_to_string(&path).await else {
+                continue;
+            };
+            if let Ok(cache) = serde_json::from_str::<TaskCache>(&cache) {
+                referenced.extend(cache.input_hash);
+                referenced.extend(cache.previous_input_hashes);
+            }
+        }
+        self.output_store().prune(&referenced).await
+    }
+
+    /// Explains why the task could not be skipped. The `hash` is the hash that
+    /// was returned by [`Self::can_skip`].
+    pub(crate) async fn explain_cache_miss(
+        &self,
+        hash: Option<&TaskHash>,
+    ) -> Result<Vec<CacheMissReason>, std::io::Error> {
+        let cache_file = self.project().task_cache_folder().join(self.cache_name());
+        if hash.is_some() && !cache_file.exists() {
+            return Ok(vec![CacheMissReason::NoCacheEntry]);
+        }
+        let Some(hash) = hash else {
+            let is_cacheable = self
+                .task()
+                .as_execute()
+                .is_some_and(|execute| execute.inputs.is_some() || execute.outputs.is_some());
+            return Ok(vec![if is_cacheable && !cache_file.exists() {
+                CacheMissReason::NoCacheEntry
+            } else {
+                CacheMissReason::NotCacheable
+            }]);
+        };
+
+        let cache = tokio_fs::read_to_string(&cache_file).await?;
+        let cache: TaskCache = serde_json::from_str(&cache)?;
+        Ok(cache.explain(hash))
     }
 }
 
@@ -304,7 +477,7 @@ struct ExecutableTaskConsoleDisplay<'p, 't> {
 
 impl<'p, 't> Display for ExecutableTaskConsoleDisplay<'p, 't> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let command = self.task.task.as_single_command();
+        let command = self.task.command();
         write!(
             f,
             "{}",
@@ -456,9 +629,10 @@ mod tests {
             task: Cow::Borrowed(task),
             run_environment: project.default_environment(),
             additional_args: vec![],
+            args: None,
         };
 
-        let script = executable_task.as_script().unwrap();
+        let script = executable_task.as_script().unwrap().unwrap();
         assert_eq!(script, "export \"FOO=bar\";\n\ntest ");
     }
 
@@ -488,4 +662,98 @@ mod tests {
                 .to_string()
         );
     }
+
+    #[tokio::test]
+    async fn test_restore_outputs_of_earlier_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_contents = r#"
+            [tasks]
+            build = { cmd = "cp input.txt output.txt", inputs = ["input.txt"], outputs = ["output.txt"] }
+            "#;
+        let project = Project::from_str(
+            &dir.path().join("pixi.toml"),
+            &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+        )
+        .unwrap();
+        let environment = project.default_environment();
+        let task = ExecutableTask {
+            project: &project,
+            name: Some("build".into()),
+            task: Cow::Borrowed(environment.task(&"build".into(), None).unwrap()),
+            run_environment: environment,
+            additional_args: vec![],
+            args: None,
+        };
+        let lock_file = LockFile::default();
+
+        // Run the task with the inputs `a`, `b` and then `a` again.
+        let run = |contents: &'static str| {
+            fs_err::write(dir.path().join("input.txt"), contents).unwrap();
+            fs_err::write(dir.path().join("output.txt"), contents).unwrap();
+        };
+        run("a");
+        task.save_cache(&lock_file, None).await.unwrap();
+        run("b");
+        task.save_cache(&lock_file, None).await.unwrap();
+
+        fs_err::write(dir.path().join("input.txt"), "a").unwrap();
+        fs_err::remove_file(dir.path().join("output.txt")).unwrap();
+        assert!(matches!(
+            task.can_skip(&lock_file).await.unwrap(),
+            CanSkip::Restored
+        ));
+        assert_eq!(
+            fs_err::read_to_string(dir.path().join("output.txt")).unwrap(),
+            "a"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changed_command_is_explained() {
+        let dir = tempfile::tempdir().unwrap();
+        fs_err::write(dir.path().join("input.txt"), "input").unwrap();
+        let project = |cmd: &str| {
+            let file_contents = format!(
+                r#"
+                [tasks]
+                build = {{ cmd = "{cmd}", inputs = ["input.txt"] }}
+                "#
+            );
+            Project::from_str(
+                &dir.path().join("pixi.toml"),
+                &format!("{PROJECT_BOILERPLATE}\n{file_contents}"),
+            )
+            .unwrap()
+        };
+        fn task(project: &Project) -> ExecutableTask<'_> {
+            let environment = project.default_environment();
+            ExecutableTask {
+                project,
+                name: Some("build".into()),
+                task: Cow::Borrowed(environment.task(&"build".into(), None).unwrap()),
+                run_environment: environment,
+                additional_args: vec![],
+                args: None,
+            }
+        }
+        let lock_file = LockFile::default();
+
+        let old_project = project("echo old");
+        let old_task = task(&old_project);
+        old_task.save_cache(&lock_file, None).await.unwrap();
+
+        let new_project = project("echo new");
+        let new_task = task(&new_project);
+        assert_eq!(old_task.cache_name(), new_task.cache_name());
+        let CanSkip::No(hash) = new_task.can_skip(&lock_file).await.unwrap() else {
+            panic!("the task with the changed command is skipped");
+        };
+        assert_eq!(
+            new_task.explain_cache_miss(hash.as_ref()).await.unwrap(),
+            vec![CacheMissReason::CommandChanged {
+                previous: Some(String::from("echo old")),
+                current: Some(String::from("echo new")),
+            }]
+        );
+    }
 }
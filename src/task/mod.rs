@@ -1,17 +1,23 @@
 mod error;
 mod executable_task;
 mod file_hashes;
+mod output_store;
+mod sandbox;
 mod task_environment;
 mod task_graph;
 mod task_hash;
 
 pub use file_hashes::{FileHashes, FileHashesError};
+pub use output_store::OutputStore;
 pub use pixi_manifest::{Task, TaskName};
-pub use task_hash::{ComputationHash, EnvironmentHash, InputHashes, TaskHash};
+pub use sandbox::{run_sandboxed, SandboxError, SandboxPolicy};
+pub use task_hash::{
+    CacheMissReason, ComputationHash, EnvironmentHash, EnvironmentInputs, InputHashes, TaskHash,
+};
 
 pub use executable_task::{
-    get_task_env, CanSkip, ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory,
-    RunOutput, TaskExecutionError,
+    get_task_env, CacheUpdateError, CanSkip, ExecutableTask, FailedToParseShellScript,
+    FailedToRenderCommand, InvalidWorkingDirectory, RunOutput, TaskExecutionError,
 };
 pub use task_environment::{
     AmbiguousTask, FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment,
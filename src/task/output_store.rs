@@ -0,0 +1,315 @@
+//! Implements a content-addressed store for the outputs of tasks.
+//!
+//! After a task ran successfully, the files that match its `outputs` are copied into the store,
+//! keyed by the hash of their contents. Next to the files, a manifest is written that maps the
+//! paths of the outputs to the hashes of their contents. The manifest is keyed by the
+//! [`ComputationHash`] of everything that determines the outputs of the task, see
+//! [`super::TaskHash::input_computation_hash`].
+//!
+//! When a task is run again with the same inputs but its outputs are missing or different, the
+//! outputs are restored from the store instead of executing the task again.
+//!
+//! Only the outputs that are referenced by the cache of a task are kept, which are the outputs of
+//! the last few runs of every task. The outputs of older runs are pruned whenever new outputs are
+//! stored.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{ComputationHash, FileHashes};
+
+/// The manifest of the outputs of a single run of a task.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OutputManifest {
+    /// Maps the paths of the outputs, relative to the root of the project, to
+    /// the hashes of their contents.
+    files: BTreeMap<PathBuf, String>,
+}
+
+/// A content-addressed store for the outputs of tasks.
+#[derive(Debug, Clone)]
+pub struct OutputStore {
+    root: PathBuf,
+}
+
+impl OutputStore {
+    /// Constructs a new store in the given directory.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the path of the object with the given hash.
+    fn object_path(&self, hash: &str) -> PathBuf {
+        let prefix = hash.get(..2).unwrap_or(hash);
+        self.root.join("objects").join(prefix).join(hash)
+    }
+
+    /// Returns the path of the manifest with the given key.
+    fn manifest_path(&self, key: &ComputationHash) -> PathBuf {
+        self.root.join("manifests").join(format!("{key}.json"))
+    }
+
+    /// Stores the given outputs, relative to the root of the project, under
+    /// the given key.
+    pub async fn store(
+        &self,
+        project_root: &Path,
+        key: &ComputationHash,
+        outputs: &FileHashes,
+    ) -> io::Result<()> {
+        let store = self.clone();
+        let project_root = project_root.to_path_buf();
+        let manifest_path = self.manifest_path(key);
+        let manifest = OutputManifest {
+            files: outputs
+                .files
+                .iter()
+                .map(|(path, hash)| (path.clone(), hash.clone()))
+                .collect(),
+        };
+        tokio::task::spawn_blocking(move || {
+            for (path, hash) in &manifest.files {
+                let object_path = store.object_path(hash);
+                if !object_path.is_file() {
+                    copy_atomic(&project_root.join(path), &object_path)?;
+                }
+            }
+
+            let manifest = serde_json::to_vec(&manifest)?;
+            write_atomic(&manifest_path, &manifest)
+        })
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+
+    /// Restores the outputs that were stored under the given key into the
+    /// project. Returns `false` if the store does not contain the outputs.
+    pub async fn restore(&self, project_root: &Path, key: &ComputationHash) -> io::Result<bool> {
+        let store = self.clone();
+        let project_root = project_root.to_path_buf();
+        let manifest_path = self.manifest_path(key);
+        tokio::task::spawn_blocking(move || {
+            let manifest = match fs_err::read(&manifest_path) {
+                Ok(manifest) => manifest,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+                Err(err) => return Err(err),
+            };
+            let manifest: OutputManifest = serde_json::from_slice(&manifest)?;
+
+            // Make sure all the objects are still available before touching any of the
+            // outputs.
+            if manifest
+                .files
+                .values()
+                .any(|hash| !store.object_path(hash).is_file())
+            {
+                return Ok(false);
+            }
+
+            for (path, hash) in &manifest.files {
+                tracing::info!("Restoring output '{}' from the cache", path.display());
+                copy_atomic(&store.object_path(hash), &project_root.join(path))?;
+            }
+
+            Ok(true)
+        })
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+
+    /// Removes the manifests that are not stored under one of the given keys,
+    /// and the objects that are not referenced by any of the remaining
+    /// manifests.
+    pub async fn prune(&self, keep: &HashSet<ComputationHash>) -> io::Result<()> {
+        let store = self.clone();
+        let keep: HashSet<String> = keep.iter().map(|key| format!("{key}.json")).collect();
+        tokio::task::spawn_blocking(move || {
+            let mut referenced_objects = HashSet::new();
+            for entry in read_dir_if_exists(&store.root.join("manifests"))? {
+                let entry = entry?;
+                if is_temporary(&entry) {
+                    continue;
+                }
+                let keep_manifest = entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| keep.contains(name));
+                if !keep_manifest {
+                    fs_err::remove_file(entry.path())?;
+                    continue;
+                }
+                let manifest: OutputManifest =
+                    serde_json::from_slice(&fs_err::read(entry.path())?)?;
+                referenced_objects.extend(manifest.files.into_values());
+            }
+
+            for prefix in read_dir_if_exists(&store.root.join("objects"))? {
+                let prefix = prefix?;
+                for object in fs_err::read_dir(prefix.path())? {
+                    let object = object?;
+                    if is_temporary(&object) {
+                        continue;
+                    }
+                    let referenced = object
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|hash| referenced_objects.contains(hash));
+                    if !referenced {
+                        fs_err::remove_file(object.path())?;
+                    }
+                }
+                // Only succeeds if the directory is empty.
+                let _ = fs_err::remove_dir(prefix.path());
+            }
+
+            Ok(())
+        })
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+}
+
+/// Returns true if the entry is a temporary file that is still being written,
+/// see [`copy_atomic`] and [`write_atomic`].
+fn is_temporary(entry: &fs_err::DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with(".tmp")
+}
+
+/// Returns the entries of a directory, or no entries if it does not exist.
+fn read_dir_if_exists(path: &Path) -> io::Result<Vec<io::Result<fs_err::DirEntry>>> {
+    match fs_err::read_dir(path) {
+        Ok(entries) => Ok(entries.collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Copies a file to the destination by first copying it to a temporary file
+/// next to the destination and then moving it in place. This ensures that
+/// the destination never contains a partially written file.
+fn copy_atomic(source: &Path, destination: &Path) -> io::Result<()> {
+    let parent = destination
+        .parent()
+        .expect("the destination must have a parent directory");
+    fs_err::create_dir_all(parent)?;
+    let temp_path = tempfile::NamedTempFile::new_in(parent)?.into_temp_path();
+    fs_err::copy(source, &temp_path)?;
+    temp_path.persist(destination).map_err(|err| err.error)?;
+    Ok(())
+}
+
+/// Writes the contents to a file by first writing a temporary file next to
+/// the destination and then moving it in place.
+fn write_atomic(destination: &Path, contents: &[u8]) -> io::Result<()> {
+    let parent = destination
+        .parent()
+        .expect("the destination must have a parent directory");
+    fs_err::create_dir_all(parent)?;
+    let temp_path = tempfile::NamedTempFile::new_in(parent)?.into_temp_path();
+    fs_err::write(&temp_path, contents)?;
+    temp_path.persist(destination).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use fs_err::{create_dir, read_to_string, remove_dir_all, write};
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn store_and_restore() {
+        let project = tempdir().unwrap();
+        let store = OutputStore::new(tempdir().unwrap().into_path());
+
+        create_dir(project.path().join("build")).unwrap();
+        write(project.path().join("build/a.txt"), "a").unwrap();
+        write(project.path().join("build/b.txt"), "b").unwrap();
+
+        let outputs = FileHashes::from_files(project.path(), ["build/"])
+            .await
+            .unwrap();
+        let key = ComputationHash::from(String::from("abc"));
+        store.store(project.path(), &key, &outputs).await.unwrap();
+
+        // Nothing is stored under a different key.
+        let other_key = ComputationHash::from(String::from("def"));
+        assert!(!store.restore(project.path(), &other_key).await.unwrap());
+
+        // Remove the outputs and restore them.
+        remove_dir_all(project.path().join("build")).unwrap();
+        assert!(store.restore(project.path(), &key).await.unwrap());
+        assert_eq!(
+            read_to_string(project.path().join("build/a.txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            read_to_string(project.path().join("build/b.txt")).unwrap(),
+            "b"
+        );
+
+        // The restored outputs are identical to the original outputs.
+        let restored = FileHashes::from_files(project.path(), ["build/"])
+            .await
+            .unwrap();
+        assert_eq!(restored.files, outputs.files);
+    }
+
+    #[tokio::test]
+    async fn prune() {
+        let project = tempdir().unwrap();
+        let store = OutputStore::new(tempdir().unwrap().into_path());
+
+        create_dir(project.path().join("build")).unwrap();
+        write(project.path().join("build/a.txt"), "a").unwrap();
+        write(project.path().join("build/b.txt"), "b").unwrap();
+        let old_outputs = FileHashes::from_files(project.path(), ["build/"])
+            .await
+            .unwrap();
+        let old_key = ComputationHash::from(String::from("abc"));
+        store
+            .store(project.path(), &old_key, &old_outputs)
+            .await
+            .unwrap();
+
+        write(project.path().join("build/b.txt"), "c").unwrap();
+        let new_outputs = FileHashes::from_files(project.path(), ["build/"])
+            .await
+            .unwrap();
+        let new_key = ComputationHash::from(String::from("def"));
+        store
+            .store(project.path(), &new_key, &new_outputs)
+            .await
+            .unwrap();
+
+        store
+            .prune(&HashSet::from([new_key.clone()]))
+            .await
+            .unwrap();
+
+        // Only the objects of the outputs that are kept remain.
+        let objects: usize = fs_err::read_dir(store.root.join("objects"))
+            .unwrap()
+            .map(|prefix| fs_err::read_dir(prefix.unwrap().path()).unwrap().count())
+            .sum();
+        assert_eq!(objects, 2);
+
+        remove_dir_all(project.path().join("build")).unwrap();
+        assert!(!store.restore(project.path(), &old_key).await.unwrap());
+        assert!(store.restore(project.path(), &new_key).await.unwrap());
+        assert_eq!(
+            read_to_string(project.path().join("build/a.txt")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            read_to_string(project.path().join("build/b.txt")).unwrap(),
+            "c"
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Runs tasks with only the filesystem and network access they declare in
+//! their `sandbox` table.
+//!
+//! On Linux the access is enforced with [Landlock](https://docs.kernel.org/userspace-api/landlock.html).
+//! A Landlock domain applies to the thread that creates it and to everything
+//! that thread starts afterwards, so the shell of a sandboxed task runs on a
+//! thread of its own with its own runtime.
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+};
+
+use miette::Diagnostic;
+use pixi_manifest::task::Sandbox;
+use thiserror::Error;
+
+/// Paths that every sandboxed task can read, these are required to start
+/// programs at all.
+const SYSTEM_READ_PATHS: &[&str] = &[
+    "/bin", "/etc", "/lib", "/lib32", "/lib64", "/sbin", "/usr", "/proc", "/sys",
+];
+
+/// Paths that every sandboxed task can read and write, e.g. for `/dev/null`.
+const SYSTEM_WRITE_PATHS: &[&str] = &["/dev"];
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum SandboxError {
+    #[cfg(not(target_os = "linux"))]
+    #[error("sandboxed tasks are only supported on Linux")]
+    UnsupportedPlatform,
+
+    #[cfg(target_os = "linux")]
+    #[error("the kernel does not support Landlock, which is required to run sandboxed tasks")]
+    #[diagnostic(help(
+        "Landlock is available since Linux 5.13 and has to be enabled in the kernel"
+    ))]
+    LandlockUnsupported,
+
+    #[cfg(target_os = "linux")]
+    #[error("the kernel does not support restricting network access of sandboxed tasks")]
+    #[diagnostic(help(
+        "restricting network access requires Linux 6.7 or newer, or allow it with `network = true`"
+    ))]
+    NetworkUnsupported,
+
+    #[error("the sandbox can't grant write access to '{}', because its parent directory does not exist", .0.display())]
+    #[diagnostic(help(
+        "create the parent directory, or end the path with a `/` to create it as a directory"
+    ))]
+    MissingWriteParent(PathBuf),
+
+    #[error("failed to set up the sandbox")]
+    Io(#[from] std::io::Error),
+}
+
+/// The absolute paths and network access that a sandboxed task is allowed.
+#[derive(Debug, Clone)]
+pub struct SandboxPolicy {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+    network: bool,
+}
+
+impl SandboxPolicy {
+    /// Creates the policy of a sandbox from the manifest. Relative paths are
+    /// resolved against the root of the project. Besides the declared paths
+    /// the environment the task runs in and the system directories can be
+    /// read.
+    ///
+    /// Access can only be granted to existing paths. Writable paths that end
+    /// with a separator are created as directories, for other paths that
+    /// don't exist yet the existing parent directory is writable instead.
+    /// Files are never created.
+    pub fn new(
+        sandbox: &Sandbox,
+        project_root: &Path,
+        environment_dir: &Path,
+    ) -> Result<Self, SandboxError> {
+        let read = sandbox
+            .read
+            .iter()
+            .map(|path| project_root.join(path))
+            .chain([environment_dir.to_path_buf()])
+            .chain(SYSTEM_READ_PATHS.iter().map(PathBuf::from))
+            .collect();
+
+        let mut write = Vec::new();
+        for entry in &sandbox.write {
+            let path = project_root.join(entry);
+            if path.exists() {
+                write.push(path);
+            } else if has_trailing_separator(entry) {
+                fs_err::create_dir_all(&path)?;
+                write.push(path);
+            } else {
+                match path.parent() {
+                    Some(parent) if parent.is_dir() => {
+                        tracing::debug!(
+                            "'{}' does not exist, granting write access to '{}' instead",
+                            path.display(),
+                            parent.display()
+                        );
+                        write.push(parent.to_path_buf());
+                    }
+                    _ => return Err(SandboxError::MissingWriteParent(path)),
+                }
+            }
+        }
+        write.extend(SYSTEM_WRITE_PATHS.iter().map(PathBuf::from));
+
+        Ok(Self {
+            read,
+            write,
+            network: sandbox.network,
+        })
+    }
+
+    /// Allows reading (and executing) the given path as well.
+    pub fn allow_read(&mut self, path: PathBuf) {
+        self.read.push(path);
+    }
+
+    /// Restricts the current thread, and the threads and processes it starts
+    /// afterwards, to the policy. This can not be undone.
+    #[cfg(target_os = "linux")]
+    fn restrict_current_thread(&self) -> Result<(), SandboxError> {
+        landlock::restrict_current_thread(self)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn restrict_current_thread(&self) -> Result<(), SandboxError> {
+        Err(SandboxError::UnsupportedPlatform)
+    }
+}
+
+/// Returns whether a path from the manifest ends with a separator, which marks
+/// it as a directory.
+fn has_trailing_separator(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(std::path::is_separator)
+}
+
+/// Runs the future that is created by `f` on a new thread that is restricted
+/// to the policy, and returns its output.
+pub async fn run_sandboxed<F, Fut>(policy: SandboxPolicy, f: F) -> Result<Fut::Output, SandboxError>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future,
+    Fut::Output: Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let result = policy.restrict_current_thread().and_then(|()| {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            Ok(runtime.block_on(f()))
+        });
+        let _ = sender.send(result);
+    });
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(std::io::Error::other("the sandboxed task panicked").into()))
+}
+
+/// A minimal binding of the Landlock syscalls.
+#[cfg(target_os = "linux")]
+mod landlock {
+    use std::{
+        ffi::CString,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::ffi::OsStrExt,
+        },
+        path::Path,
+    };
+
+    use super::{SandboxError, SandboxPolicy};
+
+    const CREATE_RULESET_VERSION: u32 = 1 << 0;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    const ACCESS_FS_IOCTL_DEV: u64 = 1 << 15;
+
+    const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+    const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+    /// The access rights that apply to files, the other rights only apply to
+    /// directories.
+    const ACCESS_FS_FILE: u64 = ACCESS_FS_EXECUTE
+        | ACCESS_FS_WRITE_FILE
+        | ACCESS_FS_READ_FILE
+        | ACCESS_FS_TRUNCATE
+        | ACCESS_FS_IOCTL_DEV;
+    const ACCESS_FS_READ: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: libc::c_int,
+    }
+
+    /// Returns the filesystem access rights that are known to the given
+    /// version of the Landlock ABI.
+    fn access_fs_all(abi: libc::c_long) -> u64 {
+        // The first version knows the rights up to `MAKE_SYM`.
+        let mut access = (1 << 13) - 1;
+        if abi >= 2 {
+            access |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            access |= ACCESS_FS_TRUNCATE;
+        }
+        if abi >= 5 {
+            access |= ACCESS_FS_IOCTL_DEV;
+        }
+        access
+    }
+
+    fn check(result: libc::c_long) -> std::io::Result<libc::c_long> {
+        if result < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub(super) fn restrict_current_thread(policy: &SandboxPolicy) -> Result<(), SandboxError> {
+        // SAFETY: querying the ABI version doesn't read any memory.
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Err(SandboxError::LandlockUnsupported);
+        }
+        if !policy.network && abi < 4 {
+            return Err(SandboxError::NetworkUnsupported);
+        }
+
+        let access_fs = access_fs_all(abi);
+        let attr = RulesetAttr {
+            handled_access_fs: access_fs,
+            handled_access_net: if policy.network {
+                0
+            } else {
+                ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP
+            },
+        };
+        // Older kernels reject the network field, so only pass it when it's
+        // known.
+        let attr_size = if abi >= 4 {
+            std::mem::size_of::<RulesetAttr>()
+        } else {
+            std::mem::size_of::<u64>()
+        };
+        // SAFETY: `attr` outlives the call and `attr_size` doesn't exceed it.
+        let ruleset = check(unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                attr_size,
+                0u32,
+            )
+        })?;
+        // SAFETY: the syscall returned a new file descriptor that we own.
+        let ruleset = unsafe { OwnedFd::from_raw_fd(ruleset as libc::c_int) };
+
+        let rules = policy
+            .read
+            .iter()
+            .map(|path| (path, ACCESS_FS_READ))
+            .chain(policy.write.iter().map(|path| (path, access_fs)));
+        for (path, access) in rules {
+            add_path_rule(&ruleset, path, access)?;
+        }
+
+        // SAFETY: these calls don't read any memory.
+        unsafe {
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0).into())?;
+            check(libc::syscall(
+                libc::SYS_landlock_restrict_self,
+                ruleset.as_raw_fd(),
+                0u32,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Allows the access to everything beneath the path. Paths that don't
+    /// exist are skipped.
+    fn add_path_rule(ruleset: &OwnedFd, path: &Path, access: u64) -> Result<(), SandboxError> {
+        let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+            return Ok(());
+        };
+        // SAFETY: `c_path` is a valid nul-terminated string.
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            tracing::debug!(
+                "not adding '{}' to the sandbox: it does not exist",
+                path.display()
+            );
+            return Ok(());
+        }
+        // SAFETY: `open` returned a new file descriptor that we own.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let access = if path.is_dir() {
+            access
+        } else {
+            access & ACCESS_FS_FILE
+        };
+        let attr = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: fd.as_raw_fd(),
+        };
+        // SAFETY: `attr` outlives the call.
+        check(unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr,
+                0u32,
+            )
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_policy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sandbox = Sandbox {
+            read: vec![PathBuf::from("src")],
+            write: vec![
+                PathBuf::from("build/out/"),
+                PathBuf::from("logs/run.log"),
+                PathBuf::from("data.db"),
+            ],
+            network: false,
+        };
+        let env_dir = temp_dir.path().join(".pixi/envs/default");
+        fs_err::create_dir(temp_dir.path().join("logs")).unwrap();
+        fs_err::write(temp_dir.path().join("data.db"), "").unwrap();
+
+        let policy = SandboxPolicy::new(&sandbox, temp_dir.path(), &env_dir).unwrap();
+        assert!(policy.read.contains(&temp_dir.path().join("src")));
+        assert!(policy.read.contains(&env_dir));
+        assert!(policy.write.contains(&temp_dir.path().join("build/out/")));
+        assert!(
+            temp_dir.path().join("build/out").is_dir(),
+            "missing directories are created"
+        );
+        assert!(
+            policy.write.contains(&temp_dir.path().join("logs")),
+            "the parent directory of a missing file is writable"
+        );
+        assert!(
+            !temp_dir.path().join("logs/run.log").exists(),
+            "missing files are not created"
+        );
+        assert!(policy.write.contains(&temp_dir.path().join("data.db")));
+        assert!(!policy.network);
+
+        // The parent directory of a missing file has to exist.
+        let sandbox = Sandbox {
+            write: vec![PathBuf::from("missing/run.log")],
+            ..sandbox
+        };
+        assert!(matches!(
+            SandboxPolicy::new(&sandbox, temp_dir.path(), &env_dir),
+            Err(SandboxError::MissingWriteParent(_))
+        ));
+        assert!(!temp_dir.path().join("missing").exists());
+    }
+
+    #[tokio::test]
+    #[cfg(target_os = "linux")]
+    async fn test_run_sandboxed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs_err::write(temp_dir.path().join("secret"), "secret").unwrap();
+        let sandbox = Sandbox {
+            read: vec![],
+            write: vec![PathBuf::from("allowed/")],
+            network: true,
+        };
+        let policy =
+            SandboxPolicy::new(&sandbox, temp_dir.path(), &temp_dir.path().join("env")).unwrap();
+
+        let root = temp_dir.path().to_path_buf();
+        let result = run_sandboxed(policy, move || async move {
+            (
+                std::fs::write(root.join("allowed/file"), "data").is_ok(),
+                std::fs::read(root.join("secret")).is_ok(),
+            )
+        })
+        .await;
+        match result {
+            Ok((can_write, can_read_secret)) => {
+                assert!(can_write);
+                assert!(!can_read_secret);
+            }
+            // Landlock is not available on every kernel the tests run on.
+            Err(SandboxError::LandlockUnsupported) => {}
+            Err(err) => panic!("{err}"),
+        }
+    }
+}
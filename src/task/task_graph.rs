@@ -6,11 +6,13 @@ use std::{
     ops::Index,
 };
 
+use fancy_display::FancyDisplay;
+use indexmap::IndexMap;
 use itertools::Itertools;
 use miette::Diagnostic;
 use pixi_manifest::{
-    task::{CmdArgs, Custom},
-    Task, TaskName,
+    task::{quote_literal, CmdArgs, Custom, Dependency},
+    EnvironmentName, Task, TaskName,
 };
 use thiserror::Error;
 
@@ -29,6 +31,10 @@ use crate::{
     Project,
 };
 
+/// The values of the arguments of a task, keyed by the name of the argument
+/// in the order in which the task declares them.
+pub type ArgValues = IndexMap<String, String>;
+
 /// A task ID is a unique identifier for a [`TaskNode`] in a [`TaskGraph`].
 ///
 /// To get a task from a [`TaskGraph`], you can use the [`TaskId`] as an index.
@@ -51,6 +57,10 @@ pub struct TaskNode<'p> {
     /// verbatim, e.g. they will not be interpreted by deno.
     pub additional_args: Vec<String>,
 
+    /// The values of the arguments that the task declares or `None` if the
+    /// task does not declare any arguments.
+    pub args: Option<ArgValues>,
+
     /// The id's of the task that this task depends on.
     pub dependencies: Vec<TaskId>,
 }
@@ -82,6 +92,9 @@ impl<'p> TaskNode<'p> {
     #[cfg(test)]
     pub(crate) fn full_command(&self) -> Option<String> {
         let mut cmd = self.task.as_single_command()?.to_string();
+        if let Some(args) = &self.args {
+            cmd = render_task_template(&cmd, args).ok()?;
+        }
 
         if !self.additional_args.is_empty() {
             // Pass each additional argument varbatim by wrapping it in single quotes
@@ -165,14 +178,28 @@ impl<'p> TaskGraph<'p> {
                         Some(explicit_env) if task_env.is_default() => explicit_env,
                         _ => task_env,
                     };
+                    let name = TaskName::from(args.remove(0));
+
+                    // If the task declares arguments, the remaining arguments are the values
+                    // of those arguments. Otherwise they are passed verbatim to the command.
+                    let (task_args, additional_args) = if task.args().is_some() {
+                        (
+                            Some(resolve_task_args(&name, task, Some(args.as_slice()))?),
+                            Vec::new(),
+                        )
+                    } else {
+                        (None, args)
+                    };
+
                     return Self::from_root(
                         project,
                         search_envs,
                         TaskNode {
-                            name: Some(args.remove(0).into()),
+                            name: Some(name),
                             task: Cow::Borrowed(task),
                             run_environment: run_env,
-                            additional_args: args,
+                            additional_args,
+                            args: task_args,
                             dependencies: vec![],
                         },
                     );
@@ -213,6 +240,7 @@ impl<'p> TaskGraph<'p> {
                 ),
                 run_environment,
                 additional_args,
+                args: None,
                 dependencies: vec![],
             },
         )
@@ -224,62 +252,131 @@ impl<'p> TaskGraph<'p> {
         search_environments: &SearchEnvironments<'p, D>,
         root: TaskNode<'p>,
     ) -> Result<Self, TaskGraphError> {
-        let mut task_name_to_node: HashMap<TaskName, TaskId> =
-            HashMap::from_iter(root.name.clone().into_iter().map(|name| (name, TaskId(0))));
+        let mut task_name_to_node: HashMap<NodeKey, TaskId> =
+            HashMap::from_iter(root.name.clone().into_iter().map(|name| {
+                (
+                    (
+                        name,
+                        root.run_environment.name().clone(),
+                        node_args_key(&root.args),
+                    ),
+                    TaskId(0),
+                )
+            }));
         let mut nodes = vec![root];
 
+        // The tasks that were found by searching the environments, this makes sure
+        // we only have to disambiguate a task once.
+        let mut found_tasks = HashMap::new();
+
         // Iterate over all the nodes in the graph and add them to the graph.
         let mut next_node_to_visit = 0;
         while next_node_to_visit < nodes.len() {
-            let dependency_names =
+            let dependencies =
                 Vec::from_iter(nodes[next_node_to_visit].task.depends_on().iter().cloned());
 
             // Iterate over all the dependencies of the node and add them to the graph.
-            let mut node_dependencies = Vec::with_capacity(dependency_names.len());
-            for dependency in dependency_names {
-                // Check if we visited this node before already.
-                if let Some(&task_id) = task_name_to_node.get(&dependency) {
-                    node_dependencies.push(task_id);
-                    continue;
-                }
-
-                // Find the task in the project
+            let mut node_dependencies = Vec::with_capacity(dependencies.len());
+            for dependency in dependencies {
                 let node = &nodes[next_node_to_visit];
-                let (task_env, task_dependency) = match search_environments.find_task(
-                    dependency.clone(),
-                    FindTaskSource::DependsOn(
-                        node.name
-                            .clone()
-                            .expect("only named tasks can have dependencies"),
-                        match &node.task {
-                            Cow::Borrowed(task) => task,
-                            Cow::Owned(_) => {
-                                unreachable!("only named tasks can have dependencies")
-                            }
-                        },
-                    ),
-                ) {
-                    Err(FindTaskError::MissingTask(err)) => {
-                        return Err(TaskGraphError::MissingTask(err))
+                let parent_name = node
+                    .name
+                    .clone()
+                    .expect("only named tasks can have dependencies");
+
+                // Find the task in the project, either in the environment that the
+                // dependency explicitly asks for or by searching the environments.
+                let (task_env, task_dependency) = match &dependency.environment {
+                    Some(environment_name) => {
+                        let environment =
+                            project.environment(environment_name).ok_or_else(|| {
+                                TaskGraphError::UnknownEnvironment {
+                                    task: parent_name.clone(),
+                                    environment: environment_name.clone(),
+                                }
+                            })?;
+                        if !search_environments.ignore_system_requirements {
+                            verify_current_platform_has_required_virtual_packages(&environment)?;
+                        }
+                        let task = environment
+                            .task(&dependency.task_name, Some(environment.best_platform()))
+                            .map_err(|_| MissingTaskError {
+                                task_name: dependency.task_name.clone(),
+                            })?;
+                        (environment, task)
                     }
-                    Err(FindTaskError::AmbiguousTask(err)) => {
-                        return Err(TaskGraphError::AmbiguousTask(err))
+                    None => match found_tasks.get(&dependency.task_name) {
+                        Some(found) => found.clone(),
+                        None => {
+                            let found = match search_environments.find_task(
+                                dependency.task_name.clone(),
+                                FindTaskSource::DependsOn(
+                                    parent_name.clone(),
+                                    match &node.task {
+                                        Cow::Borrowed(task) => task,
+                                        Cow::Owned(_) => {
+                                            unreachable!("only named tasks can have dependencies")
+                                        }
+                                    },
+                                ),
+                            ) {
+                                Err(FindTaskError::MissingTask(err)) => {
+                                    return Err(TaskGraphError::MissingTask(err))
+                                }
+                                Err(FindTaskError::AmbiguousTask(err)) => {
+                                    return Err(TaskGraphError::AmbiguousTask(err))
+                                }
+                                Ok(result) => result,
+                            };
+                            found_tasks.insert(dependency.task_name.clone(), found.clone());
+                            found
+                        }
+                    },
+                };
+
+                // Determine the values of the arguments of the dependency. The arguments
+                // passed in the `depends-on` entry can refer to the arguments of the
+                // task that depends on it.
+                let dependency_args =
+                    resolve_dependency_args(&parent_name, node.args.as_ref(), &dependency)?;
+                let task_args = match (task_dependency.args(), &dependency_args) {
+                    (Some(_), _) => Some(resolve_task_args(
+                        &dependency.task_name,
+                        task_dependency,
+                        dependency_args.as_deref(),
+                    )?),
+                    (None, Some(args)) if !args.is_empty() => {
+                        return Err(TaskGraphError::ArgumentsNotSupported {
+                            task: dependency.task_name.clone(),
+                        })
                     }
-                    Ok(result) => result,
+                    (None, _) => None,
                 };
 
+                // Check if we visited this node before already.
+                let key = (
+                    dependency.task_name.clone(),
+                    task_env.name().clone(),
+                    node_args_key(&task_args),
+                );
+                if let Some(&task_id) = task_name_to_node.get(&key) {
+                    node_dependencies.push(task_id);
+                    continue;
+                }
+
                 // Add the node to the graph
                 let task_id = TaskId(nodes.len());
                 nodes.push(TaskNode {
-                    name: Some(dependency.clone()),
+                    name: Some(dependency.task_name.clone()),
                     task: Cow::Borrowed(task_dependency),
                     run_environment: task_env,
                     additional_args: Vec::new(),
+                    args: task_args,
                     dependencies: Vec::new(),
                 });
 
                 // Store the task id in the map to be able to look up the name later
-                task_name_to_node.insert(dependency.clone(), task_id);
+                task_name_to_node.insert(key, task_id);
 
                 // Add the dependency to the node
                 node_dependencies.push(task_id);
@@ -326,6 +423,112 @@ impl<'p> TaskGraph<'p> {
     }
 }
 
+/// Uniquely identifies a node in the graph by the name of the task, the
+/// environment it runs in and the values of its arguments.
+type NodeKey = (TaskName, EnvironmentName, Option<Vec<(String, String)>>);
+
+/// Returns a hashable representation of the argument values of a node.
+fn node_args_key(args: &Option<ArgValues>) -> Option<Vec<(String, String)>> {
+    args.as_ref().map(|args| {
+        args.iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    })
+}
+
+/// Renders the placeholders (e.g. `{{ name }}`) in the command of a task with
+/// the values of its arguments. Every value is quoted so the shell passes it
+/// as a single word, just like additional arguments. Referring to an argument
+/// that does not exist is an error.
+pub(crate) fn render_task_template(
+    template: &str,
+    args: &ArgValues,
+) -> Result<String, minijinja::Error> {
+    let mut env = minijinja::Environment::new();
+    env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+    env.set_formatter(|out, _state, value| {
+        fmt::Write::write_str(out, &quote_literal(&value.to_string())).map_err(|err| {
+            minijinja::Error::new(minijinja::ErrorKind::WriteFailure, err.to_string())
+        })
+    });
+    env.render_str(template, args)
+}
+
+/// Renders the placeholders in the arguments that are passed to a dependency
+/// with the values of the arguments of the task that depends on it. The
+/// values are not quoted because they end up in the command of the
+/// dependency, which quotes them itself.
+fn render_dependency_arg(template: &str, args: &ArgValues) -> Result<String, minijinja::Error> {
+    let mut env = minijinja::Environment::new();
+    env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+    env.render_str(template, args)
+}
+
+/// Determines the values of the arguments of a task from the values that were
+/// passed in order, falling back to the defaults of the arguments. Also
+/// verifies that the command of the task can be rendered with these values.
+fn resolve_task_args(
+    name: &TaskName,
+    task: &Task,
+    values: Option<&[String]>,
+) -> Result<ArgValues, TaskGraphError> {
+    let declared = task.args().unwrap_or_default();
+    let values = values.unwrap_or_default();
+    if values.len() > declared.len() {
+        return Err(TaskGraphError::TooManyArguments {
+            task: name.clone(),
+            expected: declared.len(),
+            actual: values.len(),
+        });
+    }
+
+    let mut args = ArgValues::with_capacity(declared.len());
+    for (idx, arg) in declared.iter().enumerate() {
+        let value = match values.get(idx).or(arg.default.as_ref()) {
+            Some(value) => value.clone(),
+            None => {
+                return Err(TaskGraphError::MissingArgument {
+                    task: name.clone(),
+                    arg: arg.name.clone(),
+                })
+            }
+        };
+        args.insert(arg.name.clone(), value);
+    }
+
+    if let Some(cmd) = task.as_single_command() {
+        render_task_template(&cmd, &args).map_err(|source| TaskGraphError::InvalidTemplate {
+            task: name.clone(),
+            source,
+        })?;
+    }
+
+    Ok(args)
+}
+
+/// Renders the arguments of a `depends-on` entry with the values of the
+/// arguments of the task that declares the dependency.
+fn resolve_dependency_args(
+    parent: &TaskName,
+    parent_args: Option<&ArgValues>,
+    dependency: &Dependency,
+) -> Result<Option<Vec<String>>, TaskGraphError> {
+    let (Some(args), Some(parent_args)) = (&dependency.args, parent_args) else {
+        return Ok(dependency.args.clone());
+    };
+    args.iter()
+        .map(|arg| {
+            render_dependency_arg(arg, parent_args).map_err(|source| {
+                TaskGraphError::InvalidTemplate {
+                    task: parent.clone(),
+                    source,
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
 #[derive(Debug, Error, Diagnostic)]
 pub enum TaskGraphError {
     #[error(transparent)]
@@ -341,6 +544,36 @@ pub enum TaskGraphError {
 
     #[error("could not split task, assuming non valid task")]
     InvalidTask,
+
+    #[error("the task '{}' accepts {expected} argument(s) but {actual} were given", task.fancy_display())]
+    #[diagnostic(help("use `pixi task list` to see which tasks accept arguments"))]
+    TooManyArguments {
+        task: TaskName,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("no value was provided for the argument '{arg}' of the task '{}'", task.fancy_display())]
+    #[diagnostic(help("pass a value for the argument or add a `default` to the argument"))]
+    MissingArgument { task: TaskName, arg: String },
+
+    #[error("the task '{}' depends on a task in the environment '{}' which does not exist", task.fancy_display(), environment.fancy_display())]
+    #[diagnostic(help("add the environment to the `[environments]` table of the manifest"))]
+    UnknownEnvironment {
+        task: TaskName,
+        environment: EnvironmentName,
+    },
+
+    #[error("the task '{}' does not accept arguments", task.fancy_display())]
+    #[diagnostic(help("declare the arguments of the task with `args = [...]`"))]
+    ArgumentsNotSupported { task: TaskName },
+
+    #[error("failed to render the arguments of the task '{}'", task.fancy_display())]
+    InvalidTemplate {
+        task: TaskName,
+        #[source]
+        source: minijinja::Error,
+    },
 }
 
 #[cfg(test)]
@@ -351,7 +584,10 @@ mod test {
     use rattler_conda_types::Platform;
 
     use crate::{
-        task::{task_environment::SearchEnvironments, task_graph::TaskGraph},
+        task::{
+            task_environment::SearchEnvironments,
+            task_graph::{TaskGraph, TaskGraphError},
+        },
         Project,
     };
 
@@ -609,4 +845,142 @@ mod test {
             None,
         );
     }
+
+    #[test]
+    fn test_task_args() {
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        greet = { cmd = "echo hello {{ name }}{{ suffix }}", args = ["name", { arg = "suffix", default = "!" }] }
+    "#,
+                &["greet", "world"],
+                None,
+                None
+            ),
+            vec!["echo hello world'!'"]
+        );
+    }
+
+    #[test]
+    fn test_task_args_from_depends_on() {
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        build = { cmd = "echo build {{ target }}", args = [{ arg = "target", default = "debug" }] }
+        test = { cmd = "echo test {{ target }}", args = ["target"], depends-on = [{ task = "build", args = ["{{ target }}"] }, "build"] }
+    "#,
+                &["test", "release"],
+                None,
+                None
+            ),
+            vec![
+                "echo build release",
+                "echo build debug",
+                "echo test release"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_args_are_quoted() {
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        greet = { cmd = "echo hello {{ name }}", args = ["name"] }
+    "#,
+                &["greet", "a b; rm -rf ~"],
+                None,
+                None
+            ),
+            vec!["echo hello 'a b; rm -rf ~'"]
+        );
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        greet = { cmd = "echo hello {{ name }}", args = ["name"] }
+    "#,
+                &["greet", "it's"],
+                None,
+                None
+            ),
+            vec![r#"echo hello 'it'"'"'s'"#]
+        );
+    }
+
+    #[test]
+    fn test_task_args_missing() {
+        let project = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        greet = { cmd = "echo hello {{ name }}", args = ["name"] }
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None)
+            .with_ignore_system_requirements(true);
+
+        let err = TaskGraph::from_cmd_args(&project, &search_envs, vec!["greet".to_string()])
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                TaskGraphError::MissingArgument { task, arg }
+                    if task.as_str() == "greet" && arg == "name"
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_depends_on_environment() {
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+
+        [tasks]
+        lint = "echo lint default"
+        ci = { depends-on = [{ task = "lint", environment = "default" }, { task = "lint", environment = "lint" }] }
+
+        [feature.lint.tasks]
+        lint = "echo lint feature"
+
+        [environments]
+        lint = { features = ["lint"], no-default-feature = true }
+    "#,
+                &["ci"],
+                None,
+                None
+            ),
+            vec!["echo lint default", "echo lint feature"]
+        );
+    }
 }
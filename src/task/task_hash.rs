@@ -1,11 +1,15 @@
+use crate::activation::get_env_file_variables;
 use crate::project;
-use crate::task::{ExecutableTask, FileHashes, FileHashesError, InvalidWorkingDirectory};
+use crate::task::{
+    ExecutableTask, FailedToRenderCommand, FileHashes, FileHashesError, InvalidWorkingDirectory,
+};
 use miette::Diagnostic;
 use rattler_lock::LockFile;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 use xxhash_rust::xxh3::Xxh3;
 
@@ -27,11 +31,390 @@ impl Display for ComputationHash {
     }
 }
 
-/// The cache of a task. It contains the hash of the task.
+/// The cache of a task. It contains the hash of the task and the individual
+/// hashes it was computed from, these are used to explain why a task has to be
+/// run again.
 #[derive(Deserialize, Serialize)]
 pub struct TaskCache {
     /// The hash of the task.
     pub hash: ComputationHash,
+
+    /// The command that was executed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    /// The hashes of the input files of the task.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<PathBuf, String>,
+
+    /// The hashes of the output files of the task.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub outputs: BTreeMap<PathBuf, String>,
+
+    /// The environment the task was executed in.
+    #[serde(default)]
+    pub environment: EnvironmentInputs,
+
+    /// The key under which the outputs of the task are kept in the output
+    /// store, if the task has outputs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<ComputationHash>,
+
+    /// The keys of the outputs of the previous runs of the task that are
+    /// still kept in the output store, the most recent first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_input_hashes: Vec<ComputationHash>,
+}
+
+impl TaskCache {
+    /// Constructs the cache of a task from its hash.
+    pub fn from_hash(hash: &TaskHash) -> Self {
+        Self {
+            hash: hash.computation_hash(),
+            command: hash.command.clone(),
+            inputs: sorted_files(hash.inputs.as_ref().map(|inputs| &inputs.files)),
+            outputs: sorted_files(hash.outputs.as_ref().map(|outputs| &outputs.files)),
+            environment: hash.environment.clone(),
+            input_hash: hash
+                .outputs
+                .is_some()
+                .then(|| hash.input_computation_hash()),
+            previous_input_hashes: Vec::new(),
+        }
+    }
+
+    /// Returns the reasons why the cache does not match the given hash of the
+    /// task.
+    pub fn explain(&self, hash: &TaskHash) -> Vec<CacheMissReason> {
+        let mut reasons = Vec::new();
+
+        if self.command != hash.command {
+            reasons.push(CacheMissReason::CommandChanged {
+                previous: self.command.clone(),
+                current: hash.command.clone(),
+            });
+        }
+
+        diff_files(
+            FileKind::Input,
+            &self.inputs,
+            &sorted_files(hash.inputs.as_ref().map(|inputs| &inputs.files)),
+            &mut reasons,
+        );
+        diff_files(
+            FileKind::Output,
+            &self.outputs,
+            &sorted_files(hash.outputs.as_ref().map(|outputs| &outputs.files)),
+            &mut reasons,
+        );
+
+        let previous = &self.environment;
+        let current = &hash.environment;
+        let env_var_names: BTreeSet<&String> = previous
+            .env_vars
+            .keys()
+            .chain(current.env_vars.keys())
+            .collect();
+        for name in env_var_names {
+            let previous_value = previous.env_vars.get(name);
+            let current_value = current.env_vars.get(name);
+            if previous_value != current_value {
+                reasons.push(CacheMissReason::EnvVarChanged {
+                    name: name.clone(),
+                    previous: previous_value.cloned(),
+                    current: current_value.cloned(),
+                });
+            }
+        }
+        let env_file_var_names: BTreeSet<&String> = previous
+            .env_file_vars
+            .keys()
+            .chain(current.env_file_vars.keys())
+            .collect();
+        for name in env_file_var_names {
+            if previous.env_file_vars.get(name) != current.env_file_vars.get(name) {
+                reasons.push(CacheMissReason::EnvFileVarChanged { name: name.clone() });
+            }
+        }
+        if previous.activation_scripts != current.activation_scripts
+            || previous.activation_script_hashes != current.activation_script_hashes
+        {
+            reasons.push(CacheMissReason::ActivationScriptsChanged);
+        }
+        for package in current.packages.difference(&previous.packages) {
+            reasons.push(CacheMissReason::PackageAdded(package.clone()));
+        }
+        for package in previous.packages.difference(&current.packages) {
+            reasons.push(CacheMissReason::PackageRemoved(package.clone()));
+        }
+
+        // The hash might also differ because the cache was written by a version of
+        // pixi that computed the hash differently.
+        if reasons.is_empty() && self.hash != hash.computation_hash() {
+            reasons.push(CacheMissReason::Unknown);
+        }
+
+        reasons
+    }
+}
+
+/// Returns the hashes of files sorted by their path.
+fn sorted_files(files: Option<&FileHashes>) -> BTreeMap<PathBuf, String> {
+    files
+        .map(|files| {
+            files
+                .files
+                .iter()
+                .map(|(path, hash)| (path.clone(), hash.clone()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Records the files that were added, removed or modified.
+fn diff_files(
+    kind: FileKind,
+    previous: &BTreeMap<PathBuf, String>,
+    current: &BTreeMap<PathBuf, String>,
+    reasons: &mut Vec<CacheMissReason>,
+) {
+    for (path, hash) in current {
+        match previous.get(path) {
+            None => reasons.push(CacheMissReason::FileAdded {
+                kind,
+                path: path.clone(),
+            }),
+            Some(previous_hash) if previous_hash != hash => {
+                reasons.push(CacheMissReason::FileChanged {
+                    kind,
+                    path: path.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for path in previous.keys().filter(|path| !current.contains_key(*path)) {
+        reasons.push(CacheMissReason::FileRemoved {
+            kind,
+            path: path.clone(),
+        });
+    }
+}
+
+/// Whether a file is an input or an output of a task.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FileKind {
+    Input,
+    Output,
+}
+
+impl Display for FileKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileKind::Input => write!(f, "input"),
+            FileKind::Output => write!(f, "output"),
+        }
+    }
+}
+
+/// Describes why the cache of a task could not be used.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CacheMissReason {
+    /// The task does not define any inputs or outputs.
+    NotCacheable,
+
+    /// The task has not been run before.
+    NoCacheEntry,
+
+    /// The command of the task changed.
+    CommandChanged {
+        previous: Option<String>,
+        current: Option<String>,
+    },
+
+    /// A file matching the inputs or outputs of the task was added.
+    FileAdded { kind: FileKind, path: PathBuf },
+
+    /// A file matching the inputs or outputs of the task was removed.
+    FileRemoved { kind: FileKind, path: PathBuf },
+
+    /// The contents of a file matching the inputs or outputs of the task
+    /// changed.
+    FileChanged { kind: FileKind, path: PathBuf },
+
+    /// An environment variable of the task changed.
+    EnvVarChanged {
+        name: String,
+        previous: Option<String>,
+        current: Option<String>,
+    },
+
+    /// A variable from an env-file was added, removed or changed. The values
+    /// are not known, only their hashes are stored.
+    EnvFileVarChanged { name: String },
+
+    /// The activation scripts of the environment changed.
+    ActivationScriptsChanged,
+
+    /// A package was added to the environment in the lock-file.
+    PackageAdded(String),
+
+    /// A package was removed from the environment in the lock-file.
+    PackageRemoved(String),
+
+    /// The cache does not contain enough information to explain the change.
+    Unknown,
+}
+
+impl Display for CacheMissReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheMissReason::NotCacheable => write!(
+                f,
+                "the task does not define any inputs or outputs so it is always run"
+            ),
+            CacheMissReason::NoCacheEntry => write!(f, "the task has not been run before"),
+            CacheMissReason::CommandChanged { previous, current } => write!(
+                f,
+                "the command changed from '{}' to '{}'",
+                previous.as_deref().unwrap_or_default(),
+                current.as_deref().unwrap_or_default()
+            ),
+            CacheMissReason::FileAdded { kind, path } => {
+                write!(f, "the {kind} file '{}' was added", path.display())
+            }
+            CacheMissReason::FileRemoved { kind, path } => {
+                write!(f, "the {kind} file '{}' was removed", path.display())
+            }
+            CacheMissReason::FileChanged { kind, path } => {
+                write!(f, "the {kind} file '{}' was modified", path.display())
+            }
+            CacheMissReason::EnvVarChanged {
+                name,
+                previous,
+                current,
+            } => match (previous, current) {
+                (None, Some(current)) => {
+                    write!(
+                        f,
+                        "the environment variable '{name}' was set to '{current}'"
+                    )
+                }
+                (Some(_), None) => write!(f, "the environment variable '{name}' was removed"),
+                (previous, current) => write!(
+                    f,
+                    "the environment variable '{name}' changed from '{}' to '{}'",
+                    previous.as_deref().unwrap_or_default(),
+                    current.as_deref().unwrap_or_default()
+                ),
+            },
+            CacheMissReason::EnvFileVarChanged { name } => {
+                write!(f, "the variable '{name}' of an env-file changed")
+            }
+            CacheMissReason::ActivationScriptsChanged => {
+                write!(f, "the activation scripts of the environment changed")
+            }
+            CacheMissReason::PackageAdded(package) => {
+                write!(f, "the package '{package}' was added to the lock-file")
+            }
+            CacheMissReason::PackageRemoved(package) => {
+                write!(f, "the package '{package}' was removed from the lock-file")
+            }
+            CacheMissReason::Unknown => {
+                write!(f, "the cache was created by a different version of pixi")
+            }
+        }
+    }
+}
+
+/// The parts of the environment of a task that determine whether a task has
+/// to be run again.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct EnvironmentInputs {
+    /// The environment variables of the activation of the environment and of
+    /// the task itself.
+    #[serde(default)]
+    pub env_vars: BTreeMap<String, String>,
+
+    /// The hashes of the values of the variables from the env-files of the
+    /// environment. The values are not stored because env-files usually
+    /// contain secrets.
+    #[serde(default)]
+    pub env_file_vars: BTreeMap<String, String>,
+
+    /// The activation scripts of the environment.
+    #[serde(default)]
+    pub activation_scripts: Vec<String>,
+
+    /// The hashes of the state of the activation scripts of the manifest and
+    /// of the prefix, the scripts can change without a change to the manifest
+    /// or the lock-file.
+    #[serde(default)]
+    pub activation_script_hashes: BTreeMap<PathBuf, String>,
+
+    /// The locations of the packages in the lock-file.
+    #[serde(default)]
+    pub packages: BTreeSet<String>,
+}
+
+impl EnvironmentInputs {
+    /// Collects the inputs of the environment of a task.
+    pub(crate) fn from_task(task: &ExecutableTask<'_>, lock_file: &LockFile) -> Self {
+        let run_environment = &task.run_environment;
+        let platform = run_environment.best_platform();
+
+        let mut env_vars: BTreeMap<String, String> = run_environment
+            .activation_env(Some(platform))
+            .into_iter()
+            .collect();
+        if let Some(task_env) = task.task().env() {
+            env_vars.extend(task_env.clone());
+        }
+
+        let packages = lock_file
+            .environment(run_environment.name().as_str())
+            .and_then(|env| env.packages(platform))
+            .map(|packages| {
+                packages
+                    .map(|package| package.location().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // An env-file that can't be read fails the activation of the task, so it
+        // doesn't matter that its variables are missing here.
+        let env_file_vars = get_env_file_variables(run_environment, platform)
+            .map(|vars| {
+                vars.into_iter()
+                    .map(|(name, value)| {
+                        let mut hasher = Xxh3::new();
+                        value.hash(&mut hasher);
+                        (name, format!("{:x}", hasher.finish()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let activation_scripts = run_environment.activation_scripts(Some(platform));
+        let activation_script_hashes = activation_scripts
+            .iter()
+            .map(|script| run_environment.project().root().join(script))
+            .chain(prefix_activation_files(run_environment))
+            .map(|path| {
+                let mut hasher = Xxh3::new();
+                hash_file_state(&path, &mut hasher);
+                (path, format!("{:x}", hasher.finish()))
+            })
+            .collect();
+
+        Self {
+            env_vars,
+            env_file_vars,
+            activation_scripts,
+            activation_script_hashes,
+            packages,
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -54,11 +437,19 @@ impl EnvironmentHash {
             value.hash(&mut hasher);
         }
 
-        // Hash the activation scripts
+        // Hash the activation scripts, including their contents as they can change
+        // without any change to the manifest
         let activation_scripts =
             run_environment.activation_scripts(Some(run_environment.best_platform()));
         for script in activation_scripts {
             script.hash(&mut hasher);
+            hash_file_state(&run_environment.project().root().join(script), &mut hasher);
+        }
+
+        // Hash the activation scripts and variables of the prefix, these can be
+        // modified without a change to the lock file
+        for path in prefix_activation_files(run_environment) {
+            hash_file_state(&path, &mut hasher);
         }
 
         // Hash the environment variables
@@ -72,6 +463,17 @@ impl EnvironmentHash {
             value.hash(&mut hasher);
         }
 
+        // Hash the variables from the env-files, so changing a file invalidates
+        // the hash
+        match get_env_file_variables(run_environment, run_environment.best_platform()) {
+            Ok(env_file_vars) => {
+                let mut env_file_vars: Vec<_> = env_file_vars.into_iter().collect();
+                env_file_vars.sort();
+                env_file_vars.hash(&mut hasher);
+            }
+            Err(err) => err.to_string().hash(&mut hasher),
+        }
+
         // Hash the packages
         let mut urls = Vec::new();
         if let Some(env) = lock_file.environment(run_environment.name().as_str()) {
@@ -88,6 +490,33 @@ impl EnvironmentHash {
     }
 }
 
+/// Returns the activation scripts and variables of the prefix of an
+/// environment, sorted by path.
+fn prefix_activation_files(environment: &project::Environment<'_>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for dir in ["etc/conda/activate.d", "etc/conda/env_vars.d"] {
+        let mut dir_paths: Vec<_> = fs_err::read_dir(environment.dir().join(dir))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        dir_paths.sort();
+        paths.extend(dir_paths);
+    }
+    paths
+}
+
+/// Hashes the path, modification time and contents of a file. A file that
+/// can't be read is hashed as missing.
+fn hash_file_state(path: &Path, hasher: &mut Xxh3) {
+    path.hash(hasher);
+    fs_err::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .hash(hasher);
+    fs_err::read(path).ok().hash(hasher);
+}
+
 impl Display for EnvironmentHash {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -98,7 +527,7 @@ impl Display for EnvironmentHash {
 /// with the [`TaskHash::computation_hash`] method.
 #[derive(Debug)]
 pub struct TaskHash {
-    pub environment: EnvironmentHash,
+    pub environment: EnvironmentInputs,
     pub command: Option<String>,
     pub inputs: Option<InputHashes>,
     pub outputs: Option<OutputHashes>,
@@ -118,15 +547,10 @@ impl TaskHash {
         }
 
         Ok(Some(Self {
-            command: task.full_command(),
+            command: task.full_command()?,
             outputs: output_hashes,
             inputs: input_hashes,
-            // Skipping environment variables used for caching the task
-            environment: EnvironmentHash::from_environment(
-                &task.run_environment,
-                &HashMap::new(),
-                lock_file,
-            ),
+            environment: EnvironmentInputs::from_task(task, lock_file),
         }))
     }
 
@@ -138,6 +562,16 @@ impl TaskHash {
         Ok(())
     }
 
+    /// Computes a hash of everything that determines the outputs of the task,
+    /// this is the hash of the task without its outputs.
+    pub fn input_computation_hash(&self) -> ComputationHash {
+        let mut hasher = Xxh3::new();
+        self.command.hash(&mut hasher);
+        self.inputs.hash(&mut hasher);
+        self.environment.hash(&mut hasher);
+        ComputationHash(format!("{:x}", hasher.finish()))
+    }
+
     /// Computes a single hash for the task.
     pub fn computation_hash(&self) -> ComputationHash {
         let mut hasher = Xxh3::new();
@@ -223,4 +657,191 @@ pub enum InputHashesError {
 
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
+
+    #[error(transparent)]
+    FailedToRenderCommand(#[from] FailedToRenderCommand),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        collections::HashMap,
+        path::{Path, PathBuf},
+    };
+
+    use rattler_lock::LockFile;
+
+    use super::{CacheMissReason, EnvironmentInputs, FileKind, InputHashes, TaskCache, TaskHash};
+    use crate::{
+        task::{ExecutableTask, FileHashes},
+        Project,
+    };
+
+    fn task_hash(files: &[(&str, &str)], env_vars: &[(&str, &str)]) -> TaskHash {
+        TaskHash {
+            environment: EnvironmentInputs {
+                env_vars: env_vars
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+                ..EnvironmentInputs::default()
+            },
+            command: Some(String::from("echo hello")),
+            inputs: Some(InputHashes {
+                files: FileHashes {
+                    files: files
+                        .iter()
+                        .map(|(path, hash)| (PathBuf::from(path), hash.to_string()))
+                        .collect::<HashMap<_, _>>(),
+                },
+            }),
+            outputs: None,
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let cache = TaskCache::from_hash(&task_hash(
+            &[("a.txt", "1"), ("b.txt", "2")],
+            &[("FOO", "foo"), ("BAR", "bar")],
+        ));
+
+        let unchanged = task_hash(
+            &[("a.txt", "1"), ("b.txt", "2")],
+            &[("FOO", "foo"), ("BAR", "bar")],
+        );
+        assert_eq!(cache.hash, unchanged.computation_hash());
+        assert!(cache.explain(&unchanged).is_empty());
+
+        let changed = task_hash(
+            &[("a.txt", "3"), ("c.txt", "4")],
+            &[("FOO", "baz"), ("QUX", "qux")],
+        );
+        assert_eq!(
+            cache.explain(&changed),
+            vec![
+                CacheMissReason::FileChanged {
+                    kind: FileKind::Input,
+                    path: PathBuf::from("a.txt")
+                },
+                CacheMissReason::FileAdded {
+                    kind: FileKind::Input,
+                    path: PathBuf::from("c.txt")
+                },
+                CacheMissReason::FileRemoved {
+                    kind: FileKind::Input,
+                    path: PathBuf::from("b.txt")
+                },
+                CacheMissReason::EnvVarChanged {
+                    name: String::from("BAR"),
+                    previous: Some(String::from("bar")),
+                    current: None
+                },
+                CacheMissReason::EnvVarChanged {
+                    name: String::from("FOO"),
+                    previous: Some(String::from("foo")),
+                    current: Some(String::from("baz"))
+                },
+                CacheMissReason::EnvVarChanged {
+                    name: String::from("QUX"),
+                    previous: None,
+                    current: Some(String::from("qux"))
+                },
+            ]
+        );
+    }
+
+    /// Returns a project in the directory with a `build` task that has an
+    /// input, so it is cached.
+    fn project(dir: &Path, activation: &str) -> Project {
+        fs_err::write(dir.join("input.txt"), "input").unwrap();
+        Project::from_str(
+            &dir.join("pixi.toml"),
+            &format!(
+                r#"
+                [project]
+                name = "foo"
+                version = "0.1.0"
+                channels = []
+                platforms = ["linux-64", "osx-64", "win-64", "osx-arm64", "linux-aarch64"]
+
+                [activation]
+                {activation}
+
+                [tasks]
+                build = {{ cmd = "echo $TOKEN", inputs = ["input.txt"] }}
+                "#
+            ),
+        )
+        .unwrap()
+    }
+
+    fn build_task(project: &Project) -> ExecutableTask<'_> {
+        let environment = project.default_environment();
+        ExecutableTask {
+            project,
+            name: Some("build".into()),
+            task: Cow::Borrowed(environment.task(&"build".into(), None).unwrap()),
+            run_environment: environment,
+            additional_args: vec![],
+            args: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_env_file_change_is_a_cache_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path(), r#"env-file = [".env"]"#);
+        fs_err::write(dir.path().join(".env"), "TOKEN=secret").unwrap();
+        let task = build_task(&project);
+        let lock_file = LockFile::default();
+
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        let cache = TaskCache::from_hash(&hash);
+        let serialized = serde_json::to_string(&cache).unwrap();
+        assert!(!serialized.contains("secret"));
+
+        fs_err::write(dir.path().join(".env"), "TOKEN=rotated").unwrap();
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(cache.hash, hash.computation_hash());
+        assert_eq!(
+            cache.explain(&hash),
+            vec![CacheMissReason::EnvFileVarChanged {
+                name: String::from("TOKEN")
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_activation_script_change_is_a_cache_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = project(dir.path(), r#"scripts = ["activate.sh"]"#);
+        fs_err::write(dir.path().join("activate.sh"), "export TOKEN=1").unwrap();
+        let task = build_task(&project);
+        let lock_file = LockFile::default();
+
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        let cache = TaskCache::from_hash(&hash);
+
+        fs_err::write(dir.path().join("activate.sh"), "export TOKEN=2").unwrap();
+        let hash = TaskHash::from_task(&task, &lock_file)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(cache.hash, hash.computation_hash());
+        assert_eq!(
+            cache.explain(&hash),
+            vec![CacheMissReason::ActivationScriptsChanged]
+        );
+    }
 }
@@ -413,6 +413,10 @@ impl InstallBuilder {
         self.args.lock_file_usage.frozen = true;
         self
     }
+    pub fn with_from_bundle(mut self, bundle_dir: impl Into<PathBuf>) -> Self {
+        self.args.from_bundle = Some(bundle_dir.into());
+        self
+    }
 }
 
 impl IntoFuture for InstallBuilder {
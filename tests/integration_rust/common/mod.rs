@@ -342,6 +342,7 @@ impl PixiControl {
                     revalidate: false,
                 },
                 editable: false,
+                prefer_conda: false,
             },
         }
     }
@@ -528,6 +529,9 @@ impl PixiControl {
                 },
                 config: Default::default(),
                 all: false,
+                from_bundle: None,
+                platform: None,
+                prefix: None,
             },
         }
     }
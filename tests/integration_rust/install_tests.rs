@@ -5,7 +5,7 @@ use crate::common::{
 use crate::common::{LockFileExt, PixiControl};
 use fs_err::tokio as tokio_fs;
 use pixi::cli::cli_config::{PrefixUpdateConfig, ProjectConfig};
-use pixi::cli::{run, run::Args, LockFileUsageArgs};
+use pixi::cli::{lock::fetch, run, run::Args, LockFileUsageArgs};
 use pixi::environment::LockFileUsage;
 use pixi::lock_file::UpdateMode;
 use pixi::{Project, UpdateLockFileOptions};
@@ -761,3 +761,129 @@ async fn test_ensure_gitignore_file_creation() {
         ".pixi/.gitignore file does not contain the expected content"
     );
 }
+
+/// Writes the configuration of the project, the configuration is read when
+/// the project is loaded.
+fn write_project_config(pixi: &PixiControl, config: &Config) {
+    let config_path = pixi.project().unwrap().pixi_dir().join("config.toml");
+    fs_err::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs_err::write(config_path, toml_edit::ser::to_string(config).unwrap()).unwrap();
+}
+
+/// Test that in offline mode the repodata is read from the cache, and that
+/// the channel is named in the error when it is not cached.
+#[tokio::test]
+async fn test_offline_solve() {
+    let package_database =
+        PackageDatabase::default().with_package(Package::build("foo", "1").finish());
+
+    // Serve the channel over http, the name of the channel directory is unique
+    // so the repodata is never cached by another run.
+    let server_dir = TempDir::new().unwrap();
+    let channel_dir = TempDir::new_in(server_dir.path()).unwrap();
+    package_database
+        .write_repodata(channel_dir.path())
+        .await
+        .unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let channel_url = format!(
+        "http://{}/{}",
+        listener.local_addr().unwrap(),
+        channel_dir.path().file_name().unwrap().to_str().unwrap()
+    );
+    let server = tokio::spawn(pixi::cli::channel::serve::serve(
+        listener,
+        server_dir.path().to_path_buf(),
+    ));
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init().with_channel(&channel_url).await.unwrap();
+    pixi.add("foo").without_lockfile_update().await.unwrap();
+
+    // The cache is cold, so solving offline fails.
+    let offline = Config {
+        offline: Some(true),
+        ..Config::default()
+    };
+    write_project_config(&pixi, &offline);
+    let err = pixi.update_lock_file().await.unwrap_err();
+    let message = err
+        .chain()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(message.contains("offline mode"), "{message}");
+    assert!(message.contains(&channel_url), "{message}");
+
+    // Solving online fills the cache.
+    write_project_config(&pixi, &Config::default());
+    pixi.update_lock_file().await.unwrap();
+
+    // Without the server the repodata can only come from the cache.
+    server.abort();
+    let _ = server.await;
+    write_project_config(&pixi, &offline);
+    fs_err::remove_file(pixi.project_path().join(consts::PROJECT_LOCK_FILE)).unwrap();
+    let lock = pixi.update_lock_file().await.unwrap();
+    assert!(lock.contains_match_spec(
+        consts::DEFAULT_ENVIRONMENT_NAME,
+        Platform::current(),
+        "foo ==1"
+    ));
+}
+
+/// Test that the artifacts of the lock file are fetched from a local channel
+/// into a bundle, and that the environment can be installed from the bundle
+/// after the channel is gone.
+#[tokio::test]
+async fn test_install_from_bundle() {
+    // Copy the channel, so it can be removed before installing.
+    let source_channel =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/channels/channels/dummy_channel_1");
+    let channel_dir = TempDir::new().unwrap();
+    for subdir in fs_err::read_dir(&source_channel).unwrap() {
+        let subdir = subdir.unwrap().path();
+        let target = channel_dir.path().join(subdir.file_name().unwrap());
+        fs_err::create_dir(&target).unwrap();
+        for file in fs_err::read_dir(&subdir).unwrap() {
+            let file = file.unwrap().path();
+            fs_err::copy(&file, target.join(file.file_name().unwrap())).unwrap();
+        }
+    }
+
+    let pixi = PixiControl::new().unwrap();
+    pixi.init()
+        .with_local_channel(channel_dir.path())
+        .await
+        .unwrap();
+    pixi.add("dummy-a").await.unwrap();
+
+    let bundle_dir = pixi.project_path().join("bundle");
+    fetch::execute(fetch::Args {
+        project_config: ProjectConfig {
+            manifest_path: Some(pixi.manifest_path()),
+        },
+        output: bundle_dir.clone(),
+        environment: vec![],
+        platform: vec![Platform::current()],
+    })
+    .await
+    .unwrap();
+
+    channel_dir.close().unwrap();
+    let mut install = pixi.install().with_from_bundle(bundle_dir);
+    install.args.config.offline = true;
+    install.await.unwrap();
+
+    let conda_meta = pixi.default_env_path().unwrap().join("conda-meta");
+    let installed = fs_err::read_dir(conda_meta)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    assert!(
+        installed
+            .iter()
+            .any(|name| name.starts_with("dummy-a-0.1.0-")),
+        "{installed:?}"
+    );
+}
@@ -726,6 +726,7 @@ async fn test_disabled_mapping() {
         project.pypi_name_mapping_source().unwrap(),
         &mut packages,
         None,
+        false,
     )
     .await
     .unwrap();